@@ -1,20 +1,34 @@
 use eframe::egui;
 use egui::{Color32, Vec2, Pos2, Stroke, FontId, Align2};
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use midir::{MidiInput, MidiInputConnection};
 use rfd::FileDialog;
-use rodio::{Decoder, OutputStream, Sink, Source};
+use rodio::dynamic_mixer::{self, DynamicMixerController};
+use rodio::queue::SourcesQueueOutput;
+use rodio::source::{SineWave, TakeDuration, Zero};
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
-use symphonia::core::formats::FormatOptions;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
-use symphonia::default::get_probe;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey};
+use symphonia::core::probe::{Hint, ProbeResult};
+use symphonia::core::units::Time;
+use symphonia::default::{get_codecs, get_probe};
 
 mod vec2_serde {
     use egui::Vec2;
@@ -38,7 +52,7 @@ mod vec2_serde {
 
 mod color32_serde {
     use egui::Color32;
-    use serde::{self, Deserialize, Deserializer, Serializer};
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
     pub fn serialize<S>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -54,441 +68,5747 @@ mod color32_serde {
         D: Deserializer<'de>,
     {
         let rgba = u32::deserialize(deserializer)?;
-        Ok(Color32::from_rgba_premultiplied(
+        Ok(Color32::from_rgba_unmultiplied(
             (rgba & 0xFF) as u8,
             ((rgba >> 8) & 0xFF) as u8,
             ((rgba >> 16) & 0xFF) as u8,
             ((rgba >> 24) & 0xFF) as u8,
         ))
     }
+
+    // The same encoding for optional colors
+    pub mod option {
+        use super::*;
+
+        #[derive(Serialize, Deserialize)]
+        struct Rgba(#[serde(with = "super")] Color32);
+
+        pub fn serialize<S>(color: &Option<Color32>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            color.map(Rgba).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Color32>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<Rgba>::deserialize(deserializer)?.map(|rgba| rgba.0))
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct MusicButton {
-    name: String,
-    path: PathBuf,
-    #[serde(with = "vec2_serde")]
-    position: Vec2,
-    #[serde(with = "color32_serde")]
-    color: Color32,
-    waveform: Vec<f32>,
-    duration: f32, // seconds
+// Stores a value as a JSON string inside the bincode board, so fields can be added to it later
+// (with #[serde(default)]) without changing the board layout. Human-readable formats (the JSON
+// export) get the value itself.
+mod json_blob {
+    use serde::de::DeserializeOwned;
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            return value.serialize(serializer);
+        }
+        let json = serde_json::to_string(value).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            return T::deserialize(deserializer);
+        }
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(serde::de::Error::custom)
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct MusicTab {
-    name: String,
-    buttons: Vec<MusicButton>,
+// Board layouts from earlier releases. bincode isn't self-describing, so `#[serde(default)]`
+// alone can't read files that lack a field; each released layout is mirrored here and converted.
+mod legacy {
+    use super::*;
+    use bincode::Options;
+
+    // Same encoding as bincode::serialize, but a layout mismatch must not pass by leaving bytes unread
+    fn strict() -> impl Options {
+        bincode::DefaultOptions::new().with_fixint_encoding().reject_trailing_bytes()
+    }
+
+    // Versioned boards start with their format version; boards from the first release, which
+    // had none, are recognised by their layout
+    pub fn decode_board(data: &[u8]) -> Result<MusicInterface, Box<dyn std::error::Error>> {
+        let decoded = strict()
+            .deserialize::<MusicInterface>(data)
+            .or_else(|err| strict().deserialize::<BoardV0>(data).map(BoardV0::upgrade).map_err(|_| err));
+        match decoded {
+            Ok(board) => {
+                check_version(board.version)?;
+                Ok(board)
+            }
+            // Nothing matched: if the leading version is one we don't know, say so rather than
+            // reporting a layout error
+            Err(err) => {
+                let version = data.get(..4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+                if let Some(version) = version {
+                    check_version(version)?;
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    pub fn check_version(version: u32) -> Result<(), String> {
+        if version > BOARD_VERSION {
+            return Err(format!(
+                "This board was saved by a newer Radio Conductor (format {}, this version reads up to {})",
+                version, BOARD_VERSION
+            ));
+        }
+        Ok(())
+    }
+
+    // The board as saved by the first release, field for field, session state included
+    #[derive(Serialize, Deserialize)]
+    pub struct BoardV0 {
+        pub tabs: Vec<TabV0>,
+        pub current_tab: usize,
+        pub edit_mode: bool,
+        pub current_playing: Option<(usize, usize)>,
+        pub edit_state: EditStateV0,
+        pub renaming_tab: Option<usize>,
+        pub tab_rename_buf: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct TabV0 {
+        pub name: String,
+        pub buttons: Vec<ButtonV0>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ButtonV0 {
+        pub name: String,
+        pub path: PathBuf,
+        #[serde(with = "vec2_serde")]
+        pub position: Vec2,
+        #[serde(with = "color32_serde")]
+        pub color: Color32,
+        pub waveform: Vec<f32>,
+        pub duration: f32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct EditStateV0 {
+        pub editing: Option<usize>,
+        pub name_buf: String,
+        #[serde(with = "color32_serde")]
+        pub color_buf: Color32,
+        pub pending_music_slot: Option<usize>,
+        pub pending_change_music: Option<usize>,
+    }
+
+    impl BoardV0 {
+        // The saved session state is dropped, as it is for current boards
+        fn upgrade(self) -> MusicInterface {
+            MusicInterface { tabs: self.tabs.into_iter().map(Into::into).collect(), ..Default::default() }
+        }
+    }
+
+    impl From<TabV0> for MusicTab {
+        fn from(tab: TabV0) -> Self {
+            let buttons: Vec<MusicButton> = tab
+                .buttons
+                .into_iter()
+                .map(|button| MusicButton {
+                    name: button.name,
+                    path: button.path,
+                    position: button.position,
+                    color: button.color,
+                    waveform: button.waveform,
+                    duration: button.duration,
+                    ..Default::default()
+                })
+                .collect();
+            // Tabs were one page, but overflowing ones keep their buttons on extra pages
+            let pages = buttons.len().div_ceil(GRID_SLOTS).max(1);
+            MusicTab { name: tab.name, buttons, pages, settings: TabSettings::default() }
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct EditState {
-    editing: Option<usize>, // index in tab.buttons
-    name_buf: String,
-    #[serde(with = "color32_serde")]
-    color_buf: Color32,
-    pending_music_slot: Option<usize>, // slot to add music to
-    pending_change_music: Option<usize>, // button index to change music
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+enum ToneKind {
+    #[default]
+    Sine,
+    PinkNoise,
 }
 
-#[derive(Serialize, Deserialize)]
-struct MusicInterface {
-    tabs: Vec<MusicTab>,
-    current_tab: usize,
-    #[serde(skip)]
-    audio_player: AudioPlayer,
-    edit_mode: bool,
-    current_playing: Option<(usize, usize)>, // (tab, index)
-    edit_state: EditState,
-    renaming_tab: Option<usize>, // index of tab being renamed
-    tab_rename_buf: String,      // buffer for renaming
+// Parameters of a generated test signal, used instead of a file by tone pads
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct TestTone {
+    kind: ToneKind,
+    frequency: f32, // Hz, ignored for noise
+    level_db: f32,  // dBFS
+    duration: f32,  // seconds
 }
 
-struct AudioPlayer {
-    sink: Option<Arc<Sink>>,
-    _stream: OutputStream,
-    _stream_handle: rodio::OutputStreamHandle,
-    is_fading: Arc<AtomicBool>,
-    start_time: Option<Instant>,
-    duration: f32,
+impl Default for TestTone {
+    fn default() -> Self {
+        Self { kind: ToneKind::Sine, frequency: 1000.0, level_db: -18.0, duration: 30.0 }
+    }
 }
 
-impl AudioPlayer {
-    fn new() -> Self {
-        let (_stream, _stream_handle) = OutputStream::try_default().unwrap();
-        Self {
-            sink: None,
-            _stream,
-            _stream_handle,
-            is_fading: Arc::new(AtomicBool::new(false)),
-            start_time: None,
-            duration: 0.0,
+impl TestTone {
+    fn gain(&self) -> f32 {
+        10f32.powf(self.level_db / 20.0)
+    }
+
+    fn label(&self) -> String {
+        match self.kind {
+            ToneKind::Sine => format!("Tone {:.0} Hz {:.0} dB", self.frequency, self.level_db),
+            ToneKind::PinkNoise => format!("Pink noise {:.0} dB", self.level_db),
         }
     }
+}
 
-    fn play(&mut self, path: &PathBuf, duration: f32) {
-        if let Some(current_sink) = &self.sink {
-            current_sink.stop();
+// Per-button parameters that shape playback. New ones belong here: the struct is stored as a
+// JSON blob, so a new field only needs a default and old boards keep loading.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+struct PlaybackSettings {
+    cooldown_ms: u32,         // minimum time between triggers, 0 = none
+    end_fade_ms: Option<u32>, // fade over the end of the file; None = use the app default
+    random_start: bool,       // start each trigger from a random point in the track
+    volume: f32,              // linear gain, 1.0 = as recorded
+    loop_enabled: bool,       // repeat the file until stopped
+    speed: f32,               // playback rate, 1.0 = as recorded; pitch follows
+    fade_in_ms: u32,          // ramp up from silence on every start, 0 = off
+    midi_note: Option<u8>,    // note-on that fires the button, on any channel
+    start_offset: f32,        // seconds skipped at the start of the file
+    end_offset: f32,          // seconds cut off the end of the file
+    #[serde(with = "color32_serde::option")]
+    waveform_color: Option<Color32>, // None = a faint shade of the button color
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            cooldown_ms: 0,
+            end_fade_ms: None,
+            random_start: false,
+            volume: 1.0,
+            loop_enabled: false,
+            speed: 1.0,
+            fade_in_ms: 0,
+            midi_note: None,
+            start_offset: 0.0,
+            end_offset: 0.0,
+            waveform_color: None,
         }
-        let file = BufReader::new(File::open(path).unwrap());
-        let sink = Arc::new(Sink::try_new(&self._stream_handle).unwrap());
-        let source = Decoder::new(file).unwrap();
-        sink.append(source);
-        self.sink = Some(sink);
-        self.start_time = Some(Instant::now());
-        self.duration = duration;
     }
+}
 
-    fn stop(&mut self) {
-        if let Some(sink) = &self.sink {
-            sink.stop();
+impl PlaybackSettings {
+    // (start, end) of the part of a `duration` long file that plays, in seconds. The whole
+    // file while its length isn't known yet; otherwise at least MIN_TRIMMED_SECS are kept.
+    fn trim_range(&self, duration: f32) -> (f32, f32) {
+        let duration = duration.max(0.0);
+        if duration == 0.0 {
+            return (0.0, 0.0);
         }
-        self.sink = None;
-        self.start_time = None;
+        let start = self.start_offset.max(0.0).min((duration - MIN_TRIMMED_SECS).max(0.0));
+        let end = (duration - self.end_offset.max(0.0)).max(start + MIN_TRIMMED_SECS).min(duration);
+        (start, end)
     }
 
-    fn fade_out(&mut self) {
-        if let Some(sink) = &self.sink {
-            let is_fading = self.is_fading.clone();
-            if !is_fading.load(Ordering::SeqCst) {
-                is_fading.store(true, Ordering::SeqCst);
-                let sink_clone = sink.clone();
-                thread::spawn(move || {
-                    let start = Instant::now();
-                    let duration = Duration::from_secs(1);
-                    while start.elapsed() < duration {
-                        let progress = start.elapsed().as_secs_f32() / duration.as_secs_f32();
-                        let volume = 1.0 - progress;
-                        sink_clone.set_volume(volume);
-                        thread::sleep(Duration::from_millis(16));
-                    }
-                    sink_clone.stop();
-                    is_fading.store(false, Ordering::SeqCst);
-                });
+    fn ui(&mut self, ui: &mut egui::Ui, default_end_fade_ms: u32) {
+        ui.add(egui::Slider::new(&mut self.volume, 0.0..=2.0).text("Volume"));
+        ui.add(egui::Slider::new(&mut self.speed, SPEED_RANGE).text("Speed").suffix("×"))
+            .on_hover_text("Plays faster or slower, changing the pitch with it. Ignored for test tones");
+        ui.horizontal(|ui| {
+            ui.label("Fade in:");
+            ui.add(egui::DragValue::new(&mut self.fade_in_ms).clamp_range(0..=10_000).speed(10).suffix(" ms"))
+                .on_hover_text("Eases the button in from silence on every start (0 = starts at full level)");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Cooldown:");
+            ui.add(egui::DragValue::new(&mut self.cooldown_ms).clamp_range(0..=10_000).speed(10).suffix(" ms"))
+                .on_hover_text("Minimum time between triggers of this button (0 = off)");
+        });
+        ui.horizontal(|ui| {
+            let mut custom = self.end_fade_ms.is_some();
+            if ui.checkbox(&mut custom, "End fade:").changed() {
+                self.end_fade_ms = custom.then_some(default_end_fade_ms);
             }
-        }
+            match &mut self.end_fade_ms {
+                Some(ms) => {
+                    ui.add(egui::DragValue::new(ms).clamp_range(0..=10_000).speed(10).suffix(" ms"));
+                }
+                None => {
+                    ui.weak(format!("default ({} ms)", default_end_fade_ms));
+                }
+            }
+        });
+        ui.checkbox(&mut self.random_start, "Start from a random position")
+            .on_hover_text("Useful for beds and long loops; ignored for test tones");
+        ui.checkbox(&mut self.loop_enabled, "Loop until stopped")
+            .on_hover_text("Repeats the file seamlessly; the end fade is skipped. Ignored for test tones");
     }
+}
 
-    fn elapsed(&self) -> f32 {
-        if let Some(start) = self.start_time {
-            start.elapsed().as_secs_f32()
-        } else {
-            0.0
+// What the file says about itself, read on import, and how often it was played. Stored as a JSON
+// blob like PlaybackSettings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+struct TrackInfo {
+    title: Option<String>,
+    artist: Option<String>,
+    tags: Vec<(String, String)>, // every tag as found in the file, for display
+    sample_rate: Option<u32>,    // Hz, of the default track
+    loudness_db: Option<f32>,    // see measure_loudness(); None until decoded by a version that measures it
+    artwork: Vec<u8>,            // PNG thumbnail of the file's cover art; empty when it has none
+    stats: PlayStats,            // not an edit: left out of fingerprint() and undo
+}
+
+impl TrackInfo {
+    // "Artist – Title", or just the title; None when the file has no title tag
+    fn display_name(&self) -> Option<String> {
+        let title = self.title.as_deref().map(str::trim).filter(|title| !title.is_empty())?;
+        match self.artist.as_deref().map(str::trim).filter(|artist| !artist.is_empty()) {
+            Some(artist) => Some(format!("{} – {}", artist, title)),
+            None => Some(title.to_string()),
         }
     }
 }
 
-impl Default for AudioPlayer {
-    fn default() -> Self {
-        Self::new()
+// How often and when a button was fired, for rotation tracking
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+struct PlayStats {
+    play_count: u32,
+    last_played: Option<SystemTime>,
+}
+
+impl PlayStats {
+    fn record_play(&mut self) {
+        self.play_count = self.play_count.saturating_add(1);
+        self.last_played = Some(SystemTime::now());
+    }
+
+    // "Played 3 times, last 2 h ago", or None for a button never fired
+    fn summary(&self, now: SystemTime) -> Option<String> {
+        let times = if self.play_count == 1 { "once".to_string() } else { format!("{} times", self.play_count) };
+        let ago = |last: SystemTime| match now.duration_since(last).map(|age| age.as_secs()).unwrap_or(0) {
+            secs if secs < 60 => "just now".to_string(),
+            secs if secs < 3600 => format!("{} min ago", secs / 60),
+            secs if secs < 86400 => format!("{} h ago", secs / 3600),
+            secs => format!("{} days ago", secs / 86400),
+        };
+        match (self.play_count, self.last_played) {
+            (0, _) => None,
+            (_, Some(last)) => Some(format!("Played {}, last {}", times, ago(last))),
+            (_, None) => Some(format!("Played {}", times)),
+        }
     }
 }
 
-impl Default for MusicInterface {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct MusicButton {
+    name: String,
+    path: PathBuf,
+    #[serde(with = "vec2_serde")]
+    position: Vec2,
+    #[serde(with = "color32_serde")]
+    color: Color32,
+    waveform: Vec<f32>,
+    duration: f32, // seconds
+    #[serde(default)]
+    waveform_min: Vec<f32>, // per-chunk minimum, paired with `waveform`; empty for peak envelopes
+    #[serde(default)]
+    start_latency_ms: f32, // measured delay between play() and the first sample reaching the output
+    #[serde(default)]
+    hotkey: Option<egui::Key>,
+    #[serde(default)]
+    tone: Option<TestTone>, // generated signal instead of `path`
+    #[serde(default, with = "json_blob")]
+    playback: PlaybackSettings,
+    #[serde(default, with = "json_blob")]
+    info: TrackInfo,
+    #[serde(skip)]
+    last_triggered: Option<Instant>,
+    #[serde(skip)]
+    play_error: Option<String>, // why the last trigger failed; cleared by the next successful one
+}
+
+impl Default for MusicButton {
     fn default() -> Self {
         Self {
-            tabs: vec![MusicTab { name: "Tab 1".to_string(), buttons: Vec::new() }],
-            current_tab: 0,
-            audio_player: AudioPlayer::new(),
-            edit_mode: false,
-            current_playing: None,
-            edit_state: EditState {
-                editing: None,
-                name_buf: String::new(),
-                color_buf: Color32::WHITE,
-                pending_music_slot: None,
-                pending_change_music: None,
-            },
-            renaming_tab: None,
-            tab_rename_buf: String::new(),
+            name: String::new(),
+            path: PathBuf::new(),
+            position: Vec2::ZERO,
+            color: Color32::from_rgb(100, 100, 255),
+            waveform: vec![],
+            waveform_min: vec![],
+            duration: 0.0,
+            start_latency_ms: 0.0,
+            hotkey: None,
+            tone: None,
+            playback: PlaybackSettings::default(),
+            info: TrackInfo::default(),
+            last_triggered: None,
+            play_error: None,
         }
     }
 }
 
-impl MusicInterface {
-    fn get_duration_with_symphonia(path: &PathBuf) -> Option<f32> {
-        let file = std::fs::File::open(path).ok()?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
-        let probed = get_probe().format(
-            &Hint::new(),
-            mss,
-            &FormatOptions::default(),
-            &MetadataOptions::default(),
-        ).ok()?;
-        let format = probed.format;
-        let track = format.default_track()?;
-        let codec_params = &track.codec_params;
-        let duration = codec_params.n_frames?;
-        let sample_rate = codec_params.sample_rate?;
-        Some(duration as f32 / sample_rate as f32)
+impl MusicButton {
+    // Playback rate actually used; test tones keep their own pitch
+    fn speed(&self) -> f32 {
+        if self.tone.is_some() || !self.playback.speed.is_finite() {
+            1.0
+        } else {
+            self.playback.speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end())
+        }
     }
 
-    fn generate_waveform_and_duration(path: &PathBuf) -> (Vec<f32>, f32) {
-        let file = BufReader::new(File::open(path).unwrap());
-        let decoder = Decoder::new(file).unwrap();
-        let samples: Vec<f32> = decoder
-            .convert_samples::<f32>()
-            .collect::<Vec<f32>>()
-            .chunks(1024)
-            .map(|chunk| chunk.iter().map(|s| s.abs()).max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap_or(0.0))
-            .collect();
-        let duration = Self::get_duration_with_symphonia(path).unwrap_or(0.0);
-        (samples, duration)
+    // Seconds the button plays for at its speed
+    fn play_length(&self) -> f32 {
+        self.trimmed_duration() / self.speed()
     }
 
-    fn add_music_at(&mut self, slot: usize) {
-        if let Some(path) = FileDialog::new()
-            .add_filter("Audio", &["mp3", "wav"])
-            .pick_file()
-        {
-            let name = path.file_name().unwrap().to_string_lossy().to_string();
-            let (waveform, duration) = Self::generate_waveform_and_duration(&path);
-            let tab = &mut self.tabs[self.current_tab];
-            if tab.buttons.len() <= slot {
-                tab.buttons.resize_with(slot + 1, || MusicButton {
-                    name: String::new(),
-                    path: PathBuf::new(),
-                    position: Vec2::ZERO,
-                    color: Color32::from_rgb(100, 100, 255),
-                    waveform: vec![],
-                    duration: 0.0,
-                });
-            }
-            tab.buttons[slot] = MusicButton {
-                name,
-                path,
-                position: Vec2::ZERO,
-                color: Color32::from_rgb(100, 100, 255),
-                waveform,
-                duration,
-            };
+    // (start, end) of the part of the file that plays, in seconds; tones always play whole
+    fn trim_range(&self) -> (f32, f32) {
+        match self.tone {
+            Some(_) => (0.0, self.duration.max(0.0)),
+            None => self.playback.trim_range(self.duration),
         }
     }
 
-    fn format_time(secs: f32) -> String {
-        let secs = secs.max(0.0) as u64;
-        let h = secs / 3600;
-        let m = (secs % 3600) / 60;
-        let s = secs % 60;
-        if h > 0 {
-            format!("{:02}:{:02}:{:02}", h, m, s)
-        } else {
-            format!("{:02}:{:02}", m, s)
+    fn trimmed_duration(&self) -> f32 {
+        let (start, end) = self.trim_range();
+        end - start
+    }
+
+    // Where the player starts in the file and for how long; None when it plays all of it
+    fn trim(&self) -> Option<(Duration, Duration)> {
+        let (start, end) = self.trim_range();
+        (start > 0.0 || end < self.duration).then(|| (Duration::from_secs_f32(start), Duration::from_secs_f32(end - start)))
+    }
+
+    // The trimmed part as fractions of the waveform
+    fn trim_span(&self) -> (f32, f32) {
+        let (start, end) = self.trim_range();
+        if self.duration > 0.0 { (start / self.duration, end / self.duration) } else { (0.0, 1.0) }
+    }
+
+    // Sets the volume that brings the measured loudness to `target_db`; false when the file
+    // hasn't been measured or is silent
+    fn normalize_to(&mut self, target_db: f32) -> bool {
+        let Some(loudness) = self.info.loudness_db.filter(|db| *db > LOUDNESS_FLOOR_DB) else {
+            return false;
+        };
+        self.playback.volume = 10f32.powf((target_db - loudness) / 20.0).clamp(0.0, NORMALIZE_MAX_GAIN);
+        true
+    }
+
+    // Whether the file is among `missing`, see MusicInterface::mark_missing_files()
+    fn is_missing(&self, missing: &HashSet<PathBuf>) -> bool {
+        !self.name.is_empty() && self.tone.is_none() && missing.contains(&self.path)
+    }
+
+    fn set_waveform(&mut self, waveform: Vec<f32>, waveform_min: Vec<f32>) {
+        self.waveform = waveform;
+        self.waveform_min = waveform_min;
+    }
+
+    // Factor that stretches the loudest point of the waveform to full height. Silence is left
+    // alone. The grid keeps it in MusicInterface::waveform_scales rather than scan every frame.
+    fn waveform_scale(&self) -> f32 {
+        let peak = self.waveform.iter().chain(&self.waveform_min).fold(0.0f32, |peak, v| peak.max(v.abs()));
+        if peak > 1e-4 { 1.0 / peak } else { 1.0 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct MusicTab {
+    name: String,
+    buttons: Vec<MusicButton>,
+    #[serde(default)]
+    pages: usize, // grid pages of GRID_SLOTS each; buttons never reach past them
+    #[serde(default, with = "json_blob")]
+    settings: TabSettings,
+}
+
+// Per-tab options, stored as a JSON blob like PlaybackSettings so new ones don't need a new
+// board layout
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(default)]
+struct TabSettings {
+    playlist_mode: bool, // when a cue ends, the next populated slot of the tab starts
+    #[serde(with = "color32_serde::option")]
+    color: Option<Color32>, // tints the tab label and the grid behind its buttons; None = no tint
+    fit_to_content: bool,   // show just the populated buttons and one free slot instead of fixed pages
+}
+
+// Offered in the tab label's context menu; muted enough to sit behind any button color
+const TAB_COLORS: &[(&str, Color32)] = &[
+    ("Red", Color32::from_rgb(200, 60, 60)),
+    ("Orange", Color32::from_rgb(210, 130, 40)),
+    ("Yellow", Color32::from_rgb(200, 180, 50)),
+    ("Green", Color32::from_rgb(60, 160, 80)),
+    ("Teal", Color32::from_rgb(40, 150, 150)),
+    ("Blue", Color32::from_rgb(60, 110, 200)),
+    ("Purple", Color32::from_rgb(140, 80, 190)),
+    ("Pink", Color32::from_rgb(200, 80, 150)),
+];
+
+impl MusicTab {
+    fn capacity(&self) -> usize {
+        self.pages.max(1) * GRID_SLOTS
+    }
+
+    // Make room for `slot`, adding pages when it lies past the last one so repair won't move
+    // the button back (fit-to-content tabs add past the fixed pages)
+    fn ensure_slot(&mut self, slot: usize) {
+        if self.buttons.len() <= slot {
+            self.buttons.resize_with(slot + 1, MusicButton::default);
         }
+        self.pages = self.pages.max(slot / GRID_SLOTS + 1);
     }
 
-    fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let data = bincode::serialize(self)?;
-        std::fs::write(path, data)?;
-        Ok(())
+    // Slots drawn by a fit-to-content tab: every populated button, then the first free slot
+    fn fitted_slots(&self) -> Vec<usize> {
+        let mut slots: Vec<usize> = (0..self.buttons.len()).filter(|&i| !self.buttons[i].name.is_empty()).collect();
+        let free = (0..).find(|&i| self.buttons.get(i).is_none_or(|button| button.name.is_empty())).unwrap_or(0);
+        slots.push(free);
+        slots
     }
 
-    fn load_from_file(&mut self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let data = std::fs::read(path)?;
-        let mut loaded: MusicInterface = bincode::deserialize(&data)?;
-        loaded.audio_player = AudioPlayer::new();
-        *self = loaded;
-        Ok(())
+    // (populated buttons, seconds of audio they hold at their playback speed)
+    fn runtime(&self) -> (usize, f32) {
+        self.buttons
+            .iter()
+            .filter(|button| !button.name.is_empty())
+            .fold((0, 0.0), |(count, total), button| (count + 1, total + button.play_length()))
     }
 }
 
-impl eframe::App for MusicInterface {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Save/Import buttons
-            ui.horizontal(|ui| {
-                if ui.button("Save").clicked() {
-                    if let Some(path) = FileDialog::new().set_file_name("radio_conductor_save.bin").save_file() {
-                        let _ = self.save_to_file(&path);
-                    }
-                }
-                if ui.button("Import").clicked() {
-                    if let Some(path) = FileDialog::new().pick_file() {
-                        let _ = self.load_from_file(&path);
-                    }
-                }
-            });
-            // Edit mode banner
-            if self.edit_mode {
-                ui.colored_label(
-                    Color32::from_rgb(255, 200, 0),
-                    egui::RichText::new("YOU ARE IN EDIT MODE: You can edit music buttons. Click 'Exit Edit Mode' to return to normal mode.")
-                        .strong()
-                        .size(20.0),
-                );
-                ui.add_space(8.0);
-            }
-            // Tabs
-            ui.horizontal(|ui| {
-                for (i, tab) in self.tabs.iter_mut().enumerate() {
-                    let tab_id = ui.make_persistent_id(("tab", i));
-                    if self.renaming_tab == Some(i) {
-                        let resp = ui.text_edit_singleline(&mut self.tab_rename_buf);
-                        let enter_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
-                        if (resp.lost_focus() && ui.input(|i| !i.pointer.any_pressed())) || resp.clicked_elsewhere() || enter_pressed {
-                            if !self.tab_rename_buf.trim().is_empty() {
-                                tab.name = self.tab_rename_buf.trim().to_string();
-                            }
-                            self.renaming_tab = None;
-                        }
-                    } else {
-                        let label = ui.selectable_label(self.current_tab == i, &tab.name);
-                        if label.clicked() {
-                            self.current_tab = i;
-                        }
-                        if label.double_clicked() {
-                            self.renaming_tab = Some(i);
-                            self.tab_rename_buf = tab.name.clone();
-                        }
-                    }
-                }
-                if ui.button("+").clicked() {
-                    let idx = self.tabs.len() + 1;
-                    self.tabs.push(MusicTab { name: format!("Tab {}", idx), buttons: Vec::new() });
-                    self.current_tab = self.tabs.len() - 1;
-                }
-            });
-            ui.separator();
-            // Edit mode toggle only
-            ui.horizontal(|ui| {
-                if ui.button(if self.edit_mode { "Exit Edit Mode" } else { "Enter Edit Mode" }).clicked() {
-                    self.edit_mode = !self.edit_mode;
-                }
-            });
-            ui.separator();
-            // Responsive grid with 20 slots
-            let tab = &mut self.tabs[self.current_tab];
+const FADE_KEY: egui::Key = egui::Key::F; // fades out the current cue, see AppConfig::fade_key_enabled
+const UNDO_DEPTH: usize = 50; // edits kept for undo; the oldest go first
+
+// What a button got from decoding its file, stored once per file for all undo steps
+#[derive(PartialEq)]
+struct ButtonMedia {
+    waveform: Vec<f32>,
+    waveform_min: Vec<f32>,
+    artwork: Vec<u8>,
+}
+
+// The tabs as they were before an edit. Buttons with a file have their media stripped; it is
+// looked up in UndoHistory::media by path on restore.
+struct Snapshot {
+    action: &'static str, // shown as "Undo <action>"
+    tabs: Vec<MusicTab>,
+    current_tab: usize,
+}
+
+#[derive(Default)]
+struct UndoHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    media: HashMap<PathBuf, ButtonMedia>,
+}
+
+impl UndoHistory {
+    // A new edit: the state before it becomes the next undo step and the redo steps are dropped
+    fn record(&mut self, action: &'static str, tabs: &mut [MusicTab], current_tab: usize) {
+        let snapshot = self.capture(action, tabs, current_tab);
+        self.undo.push(snapshot);
+        if self.undo.len() > UNDO_DEPTH {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.prune_media();
+    }
+
+    fn capture(&mut self, action: &'static str, tabs: &mut [MusicTab], current_tab: usize) -> Snapshot {
+        let tabs = tabs
+            .iter_mut()
+            .map(|tab| MusicTab {
+                name: tab.name.clone(),
+                buttons: tab.buttons.iter_mut().map(|button| self.strip(button)).collect(),
+                pages: tab.pages,
+                settings: tab.settings,
+            })
+            .collect();
+        Snapshot { action, tabs, current_tab }
+    }
+
+    // Copy of the button without its media, which goes to the store instead (unless it is
+    // already there). Test tones keep their small generated waveform.
+    fn strip(&mut self, button: &mut MusicButton) -> MusicButton {
+        if button.path.as_os_str().is_empty() {
+            return button.clone();
+        }
+        let media = ButtonMedia {
+            waveform: std::mem::take(&mut button.waveform),
+            waveform_min: std::mem::take(&mut button.waveform_min),
+            artwork: std::mem::take(&mut button.info.artwork),
+        };
+        let copy = button.clone();
+        // Still decoding: there is nothing to keep, restore() asks for it again
+        if !media.waveform.is_empty() && self.media.get(&button.path) != Some(&media) {
+            self.media.insert(button.path.clone(), ButtonMedia { waveform: media.waveform.clone(), waveform_min: media.waveform_min.clone(), artwork: media.artwork.clone() });
+        }
+        button.waveform = media.waveform;
+        button.waveform_min = media.waveform_min;
+        button.info.artwork = media.artwork;
+        copy
+    }
+
+    // The snapshot's tabs with their media put back. Buttons that were still decoding come
+    // back without a waveform.
+    fn restore(&self, snapshot: Snapshot) -> Vec<MusicTab> {
+        let mut tabs = snapshot.tabs;
+        for button in tabs.iter_mut().flat_map(|tab| &mut tab.buttons) {
+            if let Some(media) = self.media.get(&button.path) {
+                button.set_waveform(media.waveform.clone(), media.waveform_min.clone());
+                button.info.artwork = media.artwork.clone();
+            }
+        }
+        tabs
+    }
+
+    // Forget the media of files no undo or redo step refers to any more
+    fn prune_media(&mut self) {
+        let used: HashSet<&PathBuf> =
+            self.undo.iter().chain(&self.redo).flat_map(|snapshot| &snapshot.tabs).flat_map(|tab| &tab.buttons).map(|button| &button.path).collect();
+        self.media.retain(|path, _| used.contains(path));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct EditState {
+    editing: Option<usize>, // index in tab.buttons
+    name_buf: String,
+    #[serde(with = "color32_serde")]
+    color_buf: Color32,
+    pending_music_slot: Option<usize>, // slot to add music to
+    pending_change_music: Option<usize>, // button index to change music
+    #[serde(skip)]
+    hotkey_buf: Option<egui::Key>,
+    #[serde(skip)]
+    capturing_hotkey: bool,
+    #[serde(skip)]
+    learning_midi: bool, // the next MIDI note goes to playback_buf.midi_note
+    #[serde(skip)]
+    pending_hotkey: Option<egui::Key>, // captured key awaiting the conflict check
+    #[serde(skip)]
+    playback_buf: PlaybackSettings,
+    #[serde(skip)]
+    tone_buf: Option<TestTone>,
+    #[serde(skip)]
+    pending_tone_slot: Option<usize>, // slot to add a test tone to
+    #[serde(skip)]
+    pending_paste_slot: Option<usize>, // slot to paste the clipboard into
+    #[serde(skip)]
+    pending_replace: Option<((usize, usize), PathBuf)>, // new file for a slot in use, awaiting confirmation
+    #[serde(skip)]
+    pending_relink: Option<usize>, // button index whose missing file is being looked for
+    #[serde(skip)]
+    confirm_delete: bool, // Delete was clicked once; the next click clears the slot
+}
+
+// Format version written at the start of every board. Bump it whenever a release changes the
+// serialized layout, and keep the previous layout in `legacy` so old files still load. The
+// first release wrote no version at all.
+const BOARD_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct MusicInterface {
+    #[serde(default)]
+    version: u32, // format the board was saved in; 0 for JSON exports from before versioning
+    tabs: Vec<MusicTab>,
+    // Session state from here on: a loaded board always opens on its first tab, out of edit mode
+    #[serde(skip)]
+    current_tab: usize,
+    #[serde(skip)]
+    audio_player: AudioPlayer,
+    #[serde(skip)]
+    edit_mode: bool,
+    #[serde(skip)]
+    current_playing: Option<(usize, usize)>, // (tab, index)
+    #[serde(skip)]
+    edit_state: EditState,
+    #[serde(skip)]
+    renaming_tab: Option<usize>, // index of tab being renamed
+    #[serde(skip)]
+    tab_rename_buf: String, // buffer for renaming
+    #[serde(skip)]
+    show_mixer: bool,
+    #[serde(skip)]
+    show_settings: bool,
+    #[serde(skip)]
+    config: AppConfig,
+    #[serde(skip)]
+    toasts: Vec<Toast>,
+    #[serde(skip)]
+    queued_triggers: Vec<(usize, usize)>, // triggers held back until their cooldown ends
+    #[serde(skip)]
+    ipc_server: Option<IpcServer>,
+    #[serde(skip)]
+    web_remote: Option<WebRemote>,
+    #[serde(skip)]
+    master_muted: bool, // not persisted, see AppConfig::master_volume
+    #[serde(skip)]
+    current_page: usize, // grid page shown for the current tab
+    #[serde(skip)]
+    raise_hotkey: Option<RaiseHotkey>,
+    #[serde(skip)]
+    pending_waveforms: HashMap<(usize, usize), PathBuf>, // (tab, index) -> file being decoded
+    #[serde(skip)]
+    pending_normalize: HashSet<(usize, usize)>, // levelled once their decode in pending_waveforms has measured the file
+    #[serde(skip)]
+    missing_paths: HashSet<PathBuf>, // files buttons point at that weren't there on load; see mark_missing_files()
+    #[serde(skip)]
+    waveform_progress: HashMap<PathBuf, f32>, // share of a pending file decoded so far, when its length is known
+    #[serde(skip)]
+    waveform_jobs: WaveformChannel,
+    #[serde(skip)]
+    confirm_remove_tab: Option<usize>, // tab whose × was clicked once while it still had buttons
+    #[serde(skip)]
+    last_autosave: Option<Instant>,
+    #[serde(skip)]
+    saved_fingerprint: u64, // fingerprint() of the tabs as last written by autosave
+    #[serde(skip)]
+    file_fingerprint: u64, // fingerprint() of the tabs as last saved to or opened from a board file
+    #[serde(skip)]
+    confirm_exit: bool, // the window was asked to close with unsaved changes
+    #[serde(skip)]
+    exit_confirmed: bool, // let the next close request through
+    #[serde(skip)]
+    offer_restore: bool, // an autosave newer than the last saved board was found at startup
+    #[serde(skip)]
+    load_error: Option<String>, // shown in a dialog until dismissed
+    #[serde(skip)]
+    previous_board: Option<Vec<u8>>, // the board replaced by the last load, bincode-encoded
+    #[serde(skip)]
+    dragging_tab: Option<usize>, // tab label being dragged along the strip
+    #[serde(skip)]
+    dragging_button: Option<usize>, // index in the current tab of the button being dragged in edit mode
+    #[serde(skip)]
+    artwork_textures: HashMap<PathBuf, Option<egui::TextureHandle>>, // uploaded on first draw; None if undecodable
+    #[serde(skip)]
+    waveform_scales: HashMap<PathBuf, f32>, // MusicButton::waveform_scale() per file, dropped when it is decoded again
+    #[serde(skip)]
+    output_devices: Option<Vec<String>>, // listed when the settings window opens
+    #[serde(skip)]
+    midi_ports: Option<Vec<String>>, // likewise
+    #[serde(skip)]
+    midi_input: Option<MidiListener>,
+    #[serde(skip)]
+    last_output_reopen: Option<Instant>,
+    #[serde(skip)]
+    meter: f32, // output level shown in the toolbar, falling back between peaks
+    #[serde(skip)]
+    search_query: String,
+    #[serde(skip)]
+    flash: Option<((usize, usize), Instant)>, // (tab, index) of the slot the search jumped to
+    #[serde(skip)]
+    clipboard: Option<MusicButton>, // copied from the edit popup, pasted into empty slots
+    #[serde(skip)]
+    history: UndoHistory,
+    #[serde(skip)]
+    tab_drag_recorded: bool, // the tab being dragged has already recorded its undo step
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Hash)]
+enum WaveformMode {
+    #[default]
+    MinMax, // signed min/max pair per chunk, drawn around the centre line
+    Peak,   // max absolute sample per chunk, drawn from the bottom
+    Rms,    // root mean square per chunk, drawn from the bottom; follows loudness, not transients
+}
+
+// What to do when a hotkey is assigned to (or already shared by) more than one button
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum HotkeyConflict {
+    #[default]
+    Reject, // refuse conflicting assignments; clashes in loaded boards fire like PreferCurrentTab
+    TriggerAll, // fire every match as a layer; needs polyphonic mode, see conflict_targets()
+    PreferCurrentTab, // the match on the current tab, otherwise the first match
+}
+
+// How the playing cue is left when another one is triggered
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum Transition {
+    #[default]
+    FadeOut,   // the old cue fades out on its own sink while the new one starts
+    HardCut,   // stop everything, including cues still fading, before starting
+    Crossfade, // the old cue fades out while the new one fades in over the same window
+}
+
+// Shape of every volume ramp: manual fades, end fades, crossfades and fade-ins
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+enum FadeCurve {
+    #[default]
+    Linear,
+    Log,        // even steps in dB down to FADE_LOG_RANGE_DB, so the ear hears a steady fade
+    EqualPower, // sine law: a crossfade keeps the combined power, with no dip halfway
+}
+
+const FADE_LOG_RANGE_DB: f32 = 40.0;
+
+impl FadeCurve {
+    // Gain at `level` of the way up the ramp (0 = silent end, 1 = full level); a fade-out runs
+    // it from 1 down to 0
+    fn gain(self, level: f32) -> f32 {
+        let level = if level.is_nan() { 1.0 } else { level.clamp(0.0, 1.0) };
+        match self {
+            FadeCurve::Linear => level,
+            FadeCurve::Log if level == 0.0 => 0.0,
+            FadeCurve::Log => 10f32.powf((level - 1.0) * FADE_LOG_RANGE_DB / 20.0),
+            FadeCurve::EqualPower => (level * std::f32::consts::FRAC_PI_2).sin(),
+        }
+    }
+}
+
+// What happens to a trigger that arrives while its button is cooling down
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum CooldownAction {
+    #[default]
+    Ignore,
+    Queue, // fire once when the cooldown ends
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum TabBarPosition {
+    #[default]
+    Top,
+    Left, // vertical sidebar
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum HoverStyle {
+    Off,
+    #[default]
+    Border,
+    Brighten,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+enum StartupBoard {
+    Empty,
+    #[default]
+    LastFile,
+    Fixed(PathBuf),
+}
+
+// What happens to the edit popup when the user clicks elsewhere
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum EditDismiss {
+    #[default]
+    Modal, // the rest of the window is blocked until Save/Cancel
+    ClickAwayCancel,
+    ClickAwaySave,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum WaveformStyle {
+    #[default]
+    Filled, // smooth filled shape
+    Lines,  // one vertical line per pixel column
+}
+
+// Application settings, stored as JSON in the OS config dir (not in the board file)
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct AppConfig {
+    startup_board: StartupBoard,
+    last_file: Option<PathBuf>,
+    recent_files: Vec<PathBuf>, // last saved/imported boards, newest first, at most RECENT_FILES
+    waveform_mode: WaveformMode,
+    waveform_points: usize, // detail of new waveforms, see waveform_points()
+    waveform_height: f32, // share of the pad height the waveform may use
+    waveform_style: WaveformStyle,
+    hotkey_conflict: HotkeyConflict,
+    quickfire_enabled: bool, // 1-9 and 0 fire the first ten slots of the visible page
+    hover_style: HoverStyle,
+    transition: Transition,
+    crossfade_ms: u32,
+    preload_sinks: bool,
+    tab_bar: TabBarPosition,
+    cooldown_action: CooldownAction,
+    end_fade_ms: u32, // default end fade for buttons without their own, 0 = off
+    edit_dismiss: EditDismiss,
+    // Live-mix policy: the master level is a property of the studio, so it lives here and
+    // survives restarts and board loads. Mutes are momentary and always start cleared.
+    master_volume: f32,
+    ipc_enabled: bool,
+    ipc_address: String,
+    raise_hotkey_enabled: bool,
+    raise_hotkey: String, // e.g. "Ctrl+Alt+R", in global-hotkey's format
+    autosave_secs: u32,   // interval between autosaves of a changed board, 0 = off
+    dark_mode: bool,
+    output_device: Option<String>, // None = system default
+    prelisten_device: Option<String>, // Alt+click plays a pad here instead of on air; None = off
+    normalize_waveform: bool, // stretch each waveform to the pad height instead of showing true levels
+    polyphonic: bool,         // buttons play on top of each other instead of replacing the current cue
+    mono_output: bool,        // sum the output to mono, for checking mono compatibility
+    warn_remaining_secs: u32, // remaining time is shown in warning colors below this, 0 = off
+    fade_key_enabled: bool,   // FADE_KEY fades out the current cue
+    midi_enabled: bool,
+    midi_port: Option<String>, // None = the first input port found
+    web_enabled: bool,
+    web_address: String, // this machine only by default; 0.0.0.0:<port> opens it to the network
+    loudness_target_db: f32, // level "Normalize levels" brings buttons to, see measure_loudness()
+    fade_curve: FadeCurve,
+    name_font_min: f32,      // bounds of the pad name size, which otherwise follows the pad size
+    name_font_max: f32,
+    ui_scale: f32, // zoom of the whole interface on top of the OS scaling
+    max_pad_aspect: f32, // widest a pad may get, as width / height; 0 = stretch to the window
+    snap_to_zero: bool,  // start seeks and trims at a zero crossing near the asked position
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            startup_board: StartupBoard::default(),
+            last_file: None,
+            recent_files: Vec::new(),
+            waveform_mode: WaveformMode::default(),
+            waveform_points: WAVEFORM_POINTS,
+            waveform_height: 0.8,
+            waveform_style: WaveformStyle::default(),
+            hotkey_conflict: HotkeyConflict::default(),
+            quickfire_enabled: false,
+            hover_style: HoverStyle::default(),
+            transition: Transition::default(),
+            crossfade_ms: 2000,
+            preload_sinks: false,
+            tab_bar: TabBarPosition::default(),
+            cooldown_action: CooldownAction::default(),
+            end_fade_ms: 0,
+            edit_dismiss: EditDismiss::default(),
+            master_volume: 1.0,
+            ipc_enabled: false,
+            ipc_address: "127.0.0.1:7878".to_string(),
+            raise_hotkey_enabled: false,
+            raise_hotkey: "Ctrl+Alt+R".to_string(),
+            autosave_secs: 60,
+            dark_mode: true,
+            output_device: None,
+            prelisten_device: None,
+            normalize_waveform: true,
+            polyphonic: false,
+            mono_output: false,
+            warn_remaining_secs: 10,
+            fade_key_enabled: true,
+            midi_enabled: false,
+            midi_port: None,
+            web_enabled: false,
+            web_address: "127.0.0.1:8080".to_string(),
+            loudness_target_db: -16.0,
+            fade_curve: FadeCurve::default(),
+            name_font_min: 12.0,
+            name_font_max: 32.0,
+            ui_scale: 1.0,
+            max_pad_aspect: 0.0,
+            snap_to_zero: false,
+        }
+    }
+}
+
+const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+const RECENT_FILES: usize = 8;
+const NAME_FONT_RANGE: std::ops::RangeInclusive<f32> = 8.0..=72.0;
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=2.0;
+const PAD_ASPECT_RANGE: std::ops::RangeInclusive<f32> = 1.0..=6.0;
+const MIN_TRIMMED_SECS: f32 = 0.1; // trim handles never close up further than this
+const LOUDNESS_FLOOR_DB: f32 = -70.0; // blocks quieter than this count as silence
+const LOUDNESS_TARGET_RANGE: std::ops::RangeInclusive<f32> = -30.0..=-6.0;
+const NORMALIZE_MAX_GAIN: f32 = 2.0; // top of the volume slider; quieter files stay a bit quiet
+const WAVEFORM_HEIGHT_RANGE: std::ops::RangeInclusive<f32> = 0.1..=1.0;
+const WAVEFORM_POINTS_RANGE: std::ops::RangeInclusive<usize> = 250..=8000;
+
+impl AppConfig {
+    // Guards against hand-edited configs; above 1.0 the waveform would spill out of the pad
+    fn waveform_height(&self) -> f32 {
+        if self.waveform_height.is_finite() {
+            self.waveform_height.clamp(*WAVEFORM_HEIGHT_RANGE.start(), *WAVEFORM_HEIGHT_RANGE.end())
+        } else {
+            0.8
+        }
+    }
+
+    // Every point is kept in the board, the undo history and the cache, two floats each in
+    // min/max mode, so a hand-edited config can't ask for more than the slider offers
+    fn waveform_points(&self) -> usize {
+        self.waveform_points.clamp(*WAVEFORM_POINTS_RANGE.start(), *WAVEFORM_POINTS_RANGE.end())
+    }
+
+    // Pad name size: in proportion to the pad (22 pt on a 150 pt high pad), held within the
+    // configured bounds; the time label is drawn a bit smaller than this
+    fn name_font_size(&self, pad: Vec2) -> f32 {
+        let clamp = |size: f32| if size.is_finite() { size.clamp(*NAME_FONT_RANGE.start(), *NAME_FONT_RANGE.end()) } else { 12.0 };
+        let min = clamp(self.name_font_min);
+        let max = clamp(self.name_font_max).max(min);
+        (pad.y / 6.8).min(pad.x / 10.0).clamp(min, max)
+    }
+
+    // None when pads may take any shape
+    fn max_pad_aspect(&self) -> Option<f32> {
+        (self.max_pad_aspect.is_finite() && self.max_pad_aspect > 0.0)
+            .then(|| self.max_pad_aspect.clamp(*PAD_ASPECT_RANGE.start(), *PAD_ASPECT_RANGE.end()))
+    }
+
+    fn ui_scale(&self) -> f32 {
+        if self.ui_scale.is_finite() { self.ui_scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end()) } else { 1.0 }
+    }
+
+    // The OS scaling times ui_scale; takes effect from the next frame
+    fn apply_ui_scale(&self, ctx: &egui::Context) {
+        ctx.set_pixels_per_point(ctx.native_pixels_per_point().unwrap_or(1.0) * self.ui_scale());
+    }
+
+    fn visuals(&self) -> egui::Visuals {
+        if self.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() }
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("radio-conductor").join("config.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    // Moves `path` to the top of the recent list, dropping the oldest past RECENT_FILES
+    fn push_recent(&mut self, path: &Path) {
+        self.recent_files.retain(|recent| recent != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(RECENT_FILES);
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("no config directory available")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+// A command from outside the UI (control socket), applied on the UI thread
+#[derive(Debug, PartialEq)]
+enum RemoteCommand {
+    Play(usize, usize), // (tab, index), zero-based
+    Stop,
+    Fade,
+    Load(PathBuf),
+}
+
+impl RemoteCommand {
+    // Parses one protocol line; tab and slot numbers are 1-based as shown in the UI
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (verb, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let number = |s: Option<&str>, what: &str| -> Result<usize, String> {
+            s.and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n >= 1)
+                .map(|n| n - 1)
+                .ok_or_else(|| format!("expected a {} number starting at 1", what))
+        };
+        match verb.to_ascii_lowercase().as_str() {
+            "play" => {
+                let mut args = rest.split_whitespace();
+                let tab = number(args.next(), "tab")?;
+                let slot = number(args.next(), "slot")?;
+                Ok(RemoteCommand::Play(tab, slot))
+            }
+            "stop" => Ok(RemoteCommand::Stop),
+            "fade" => Ok(RemoteCommand::Fade),
+            "load" if !rest.is_empty() => Ok(RemoteCommand::Load(PathBuf::from(rest))),
+            "load" => Err("expected a file path".to_string()),
+            _ => Err(format!("unknown command \"{}\"", verb)),
+        }
+    }
+}
+
+// Line-based TCP control socket; each connection gets its own thread and replies OK/ERR per line
+struct IpcServer {
+    address: String,
+    running: Arc<AtomicBool>,
+    commands: Receiver<RemoteCommand>,
+}
+
+impl IpcServer {
+    fn start(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let running = Arc::new(AtomicBool::new(true));
+        let (sender, commands) = mpsc::channel();
+        let accept_running = running.clone();
+        thread::spawn(move || {
+            while accept_running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let sender = sender.clone();
+                        thread::spawn(move || Self::serve(stream, sender));
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(50)),
+                    Err(_) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        });
+        Ok(Self { address: address.to_string(), running, commands })
+    }
+
+    fn serve(stream: TcpStream, sender: Sender<RemoteCommand>) {
+        let _ = stream.set_nonblocking(false);
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if writeln!(writer, "{}", Self::reply(&line, &sender)).is_err() {
+                break;
+            }
+        }
+    }
+
+    // Queues the command on `line` for update() and says whether it was understood
+    fn reply(line: &str, sender: &Sender<RemoteCommand>) -> String {
+        match RemoteCommand::parse(line) {
+            Ok(command) => match sender.send(command) {
+                Ok(()) => "OK".to_string(),
+                Err(_) => "ERR server stopped".to_string(),
+            },
+            Err(err) => format!("ERR {}", err),
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+// Page served by WebRemote: polls /state and sends commands over the WebSocket
+const REMOTE_PAGE: &str = r#"<!doctype html>
+<html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Radio Conductor</title>
+<style>
+body { font-family: sans-serif; background: #1b1b1b; color: #eee; margin: 8px; }
+h2 { font-size: 1em; margin: 12px 0 4px; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(110px, 1fr)); gap: 6px; }
+button { padding: 14px 6px; border: 0; border-radius: 8px; color: #fff; font-size: 0.9em; text-shadow: 0 1px 2px #000; }
+.playing { outline: 3px solid #ffd000; }
+#bar button { background: #b41e1e; margin-right: 6px; }
+#status { color: #f66; }
+</style></head><body>
+<div id="bar"><button onclick="send('stop')">&#9209; Stop All</button><button onclick="send('fade')">Fade</button><span id="status"></span></div>
+<div id="board"></div>
+<script>
+let socket;
+function connect() {
+  socket = new WebSocket(`ws://${location.host}/ws`);
+  socket.onopen = () => document.getElementById('status').textContent = '';
+  socket.onmessage = (e) => { if (e.data !== 'OK') document.getElementById('status').textContent = e.data; };
+  socket.onclose = () => { document.getElementById('status').textContent = 'Disconnected'; setTimeout(connect, 2000); };
+}
+function send(line) { if (socket.readyState === WebSocket.OPEN) socket.send(line); }
+async function refresh() {
+  try {
+    const state = await (await fetch('/state')).json();
+    const board = document.getElementById('board');
+    board.replaceChildren();
+    for (const tab of state.tabs) {
+      const title = document.createElement('h2');
+      title.textContent = tab.name;
+      const grid = document.createElement('div');
+      grid.className = 'grid';
+      for (const b of tab.buttons) {
+        const button = document.createElement('button');
+        button.textContent = b.name;
+        button.style.background = b.color;
+        if (b.playing) button.className = 'playing';
+        button.onclick = () => send(`play ${tab.number} ${b.slot}`);
+        grid.append(button);
+      }
+      board.append(title, grid);
+    }
+  } catch (e) {}
+}
+connect();
+refresh();
+setInterval(refresh, 1000);
+</script></body></html>
+"#;
+
+// Page served by WebRemote at /overlay for use as an OBS browser source: the current cue's
+// name and progress on a transparent background. It polls /now-playing and moves the bar on
+// between polls itself.
+const OVERLAY_PAGE: &str = r#"<!doctype html>
+<html><head><meta charset="utf-8"><title>Now playing</title>
+<style>
+body { margin: 0; background: transparent; font-family: sans-serif; color: #fff; text-shadow: 0 1px 3px #000; }
+#box { padding: 12px 16px; opacity: 0; transition: opacity 0.4s; }
+#box.on { opacity: 1; }
+#line { display: flex; justify-content: space-between; gap: 16px; font-size: 28px; }
+#name { white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+#track { height: 8px; margin-top: 8px; border-radius: 4px; background: rgba(255, 255, 255, 0.25); overflow: hidden; }
+#fill { height: 100%; width: 0; background: #e33; }
+</style></head><body>
+<div id="box"><div id="line"><span id="name"></span><span id="time"></span></div><div id="track"><div id="fill"></div></div></div>
+<script>
+let cue = null, at = 0;
+async function poll() {
+  try {
+    cue = await (await fetch('/now-playing')).json();
+    at = performance.now();
+  } catch (e) {
+    cue = null;
+  }
+}
+function clock(secs) {
+  secs = Math.max(0, Math.ceil(secs));
+  return `${Math.floor(secs / 60)}:${String(secs % 60).padStart(2, '0')}`;
+}
+function draw() {
+  const box = document.getElementById('box');
+  box.className = cue && cue.playing ? 'on' : '';
+  if (cue && cue.playing) {
+    let elapsed = cue.elapsed + (cue.paused ? 0 : (performance.now() - at) / 1000);
+    if (cue.looping && cue.duration > 0) elapsed %= cue.duration;
+    elapsed = Math.min(elapsed, cue.duration);
+    document.getElementById('name').textContent = cue.name;
+    document.getElementById('time').textContent = cue.looping ? clock(cue.duration) : `-${clock(cue.duration - elapsed)}`;
+    document.getElementById('fill').style.width = `${cue.duration > 0 ? (100 * elapsed) / cue.duration : 0}%`;
+  }
+  requestAnimationFrame(draw);
+}
+poll();
+setInterval(poll, 500);
+draw();
+</script></body></html>
+"#;
+
+// Browser remote: GET / serves REMOTE_PAGE, GET /state the board as published by update(),
+// and /ws takes play/stop/fade lines like the control socket. GET /overlay and /now-playing
+// are the read-only now-playing widget. The server threads only see the published JSON and
+// the command channel, never the board itself.
+struct WebRemote {
+    address: String,
+    running: Arc<AtomicBool>,
+    commands: Receiver<RemoteCommand>,
+    state: Arc<Mutex<String>>,
+    now_playing: Arc<Mutex<String>>,
+    last_publish: Option<Instant>,
+}
+
+impl WebRemote {
+    const PUBLISH_INTERVAL: Duration = Duration::from_millis(250);
+
+    fn start(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let running = Arc::new(AtomicBool::new(true));
+        let (sender, commands) = mpsc::channel();
+        let state = Arc::new(Mutex::new("{\"tabs\":[]}".to_string()));
+        let now_playing = Arc::new(Mutex::new(Self::now_playing_json(None)));
+        let (accept_running, accept_state, accept_now_playing) = (running.clone(), state.clone(), now_playing.clone());
+        thread::spawn(move || {
+            while accept_running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let (sender, state, now_playing) = (sender.clone(), accept_state.clone(), accept_now_playing.clone());
+                        thread::spawn(move || Self::serve(stream, sender, state, now_playing));
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        });
+        Ok(Self { address: address.to_string(), running, commands, state, now_playing, last_publish: None })
+    }
+
+    // Request headers, left unread in the socket so a WebSocket handshake can still read them.
+    // Also returns how many bytes they take up there, which the lossy text may not match.
+    fn peek_head(stream: &TcpStream) -> Option<(String, usize)> {
+        let mut buf = [0u8; 4096];
+        for _ in 0..200 {
+            let n = stream.peek(&mut buf).ok().filter(|&n| n > 0)?;
+            let head = String::from_utf8_lossy(&buf[..n]);
+            if head.contains("\r\n\r\n") || n == buf.len() {
+                return Some((head.into_owned(), n));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    // Value of the first `name` header in a request head, matched case-insensitively
+    fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+        head.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    fn serve(mut stream: TcpStream, sender: Sender<RemoteCommand>, state: Arc<Mutex<String>>, now_playing: Arc<Mutex<String>>) {
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let Some((head, head_len)) = Self::peek_head(&stream) else {
+            return;
+        };
+        if head.to_ascii_lowercase().contains("upgrade: websocket") {
+            if !Self::origin_allowed(Self::header(&head, "origin"), Self::header(&head, "host")) {
+                let _ = write!(stream, "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                return;
+            }
+            let _ = stream.set_read_timeout(None);
+            Self::serve_socket(stream, sender);
+            return;
+        }
+        // Plain GETs have no body, so the peeked head is the whole request
+        let mut request = vec![0u8; head_len];
+        let _ = std::io::Read::read_exact(&mut stream, &mut request);
+        let path = head.split_whitespace().nth(1).unwrap_or("/");
+        let (status, content_type, body) = match path {
+            "/" | "/index.html" => ("200 OK", "text/html; charset=utf-8", REMOTE_PAGE.to_string()),
+            "/state" => ("200 OK", "application/json", state.lock().map(|state| state.clone()).unwrap_or_default()),
+            "/overlay" => ("200 OK", "text/html; charset=utf-8", OVERLAY_PAGE.to_string()),
+            "/now-playing" => ("200 OK", "application/json", now_playing.lock().map(|now| now.clone()).unwrap_or_default()),
+            _ => ("404 Not Found", "text/plain", "Not found".to_string()),
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+    }
+
+    fn serve_socket(stream: TcpStream, sender: Sender<RemoteCommand>) {
+        let Ok(mut socket) = tungstenite::accept(stream) else {
+            return;
+        };
+        loop {
+            let reply = match socket.read() {
+                Ok(tungstenite::Message::Text(line)) => Self::reply(&line, &sender),
+                Ok(tungstenite::Message::Close(_)) | Err(_) => break,
+                Ok(_) => continue,
+            };
+            if socket.send(tungstenite::Message::Text(reply)).is_err() {
+                break;
+            }
+        }
+    }
+
+    // Browsers send the page's origin with every WebSocket handshake, which stops a page from
+    // another site driving the board through the browser of someone on the network. Clients
+    // that send none, like scripts, aren't browsers and are let in.
+    fn origin_allowed(origin: Option<&str>, host: Option<&str>) -> bool {
+        let Some(origin) = origin else {
+            return true;
+        };
+        let authority = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+        host.is_some_and(|host| authority.eq_ignore_ascii_case(host))
+    }
+
+    // Anyone on the network can reach the page, so it can't load files from this machine
+    fn reply(line: &str, sender: &Sender<RemoteCommand>) -> String {
+        match RemoteCommand::parse(line) {
+            Ok(RemoteCommand::Load(_)) => "ERR load isn't available from the browser remote".to_string(),
+            _ => IpcServer::reply(line, sender),
+        }
+    }
+
+    // What the page shows: every tab with its populated slots, numbered from 1 as in the UI
+    fn state_json(tabs: &[MusicTab], playing: impl Fn((usize, usize)) -> bool) -> String {
+        let tabs: Vec<serde_json::Value> = tabs
+            .iter()
+            .enumerate()
+            .map(|(t, tab)| {
+                let buttons: Vec<serde_json::Value> = tab
+                    .buttons
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, button)| !button.name.is_empty())
+                    .map(|(i, button)| {
+                        let [r, g, b, _] = button.color.to_srgba_unmultiplied();
+                        serde_json::json!({
+                            "slot": i + 1,
+                            "name": button.name,
+                            "color": format!("#{:02x}{:02x}{:02x}", r, g, b),
+                            "playing": playing((t, i)),
+                        })
+                    })
+                    .collect();
+                serde_json::json!({ "number": t + 1, "name": tab.name, "buttons": buttons })
+            })
+            .collect();
+        serde_json::json!({ "tabs": tabs }).to_string()
+    }
+
+    // The current cue for the overlay: (button, seconds into it, paused), or None when nothing
+    // is on air. Times are in seconds of playback, speed and trim included, as on the pad.
+    fn now_playing_json(cue: Option<(&MusicButton, f32, bool)>) -> String {
+        match cue {
+            Some((button, elapsed, paused)) => serde_json::json!({
+                "playing": true,
+                "name": button.name,
+                "duration": button.play_length(),
+                "elapsed": elapsed,
+                "paused": paused,
+                "looping": button.playback.loop_enabled,
+            }),
+            None => serde_json::json!({ "playing": false }),
+        }
+        .to_string()
+    }
+}
+
+impl Drop for WebRemote {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+// Note-ons from a MIDI pad controller. midir calls back on its own thread, which forwards the
+// note numbers to update(); the port closes when this is dropped.
+struct MidiListener {
+    port: Option<String>, // as asked for in the settings; None = the first port
+    notes: Receiver<u8>,
+    _connection: MidiInputConnection<()>,
+}
+
+impl MidiListener {
+    fn ports() -> Vec<String> {
+        let Ok(input) = MidiInput::new("Radio Conductor") else {
+            return Vec::new();
+        };
+        input.ports().iter().filter_map(|port| input.port_name(port).ok()).collect()
+    }
+
+    fn start(port: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let input = MidiInput::new("Radio Conductor")?;
+        let found = input
+            .ports()
+            .into_iter()
+            .find(|candidate| port.is_none_or(|port| input.port_name(candidate).is_ok_and(|name| name == port)))
+            .ok_or(if port.is_some() { "port not found" } else { "no MIDI input port" })?;
+        let (sender, notes) = mpsc::channel();
+        let connection = input
+            .connect(
+                &found,
+                "radio-conductor-in",
+                move |_, message, _| {
+                    if let Some(note) = Self::note_on(message) {
+                        let _ = sender.send(note);
+                    }
+                },
+                (),
+            )
+            .map_err(|err| err.kind().to_string())?;
+        Ok(Self { port: port.map(str::to_string), notes, _connection: connection })
+    }
+
+    // Note number of a note-on on any channel; a note-on with velocity 0 is a note-off
+    fn note_on(message: &[u8]) -> Option<u8> {
+        match *message {
+            [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => Some(note),
+            _ => None,
+        }
+    }
+
+    // "C4" for middle C (60), as most controllers label it
+    fn note_name(note: u8) -> String {
+        const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+        format!("{}{} ({})", NAMES[note as usize % 12], note as i32 / 12 - 1, note)
+    }
+}
+
+// OS-wide shortcut that brings the window to the front; unregistered when dropped
+struct RaiseHotkey {
+    spec: String,
+    hotkey: HotKey,
+    manager: GlobalHotKeyManager,
+}
+
+impl RaiseHotkey {
+    fn register(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let hotkey: HotKey = spec.parse()?;
+        let manager = GlobalHotKeyManager::new()?;
+        manager.register(hotkey)?;
+        Ok(Self { spec: spec.to_string(), hotkey, manager })
+    }
+}
+
+impl Drop for RaiseHotkey {
+    fn drop(&mut self) {
+        let _ = self.manager.unregister(self.hotkey);
+    }
+}
+
+struct Toast {
+    message: String,
+    created: Instant,
+}
+
+type WaveformData = (Vec<f32>, Vec<f32>, f32, f32); // (waveform, waveform_min, duration, loudness_db)
+
+// A finished background decode for the button at `key`; `path` tells stale results apart
+struct WaveformJob {
+    key: (usize, usize),
+    path: PathBuf,
+    result: Result<WaveformData, String>,
+    info: Option<TrackInfo>, // the file's tags, for a file just put on the button
+}
+
+// What decode threads send back: how far they got while decoding, then the result
+enum WaveformMessage {
+    Progress(PathBuf, f32),
+    Done(Box<WaveformJob>),
+}
+
+// Decoded waveforms on disk, one small bincode blob per (file, waveform mode, point count)
+struct WaveformCache {
+    dir: PathBuf,
+}
+
+impl WaveformCache {
+    fn open() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Self { dir: dir.join("radio-conductor").join("waveforms") })
+    }
+
+    // Keyed by path, size and modification time, so an edited file misses instead of
+    // showing a stale waveform. Bump CACHE_VERSION when the decoding itself changes.
+    fn entry(&self, path: &Path, mode: WaveformMode, points: usize) -> Option<PathBuf> {
+        // 2: channels are averaged before chunking, 3: fixed point count, 4: point count in the key,
+        // 5: loudness stored
+        const CACHE_VERSION: u32 = 6;
+        let meta = std::fs::metadata(path).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let mut key = Vec::new();
+        key.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        key.extend_from_slice(path.as_os_str().as_encoded_bytes());
+        key.extend_from_slice(&meta.len().to_le_bytes());
+        key.extend_from_slice(&modified.as_secs().to_le_bytes());
+        key.extend_from_slice(&modified.subsec_nanos().to_le_bytes());
+        key.push(mode as u8);
+        key.extend_from_slice(&(points as u64).to_le_bytes());
+        Some(self.dir.join(format!("{:016x}.bin", Self::fnv1a(&key))))
+    }
+
+    // 64-bit FNV-1a. std's DefaultHasher may change between Rust releases, which would quietly
+    // orphan every entry on a toolchain upgrade.
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+    }
+
+    fn get(&self, path: &Path, mode: WaveformMode, points: usize) -> Option<WaveformData> {
+        let entry = self.entry(path, mode, points)?;
+        let data = std::fs::read(&entry).ok()?;
+        // Mark the entry as used, so evict() keeps it over ones nobody has asked for lately
+        let _ = File::options().append(true).open(&entry).and_then(|file| file.set_modified(SystemTime::now()));
+        bincode::deserialize(&data).ok()
+    }
+
+    fn put(&self, path: &Path, mode: WaveformMode, points: usize, waveform: &WaveformData) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = self.entry(path, mode, points).ok_or("can't stat the source file")?;
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(entry, bincode::serialize(waveform)?)?;
+        self.evict(WAVEFORM_CACHE_LIMIT)?;
+        Ok(())
+    }
+
+    // Deletes the least recently used entries until the directory holds at most `limit` bytes
+    fn evict(&self, limit: u64) -> std::io::Result<()> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let meta = entry.metadata().ok()?;
+                meta.is_file().then(|| (meta.modified().unwrap_or(UNIX_EPOCH), meta.len(), entry.path()))
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= limit {
+                break;
+            }
+            std::fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Decode threads report back on `sender`; update() drains `receiver`
+struct WaveformChannel {
+    sender: Sender<WaveformMessage>,
+    receiver: Receiver<WaveformMessage>,
+}
+
+impl Default for WaveformChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender, receiver }
+    }
+}
+
+// Formats offered by the file pickers; rodio plays them and symphonia probes them for the waveform
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a"];
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+const GRID_COLS: usize = 5;
+const GRID_ROWS: usize = 4;
+const GRID_SLOTS: usize = GRID_COLS * GRID_ROWS;
+const ARTWORK_SIZE: u32 = 128; // longest side of stored cover art thumbnails
+const WAVEFORM_POINTS: usize = 2000; // resolution of decoded waveforms, whatever the track length
+const WAVEFORM_PROGRESS_STEP: f32 = 0.01; // decode progress is reported in steps of at least this
+const WAVEFORM_CACHE_LIMIT: u64 = 256 * 1024 * 1024; // bytes kept in the waveform cache before the oldest go
+const GRID_PADDING: f32 = 12.0; // around the grid
+const FIT_PAD_SIZE: Vec2 = Vec2::new(200.0, 120.0); // pads of fit-to-content tabs, at most
+const FIT_MIN_PAD_HEIGHT: f32 = 40.0;
+const GRID_SPACING: f32 = 8.0; // between slots
+const PAGE_SLOT_WIDTH: f32 = 24.0; // the add/remove page cell after the last slot in edit mode
+const SEEK_STRIP: f32 = 0.25; // bottom share of a playing pad that seeks instead of fading out
+const ZERO_CROSSING_WINDOW: Duration = Duration::from_millis(10); // furthest a start is moved to snap
+
+// Passes samples through untouched, recording the instant the output pulls the first one
+struct StartProbe<S> {
+    inner: S,
+    started: Option<Arc<Mutex<Option<Instant>>>>,
+}
+
+impl<S> Iterator for StartProbe<S>
+where
+    S: Source,
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        if let Some(started) = self.started.take() {
+            *started.lock().unwrap() = Some(Instant::now());
+        }
+        self.inner.next()
+    }
+}
+
+impl<S> Source for StartProbe<S>
+where
+    S: Source,
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// Format of the internal mix that every voice is summed into (and that recordings use)
+const MIX_CHANNELS: u16 = 2;
+const MIX_SAMPLE_RATE: u32 = 44_100;
+const TAP_CHUNK: usize = 4096; // samples handed to the recorder at a time
+const METER_STRIDE: usize = 8; // the level meter looks at every 8th sample of a chunk
+const METER_FALL: f32 = 0.05; // share of the meter reading left after a second without signal
+const METER_FLOOR_DB: f32 = -48.0; // bottom of the meter scale
+
+type RecordSlot = Arc<Mutex<Option<SyncSender<Vec<f32>>>>>;
+
+// Sits between the mix and the output device and copies samples to the recorder when armed
+struct OutputTap<S> {
+    inner: S,
+    record: RecordSlot,
+    gain: Arc<AtomicU32>, // master gain as f32 bits, applied before recording
+    buffer: Vec<f32>,
+    epoch: Instant,
+    pulled_ms: Arc<AtomicU64>, // ms after `epoch` the device last pulled a chunk
+    peak: Arc<AtomicU32>,      // highest level since the UI last read it, as f32 bits
+}
+
+impl<S> Iterator for OutputTap<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()? * f32::from_bits(self.gain.load(Ordering::Relaxed));
+        self.buffer.push(sample);
+        if self.buffer.len() >= TAP_CHUNK {
+            // Non-negative floats order like their bits, so the peak can be merged without a lock
+            let peak = self.buffer.iter().step_by(METER_STRIDE).fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            self.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+            // Never block the audio thread: skip this chunk if the UI holds the lock
+            if let Ok(slot) = self.record.try_lock()
+                && let Some(sender) = slot.as_ref()
+            {
+                let _ = sender.try_send(std::mem::replace(&mut self.buffer, Vec::with_capacity(TAP_CHUNK)));
+            }
+            self.buffer.clear();
+            self.pulled_ms.store(self.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+        }
+        Some(sample)
+    }
+}
+
+impl<S> Source for OutputTap<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// Sums every frame to mono while `enabled` is set, for checking a mix's mono compatibility.
+// Works frame by frame, so switching it mid-stream never swaps or smears channels.
+struct MonoSum<S> {
+    inner: S,
+    enabled: Arc<AtomicBool>,
+    frame: Vec<f32>,
+    pos: usize, // next sample of `frame` to hand out
+}
+
+impl<S> MonoSum<S> {
+    fn new(inner: S, enabled: Arc<AtomicBool>) -> Self {
+        Self { inner, enabled, frame: Vec::new(), pos: 0 }
+    }
+}
+
+impl<S> Iterator for MonoSum<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos >= self.frame.len() {
+            let channels = self.inner.channels().max(1) as usize;
+            self.frame.clear();
+            self.frame.extend(self.inner.by_ref().take(channels));
+            if self.frame.is_empty() {
+                return None;
+            }
+            if self.enabled.load(Ordering::Relaxed) {
+                let mean = self.frame.iter().sum::<f32>() / self.frame.len() as f32;
+                self.frame.fill(mean);
+            }
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(self.frame[self.pos - 1])
+    }
+}
+
+impl<S> Source for MonoSum<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// An air-check recording in progress; samples are written by a background thread
+struct Recording {
+    path: PathBuf,
+    started: Instant,
+    error: Arc<Mutex<Option<String>>>,
+    writer: thread::JoinHandle<()>,
+}
+
+// A latency measurement in flight for the button at `key`
+struct LatencyProbe {
+    key: (usize, usize),
+    requested: Instant,
+    started: Arc<Mutex<Option<Instant>>>,
+    offset: Duration, // position playback started from
+}
+
+// Decodes with symphonia directly, so playback can begin part-way into a file; rodio 0.17's
+// decoder can only get there by decoding everything before it
+struct SeekableSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn symphonia::core::codecs::Decoder>,
+    track_id: u32,
+    channels: u16,
+    sample_rate: u32,
+    buffer: Vec<f32>,
+    pos: usize,
+}
+
+impl SeekableSource {
+    // Returns the source and the position it actually starts from (seeks land on packet
+    // boundaries). With `snap`, a start part-way into the file moves on to the next zero
+    // crossing, see snap_to_zero_crossing().
+    fn open(path: &Path, offset: Duration, snap: bool) -> Result<(Self, Duration), Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+        let probed = get_probe().format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())?;
+        let mut format = probed.format;
+        let track = format.default_track().ok_or("no audio track")?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let sample_rate = params.sample_rate.ok_or("unknown sample rate")?;
+        let channels = params.channels.ok_or("unknown channel layout")?.count() as u16;
+        let mut decoder = get_codecs().make(&params, &DecoderOptions::default())?;
+        let mut actual = Duration::ZERO;
+        if !offset.is_zero() {
+            let seeked = format.seek(
+                SeekMode::Coarse,
+                SeekTo::Time { time: Time::from(offset.as_secs_f64()), track_id: Some(track_id) },
+            )?;
+            decoder.reset();
+            if let Some(time_base) = params.time_base {
+                let time = time_base.calc_time(seeked.actual_ts);
+                actual = Duration::from_secs_f64(time.seconds as f64 + time.frac);
+            }
+        }
+        let mut source = Self { format, decoder, track_id, channels, sample_rate, buffer: Vec::new(), pos: 0 };
+        if snap && !offset.is_zero() {
+            actual += source.snap_to_zero_crossing(ZERO_CROSSING_WINDOW);
+        }
+        Ok((source, actual))
+    }
+
+    // Starting mid-waveform steps the output from silence to wherever the wave is, which
+    // clicks. Skips to the first frame within `window` where the signal (channels summed)
+    // changes sign, looking only at the first decoded packet; returns the time skipped, zero
+    // when there is no crossing there. Only searches forward: the seek just landed on a packet
+    // boundary, so the samples before it are in a packet that was never decoded, and getting
+    // them would mean a second seek to a point the format may not be able to land on.
+    fn snap_to_zero_crossing(&mut self, window: Duration) -> Duration {
+        if !self.fill() {
+            return Duration::ZERO;
+        }
+        let channels = self.channels.max(1) as usize;
+        let level = |frame: usize| self.buffer[self.pos + frame * channels..self.pos + (frame + 1) * channels].iter().sum::<f32>();
+        let frames = ((self.buffer.len() - self.pos) / channels).min((window.as_secs_f64() * self.sample_rate as f64) as usize + 1);
+        if frames == 0 || level(0) == 0.0 {
+            return Duration::ZERO;
+        }
+        let Some(crossing) = (1..frames).find(|&frame| level(frame) == 0.0 || (level(frame) < 0.0) != (level(frame - 1) < 0.0)) else {
+            return Duration::ZERO;
+        };
+        // Of the two frames either side of the crossing, the one nearer zero
+        let frame = if level(crossing).abs() <= level(crossing - 1).abs() { crossing } else { crossing - 1 };
+        self.pos += frame * channels;
+        Duration::from_secs_f64(frame as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    // Decodes packets until there are samples to hand out; false at the end of the stream
+    fn fill(&mut self) -> bool {
+        while self.pos >= self.buffer.len() {
+            let Ok(packet) = self.format.next_packet() else {
+                return false;
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                    samples.copy_interleaved_ref(decoded);
+                    self.buffer.clear();
+                    self.buffer.extend_from_slice(samples.samples());
+                    self.pos = 0;
+                }
+                // A corrupt packet only costs its own samples
+                Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+impl Iterator for SeekableSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.fill() {
+            return None;
+        }
+        let sample = self.buffer[self.pos];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for SeekableSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// A sink whose source is already opened and primed, but not yet connected to the mix
+struct PreparedSink {
+    sink: Sink,
+    output: SourcesQueueOutput<f32>,
+    started: Arc<Mutex<Option<Instant>>>,
+}
+
+impl PreparedSink {
+    fn open(path: &Path, looping: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = BufReader::new(File::open(path)?);
+        let decoder = Decoder::new(file)?;
+        let (sink, output) = Sink::new_idle();
+        let started = Arc::new(Mutex::new(None));
+        if looping {
+            sink.append(StartProbe { inner: decoder.repeat_infinite(), started: Some(started.clone()) });
+        } else {
+            sink.append(StartProbe { inner: decoder, started: Some(started.clone()) });
+        }
+        Ok(Self { sink, output, started })
+    }
+
+    // Like open(), but starting `offset` into the file; also returns where it really starts.
+    // A looping sink goes on with the whole file, queued behind the partial first pass.
+    // With a trim (start, length) both are counted from the trim start, and only that part
+    // plays or loops. `snap` moves each start inside the file to a zero crossing.
+    fn open_at(path: &Path, trim: Option<(Duration, Duration)>, offset: Duration, looping: bool, snap: bool) -> Result<(Self, Duration), Box<dyn std::error::Error>> {
+        let (sink, output) = Sink::new_idle();
+        let started = Arc::new(Mutex::new(None));
+        let Some((start, length)) = trim else {
+            let (source, actual) = SeekableSource::open(path, offset, snap)?;
+            sink.append(StartProbe { inner: source, started: Some(started.clone()) });
+            if looping {
+                sink.append(Decoder::new(BufReader::new(File::open(path)?))?.repeat_infinite());
+            }
+            return Ok((Self { sink, output, started }, actual));
+        };
+        let (source, actual) = SeekableSource::open(path, start + offset, snap)?;
+        let actual = actual.saturating_sub(start);
+        sink.append(StartProbe { inner: source.take_duration(length.saturating_sub(actual)), started: Some(started.clone()) });
+        if looping {
+            sink.append(Self::loop_pass(path, start, length, snap)?.repeat_infinite());
+        }
+        Ok((Self { sink, output, started }, actual))
+    }
+
+    // One pass of a trimmed loop. Snapping delays where it starts but not how long it lasts,
+    // so the loop keeps its period and only runs as far past the trim end as it started late.
+    fn loop_pass(path: &Path, start: Duration, length: Duration, snap: bool) -> Result<TakeDuration<SeekableSource>, Box<dyn std::error::Error>> {
+        let (pass, _) = SeekableSource::open(path, start, snap)?;
+        Ok(pass.take_duration(length))
+    }
+
+    // A pad's sink from the top of its (trimmed) file
+    fn for_file(path: &Path, trim: Option<(Duration, Duration)>, looping: bool, snap: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        match trim {
+            Some(_) => Ok(Self::open_at(path, trim, Duration::ZERO, looping, snap)?.0),
+            None => Self::open(path, looping),
+        }
+    }
+
+    fn tone(tone: &TestTone) -> Self {
+        let (sink, output) = Sink::new_idle();
+        let started = Arc::new(Mutex::new(None));
+        let length = Duration::from_secs_f32(tone.duration.max(0.0));
+        match tone.kind {
+            ToneKind::Sine => {
+                let source = SineWave::new(tone.frequency).take_duration(length).amplify(tone.gain());
+                sink.append(StartProbe { inner: source, started: Some(started.clone()) });
+            }
+            ToneKind::PinkNoise => {
+                let source = PinkNoise::new().take_duration(length).amplify(tone.gain());
+                sink.append(StartProbe { inner: source, started: Some(started.clone()) });
+            }
+        }
+        Self { sink, output, started }
+    }
+
+    fn for_button(button: &MusicButton, snap: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        match &button.tone {
+            Some(tone) => Ok(Self::tone(tone)),
+            None => Self::for_file(&button.path, button.trim(), button.playback.loop_enabled, snap),
+        }
+    }
+}
+
+// Pink noise via Paul Kellet's filter over a xorshift white source; mono, endless
+struct PinkNoise {
+    rng: u32,
+    b: [f32; 7],
+}
+
+impl PinkNoise {
+    fn new() -> Self {
+        Self { rng: 0x9E37_79B9, b: [0.0; 7] }
+    }
+}
+
+impl Iterator for PinkNoise {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let white = self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        // The filter's gain is roughly 9, bring peaks back to about full scale
+        Some(pink * 0.11)
+    }
+}
+
+impl Source for PinkNoise {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MIX_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// Uniform in [0, 1). Every RandomState gets fresh keys, so hashing nothing is already random
+// enough for picking start positions and saves a dependency
+fn random_fraction() -> f32 {
+    use std::hash::{BuildHasher, Hasher};
+    let hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    (hasher.finish() >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// Opt-in pool entry for a pad; `ready` is None if the file could not be opened
+struct Preloaded {
+    path: PathBuf,
+    looping: bool,
+    trim: Option<(Duration, Duration)>,
+    snap: bool,
+    ready: Option<PreparedSink>,
+}
+
+// One sink started by the player, kept until it runs dry (including while fading)
+struct Voice {
+    name: String,
+    key: (usize, usize), // (tab, index) of the originating button
+    sink: Arc<Sink>,
+    button_volume: f32, // the button's own level; the mixer fader scales on top of it
+    volume: f32,
+    muted: bool,
+    end_gain: f32, // end-fade attenuation, 1.0 until the fade starts
+    fade_in: Option<(Instant, Duration)>, // ramp from silence started by a crossfade
+    fade_curve: FadeCurve,                // of the fade-in, as set when the voice started
+    fading: bool,                         // a fade thread owns the sink's volume until it stops it
+}
+
+impl Voice {
+    fn fade_in_gain(&self) -> f32 {
+        match self.fade_in {
+            Some((start, length)) => self.fade_curve.gain(start.elapsed().as_secs_f32() / length.as_secs_f32()),
+            None => 1.0,
+        }
+    }
+
+    // Leaves a fading voice alone: setting its level here would fight the ramp, and could
+    // bring a sink that is on its way out back up
+    fn apply_volume(&self) {
+        if self.fading {
+            return;
+        }
+        let gain = self.button_volume * self.volume * self.end_gain * self.fade_in_gain();
+        self.sink.set_volume(if self.muted { 0.0 } else { gain });
+    }
+}
+
+// A cue started in polyphonic mode: it plays next to the current cue and any other layers
+struct Layer {
+    sink: Arc<Sink>,
+    started: Instant, // backdated by the start offset, so the time since it is the position
+}
+
+// Second output for auditioning a pad on headphones; it never reaches the mix, the meter or
+// a recording
+struct PreListen {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    playing: Option<((usize, usize), Sink)>,
+}
+
+struct AudioPlayer {
+    sink: Option<Arc<Sink>>,
+    voices: Vec<Voice>,
+    layers: HashMap<(usize, usize), Layer>,
+    preloaded: HashMap<(usize, usize), Preloaded>,
+    mixer: Arc<DynamicMixerController<f32>>,
+    record_slot: RecordSlot,
+    master_gain: Arc<AtomicU32>,
+    recording: Option<Recording>,
+    stream: Option<OutputStream>, // None when no output device could be opened
+    output_error: Option<String>,
+    device: Option<String>, // output device in use; None for the system default
+    prelisten: Option<PreListen>, // None when no pre-listen device is chosen or it couldn't be opened
+    fade_curve: FadeCurve,        // for ramps started from now on; set from the config every frame
+    snap_to_zero: bool,           // for sinks opened from now on; set from the config every frame
+    epoch: Instant,
+    pulled_ms: Arc<AtomicU64>, // see OutputTap
+    peak: Arc<AtomicU32>,      // see OutputTap
+    mono: Arc<AtomicBool>,     // see MonoSum
+    // Position of the current cue = `accumulated` + time since `start_time` (the current run);
+    // pausing folds the run into `accumulated`, seeking replaces it
+    start_time: Option<Instant>,
+    accumulated: Duration,
+    paused: bool,
+    speed: f32, // rate of the current cue; the clock runs in real time, the file at `speed` times that
+    duration: f32,
+    end_fade: f32, // seconds of automatic fade before the current track's natural end
+    latency_probe: Option<LatencyProbe>,
+}
+
+impl AudioPlayer {
+    fn new() -> Self {
+        Self::open(None)
+    }
+
+    // Plays on the named output device, or the system default when it is None or can't be found
+    fn open(device: Option<&str>) -> Self {
+        let (mixer, mix) = dynamic_mixer::mixer::<f32>(MIX_CHANNELS, MIX_SAMPLE_RATE);
+        // The mixer ends once it has no inputs, so keep a silent source in it at all times
+        mixer.add(Zero::<f32>::new(MIX_CHANNELS, MIX_SAMPLE_RATE));
+        let record_slot: RecordSlot = Arc::new(Mutex::new(None));
+        let master_gain = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let mono = Arc::new(AtomicBool::new(false));
+        let tap = OutputTap {
+            inner: MonoSum::new(mix, mono.clone()),
+            record: record_slot.clone(),
+            gain: master_gain.clone(),
+            buffer: Vec::with_capacity(TAP_CHUNK),
+            epoch: Instant::now(),
+            pulled_ms: Arc::new(AtomicU64::new(0)),
+            peak: Arc::new(AtomicU32::new(0)),
+        };
+        let (epoch, pulled_ms, peak) = (tap.epoch, tap.pulled_ms.clone(), tap.peak.clone());
+        let found = device.and_then(Self::find_device);
+        let device = found.as_ref().and_then(|found| found.name().ok());
+        // Without an output device the mix is simply never pulled; the UI stays usable for editing
+        let stream = match &found {
+            Some(found) => OutputStream::try_from_device(found),
+            None => OutputStream::try_default(),
+        };
+        let opened = match stream {
+            Ok((stream, handle)) => handle.play_raw(tap).map(|_| stream).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        let (stream, output_error) = match opened {
+            Ok(stream) => (Some(stream), None),
+            Err(err) => (None, Some(err)),
+        };
+        Self {
+            sink: None,
+            voices: Vec::new(),
+            layers: HashMap::new(),
+            preloaded: HashMap::new(),
+            mixer,
+            record_slot,
+            master_gain,
+            recording: None,
+            stream,
+            output_error,
+            device,
+            prelisten: None,
+            fade_curve: FadeCurve::default(),
+            snap_to_zero: false,
+            epoch,
+            pulled_ms,
+            peak,
+            mono,
+            start_time: None,
+            accumulated: Duration::ZERO,
+            paused: false,
+            speed: 1.0,
+            duration: 0.0,
+            end_fade: 0.0,
+            latency_probe: None,
+        }
+    }
+
+    // Start latency is estimated as the time from this call until the output thread pulls the
+    // first decoded sample, which covers file open, format probing and decoder start-up. The
+    // progress clock starts at play() + the button's last measured latency and is corrected to
+    // the measured instant once the first sample is pulled.
+    // The file is opened before anything is stopped, so a missing or unreadable file leaves the
+    // player untouched.
+    fn play(&mut self, key: (usize, usize), button: &MusicButton) -> Result<(), Box<dyn std::error::Error>> {
+        let requested = Instant::now();
+        let (prepared, offset) = self.open_cue(key, button)?;
+        if let Some(current_sink) = &self.sink {
+            current_sink.stop();
+        }
+        self.start_cue(key, button, prepared, offset, requested);
+        Ok(())
+    }
+
+    // Like play(), but the cue on air is left the way `transition` says instead of being cut.
+    // That only happens once the new file has opened, so a failure leaves the old cue playing.
+    // In a crossfade the old sink fades out on its own thread (like fade_out) while the new
+    // voice ramps up from silence over the same window; earlier fades keep running.
+    fn switch_to(&mut self, key: (usize, usize), button: &MusicButton, transition: Transition, crossfade: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        let requested = Instant::now();
+        let (prepared, offset) = self.open_cue(key, button)?;
+        match transition {
+            Transition::FadeOut => self.fade_out(),
+            Transition::HardCut => self.stop(),
+            Transition::Crossfade => self.fade_out_over(crossfade),
+        }
+        self.start_cue(key, button, prepared, offset, requested);
+        if transition == Transition::Crossfade
+            && let Some(voice) = self.voices.last_mut()
+        {
+            voice.fade_in = Some((Instant::now(), crossfade));
+            voice.apply_volume();
+        }
+        Ok(())
+    }
+
+    // Makes an opened sink the current cue
+    fn start_cue(&mut self, key: (usize, usize), button: &MusicButton, prepared: PreparedSink, offset: Duration, requested: Instant) {
+        let PreparedSink { sink, output, started } = prepared;
+        self.speed = button.speed();
+        let sink = self.start_voice(key, button, sink, output);
+        self.sink = Some(sink);
+        // Count from the start offset so the progress display shows the real position
+        let expected_start = requested + Duration::from_secs_f32(button.start_latency_ms / 1000.0);
+        self.set_position(offset, expected_start);
+        self.duration = button.play_length();
+        self.end_fade = 0.0;
+        self.latency_probe = Some(LatencyProbe { key, requested, started, offset });
+    }
+
+    // Polyphonic counterpart of play(): the cue gets a sink of its own and leaves the current
+    // cue and the other layers alone. Retriggering a layer restarts it.
+    fn play_layer(&mut self, key: (usize, usize), button: &MusicButton) -> Result<(), Box<dyn std::error::Error>> {
+        let requested = Instant::now();
+        let (prepared, offset) = self.open_cue(key, button)?;
+        if let Some(layer) = self.layers.remove(&key) {
+            layer.sink.stop();
+        }
+        let PreparedSink { sink, output, .. } = prepared;
+        let sink = self.start_voice(key, button, sink, output);
+        let expected_start = requested + Duration::from_secs_f32(button.start_latency_ms / 1000.0);
+        let started = expected_start.checked_sub(offset.div_f32(button.speed())).unwrap_or(expected_start);
+        self.layers.insert(key, Layer { sink, started });
+        Ok(())
+    }
+
+    // Fades out just this pad's layer; false if it has none
+    fn fade_out_layer(&mut self, key: (usize, usize)) -> bool {
+        match self.layers.remove(&key) {
+            Some(layer) => {
+                self.fade_away(layer.sink, Duration::from_secs(1));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_layered(&self, key: (usize, usize)) -> bool {
+        self.layers.contains_key(&key)
+    }
+
+    // Seconds into a layer, or None if the pad isn't playing as one
+    fn layer_elapsed(&self, key: (usize, usize)) -> Option<f32> {
+        let layer = self.layers.get(&key)?;
+        Some(Instant::now().saturating_duration_since(layer.started).as_secs_f32())
+    }
+
+    // The pad's sink, taken from the preload slot when it is armed, or opened at a random
+    // point for random-start buttons. Returns the start offset into the file.
+    fn open_cue(&mut self, key: (usize, usize), button: &MusicButton) -> Result<(PreparedSink, Duration), Box<dyn std::error::Error>> {
+        let random_start = button.playback.random_start && button.tone.is_none() && button.trimmed_duration() > 1.0;
+        if random_start {
+            // Stay clear of the very end so the trigger is still audible
+            let offset = Duration::from_secs_f32(button.trimmed_duration() * 0.9 * random_fraction());
+            return PreparedSink::open_at(&button.path, button.trim(), offset, button.playback.loop_enabled, self.snap_to_zero);
+        }
+        let snap = self.snap_to_zero;
+        let preloaded = self
+            .preloaded
+            .get_mut(&key)
+            .filter(|entry| entry.path == button.path && entry.looping == button.playback.loop_enabled && entry.trim == button.trim() && entry.snap == snap)
+            .and_then(|entry| entry.ready.take());
+        match preloaded {
+            Some(prepared) => Ok((prepared, Duration::ZERO)),
+            None => Ok((PreparedSink::for_button(button, snap)?, Duration::ZERO)),
+        }
+    }
+
+    // Puts an opened sink on the mix as a new voice
+    fn start_voice(&mut self, key: (usize, usize), button: &MusicButton, sink: Sink, output: SourcesQueueOutput<f32>) -> Arc<Sink> {
+        sink.set_speed(button.speed());
+        self.mixer.add(output);
+        let sink = Arc::new(sink);
+        // Re-arm the pad right away so a retrigger is just as fast
+        if let Some(entry) = self.preloaded.get_mut(&key) {
+            entry.ready = PreparedSink::for_file(&entry.path, entry.trim, entry.looping, entry.snap).ok();
+        }
+        let voice = Voice {
+            name: button.name.clone(),
+            key,
+            sink: sink.clone(),
+            button_volume: button.playback.volume.max(0.0),
+            volume: 1.0,
+            muted: false,
+            end_gain: 1.0,
+            // Same ramp as a crossfade, so it lands on the button and mixer level
+            fade_in: (button.playback.fade_in_ms > 0).then(|| (Instant::now(), Duration::from_millis(button.playback.fade_in_ms as u64))),
+            fade_curve: self.fade_curve,
+            fading: false,
+        };
+        voice.apply_volume();
+        self.voices.push(voice);
+        sink
+    }
+
+    // Follow buttons that were moved to another slot
+    fn remap_keys(&mut self, remap: impl Fn((usize, usize)) -> (usize, usize)) {
+        for voice in &mut self.voices {
+            voice.key = remap(voice.key);
+        }
+        if let Some(probe) = &mut self.latency_probe {
+            probe.key = remap(probe.key);
+        }
+        self.preloaded = self.preloaded.drain().map(|(key, entry)| (remap(key), entry)).collect();
+        self.layers = self.layers.drain().map(|(key, layer)| (remap(key), layer)).collect();
+        if let Some((key, _)) = self.prelisten.as_mut().and_then(|prelisten| prelisten.playing.as_mut()) {
+            *key = remap(*key);
+        }
+    }
+
+    // Returns (key, latency in ms) once the pending probe has seen its first sample
+    fn take_start_latency(&mut self) -> Option<((usize, usize), f32)> {
+        let probe = self.latency_probe.as_ref()?;
+        let started = (*probe.started.lock().unwrap())?;
+        let probe = self.latency_probe.take()?;
+        if self.start_time.is_some() {
+            self.set_position(probe.offset, started);
+        }
+        // Seeking opens the file differently, so it says nothing about the pad's usual latency
+        if !probe.offset.is_zero() {
+            return None;
+        }
+        Some((probe.key, started.duration_since(probe.requested).as_secs_f32() * 1000.0))
+    }
+
+    fn stop(&mut self) {
+        for voice in &self.voices {
+            voice.sink.stop();
+        }
+        if let Some(sink) = &self.sink {
+            sink.stop();
+        }
+        self.sink = None;
+        self.layers.clear();
+        self.clear_position();
+        self.latency_probe = None;
+    }
+
+    // The current run starts at `since`, `position` into the file
+    fn set_position(&mut self, position: Duration, since: Instant) {
+        self.accumulated = position.div_f32(self.speed);
+        self.start_time = Some(since);
+        self.paused = false;
+    }
+
+    fn clear_position(&mut self) {
+        self.start_time = None;
+        self.accumulated = Duration::ZERO;
+        self.paused = false;
+    }
+
+    // Hold the current cue where it is; the clock stops with it
+    fn pause(&mut self) {
+        if let Some(sink) = &self.sink
+            && !self.paused
+        {
+            sink.pause();
+            self.accumulated = self.position();
+            self.paused = true;
+            // Its start would be measured from before the pause
+            self.latency_probe = None;
+        }
+    }
+
+    // Carry on from where pause() left off with a new run
+    fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+        self.start_time = Some(Instant::now());
+        self.paused = false;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // The fade thread takes the current sink over, so a following play() starts on a fresh
+    // sink and never touches one that is still ramping down
+    fn fade_out(&mut self) {
+        self.fade_out_over(Duration::from_secs(1));
+    }
+
+    fn fade_out_over(&mut self, duration: Duration) {
+        self.paused = false;
+        if let Some(sink) = self.sink.take() {
+            self.fade_away(sink, duration);
+        }
+    }
+
+    fn fade_away(&mut self, sink: Arc<Sink>, duration: Duration) {
+        // A fade-in still under way would keep raising the level under the ramp, so it stops
+        // where it got to
+        if let Some(voice) = self.voices.iter_mut().find(|voice| Arc::ptr_eq(&voice.sink, &sink)) {
+            voice.fade_in = None;
+            voice.fading = true;
+        }
+        let curve = self.fade_curve;
+        thread::spawn(move || {
+            let start = Instant::now();
+            // Ramp down from wherever the button/mixer volume left it
+            let from = sink.volume();
+            while start.elapsed() < duration {
+                let progress = start.elapsed().as_secs_f32() / duration.as_secs_f32();
+                let volume = from * curve.gain(1.0 - progress);
+                sink.set_volume(volume);
+                thread::sleep(Duration::from_millis(16));
+            }
+            sink.stop();
+        });
+    }
+
+    // Jump within the current cue by swapping in a sink opened at `pos`. rodio 0.17 can't seek
+    // a running decoder, so the file is reopened with the seekable source; the voice keeps its
+    // mixer settings.
+    fn seek(&mut self, button: &MusicButton, pos: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        let current = self.sink.clone().ok_or("nothing is playing")?;
+        if button.tone.is_some() {
+            return Err("test tones can't be seeked".into());
+        }
+        let (prepared, actual) = PreparedSink::open_at(&button.path, button.trim(), pos, button.playback.loop_enabled, self.snap_to_zero)?;
+        let PreparedSink { sink, output, .. } = prepared;
+        sink.set_speed(self.speed);
+        let sink = Arc::new(sink);
+        if let Some(voice) = self.voices.iter_mut().find(|voice| Arc::ptr_eq(&voice.sink, &current)) {
+            voice.sink = sink.clone();
+            voice.apply_volume();
+        }
+        current.stop();
+        // Seeking a paused cue moves the position but leaves it paused
+        let paused = self.paused;
+        if paused {
+            sink.pause();
+        }
+        self.mixer.add(output);
+        self.sink = Some(sink);
+        self.set_position(actual, Instant::now());
+        self.paused = paused;
+        self.latency_probe = None;
+        Ok(())
+    }
+
+    // Frame-driven update of crossfade ramps; a finished ramp is dropped so it costs nothing
+    fn apply_fade_ins(&mut self) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.fade_in.is_some()) {
+            voice.apply_volume();
+            if voice.fade_in_gain() >= 1.0 {
+                voice.fade_in = None;
+            }
+        }
+    }
+
+    fn start_recording(&mut self, path: PathBuf) -> Result<(), hound::Error> {
+        self.stop_recording();
+        let spec = hound::WavSpec {
+            channels: MIX_CHANNELS,
+            sample_rate: MIX_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec)?;
+        // Roughly six seconds of headroom before chunks are dropped on a stalled disk
+        let (sender, receiver) = mpsc::sync_channel::<Vec<f32>>(64);
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = error.clone();
+        let writer = thread::spawn(move || {
+            let result = (|| {
+                for chunk in receiver {
+                    for sample in chunk {
+                        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+                    }
+                }
+                writer.finalize()
+            })();
+            if let Err(err) = result {
+                *thread_error.lock().unwrap() = Some(err.to_string());
+            }
+        });
+        *self.record_slot.lock().unwrap() = Some(sender);
+        self.recording = Some(Recording { path, started: Instant::now(), error, writer });
+        Ok(())
+    }
+
+    // Dropping the sender lets the writer thread drain and finalize the file
+    fn stop_recording(&mut self) {
+        *self.record_slot.lock().unwrap() = None;
+        if let Some(recording) = self.recording.take() {
+            let _ = recording.writer.join();
+        }
+    }
+
+    // Returns the writer error if the recording thread gave up, stopping the recording
+    fn take_recording_error(&mut self) -> Option<String> {
+        let error = self.recording.as_ref()?.error.lock().unwrap().take()?;
+        self.stop_recording();
+        Some(error)
+    }
+
+    // Keep one primed sink per populated pad, reopening pads whose file changed
+    fn sync_preloaded(&mut self, tabs: &[MusicTab]) {
+        let mut keys = Vec::new();
+        for (t, tab) in tabs.iter().enumerate() {
+            for (i, button) in tab.buttons.iter().enumerate() {
+                // Tones are generated on the spot, there's nothing to preload
+                if button.name.is_empty() || button.tone.is_some() {
+                    continue;
+                }
+                let key = (t, i);
+                keys.push(key);
+                let (looping, trim, snap) = (button.playback.loop_enabled, button.trim(), self.snap_to_zero);
+                if self
+                    .preloaded
+                    .get(&key)
+                    .is_none_or(|entry| entry.path != button.path || entry.looping != looping || entry.trim != trim || entry.snap != snap)
+                {
+                    let ready = PreparedSink::for_file(&button.path, trim, looping, snap).ok();
+                    self.preloaded.insert(key, Preloaded { path: button.path.clone(), looping, trim, snap, ready });
+                }
+            }
+        }
+        self.preloaded.retain(|key, _| keys.contains(key));
+    }
+
+    // Output peak (after the master gain) since the last call
+    fn take_peak(&self) -> f32 {
+        f32::from_bits(self.peak.swap(0, Ordering::Relaxed))
+    }
+
+    fn has_output(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    // The current cue has played to its end (a looping one never does). A sink counts its
+    // source from append(), before any sample is pulled, so a cue that is still starting up
+    // doesn't look finished.
+    fn finished(&self) -> bool {
+        self.sink.as_ref().is_some_and(|sink| sink.empty())
+    }
+
+    // Drop a finished current cue so the progress display resets; returns whether it did
+    fn release_finished(&mut self) -> bool {
+        if !self.finished() {
+            return false;
+        }
+        self.sink = None;
+        self.clear_position();
+        self.latency_probe = None;
+        true
+    }
+
+    fn find_device(name: &str) -> Option<rodio::cpal::Device> {
+        let mut devices = rodio::cpal::default_host().output_devices().ok()?;
+        devices.find(|candidate| candidate.name().is_ok_and(|candidate| candidate == name))
+    }
+
+    // Opens the pre-listen output on `device`, or closes it for None; the main output is left alone
+    fn set_prelisten_device(&mut self, device: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.prelisten = None;
+        let Some(name) = device else {
+            return Ok(());
+        };
+        let found = Self::find_device(name).ok_or("device not found")?;
+        let (stream, handle) = OutputStream::try_from_device(&found)?;
+        self.prelisten = Some(PreListen { _stream: stream, handle, playing: None });
+        Ok(())
+    }
+
+    // Plays the button on the pre-listen output only; doing it again on the same pad stops it.
+    // The current cue, its clock and the mix are untouched.
+    fn prelisten(&mut self, key: (usize, usize), button: &MusicButton) -> Result<(), Box<dyn std::error::Error>> {
+        let prelisten = self.prelisten.as_mut().ok_or("no pre-listen device")?;
+        if let Some((playing, sink)) = prelisten.playing.take() {
+            let again = playing == key && !sink.empty();
+            sink.stop();
+            if again {
+                return Ok(());
+            }
+        }
+        let PreparedSink { sink, output, .. } = PreparedSink::for_button(button, self.snap_to_zero)?;
+        sink.set_speed(button.speed());
+        sink.set_volume(button.playback.volume.max(0.0));
+        prelisten.handle.play_raw(output)?;
+        prelisten.playing = Some((key, sink));
+        Ok(())
+    }
+
+    // The pad being auditioned, if it hasn't finished
+    fn prelistening(&self) -> Option<(usize, usize)> {
+        let (key, sink) = self.prelisten.as_ref()?.playing.as_ref()?;
+        (!sink.empty()).then_some(*key)
+    }
+
+    // Names of the output devices that can be picked right now
+    fn output_devices() -> Vec<String> {
+        match rodio::cpal::default_host().output_devices() {
+            Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // The device stopped asking for audio (unplugged, or its driver went away). The mix
+    // always has a silent input, so a working device never pauses this long.
+    fn output_stalled(&self) -> bool {
+        let pulled = Duration::from_millis(self.pulled_ms.load(Ordering::Relaxed));
+        self.has_output() && self.epoch.elapsed().saturating_sub(pulled) > Duration::from_secs(2)
+    }
+
+    // Try to open the output device again, keeping the current (silent) player on failure
+    fn retry_output(&mut self, device: Option<&str>) -> bool {
+        let mut player = AudioPlayer::open(device);
+        if player.has_output() {
+            player.take_over(self);
+            *self = player;
+            true
+        } else {
+            self.output_error = player.output_error.clone();
+            false
+        }
+    }
+
+    // Moves what outlives a stream from the player being replaced: the pre-listen output and
+    // an air-check, which goes on recording this player's mix
+    fn take_over(&mut self, old: &mut AudioPlayer) {
+        self.prelisten = old.prelisten.take();
+        self.stop_recording();
+        *self.record_slot.lock().unwrap() = old.record_slot.lock().unwrap().take();
+        self.recording = old.recording.take();
+    }
+
+    fn set_master_gain(&self, gain: f32) {
+        self.master_gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    // Applied to the whole mix as it goes out, so it also affects cues already playing
+    fn set_mono(&self, mono: bool) {
+        self.mono.store(mono, Ordering::Relaxed);
+    }
+
+    fn set_end_fade(&mut self, ms: u32) {
+        self.end_fade = ms as f32 / 1000.0;
+    }
+
+    // Frame-driven ramp over the last `end_fade` seconds of the current track. Only the
+    // current sink is touched: a manual fade_out() takes the sink and ramps it on its own.
+    fn apply_end_fade(&mut self) {
+        let (Some(sink), Some(_)) = (&self.sink, self.start_time) else {
+            return;
+        };
+        // Also skips cues whose duration isn't known yet (still being decoded)
+        if self.end_fade <= 0.0 || self.duration <= 0.0 {
+            return;
+        }
+        let remaining = self.duration - self.elapsed();
+        let gain = self.fade_curve.gain(remaining / self.end_fade);
+        if let Some(voice) = self.voices.iter_mut().find(|voice| Arc::ptr_eq(&voice.sink, sink))
+            && voice.end_gain != gain
+        {
+            voice.end_gain = gain;
+            voice.apply_volume();
+        }
+    }
+
+    // Drop voices whose sink has finished or been stopped
+    fn prune_voices(&mut self) {
+        self.voices.retain(|voice| !voice.sink.empty());
+        self.layers.retain(|_, layer| !layer.sink.empty());
+    }
+
+    fn elapsed(&self) -> f32 {
+        if self.start_time.is_some() {
+            self.position().as_secs_f32()
+        } else {
+            0.0
+        }
+    }
+
+    // A run that hasn't started yet (start latency still ahead) counts as zero
+    fn position(&self) -> Duration {
+        match self.start_time {
+            Some(start) if !self.paused => self.accumulated + Instant::now().saturating_duration_since(start),
+            _ => self.accumulated,
+        }
+    }
+}
+
+impl Drop for AudioPlayer {
+    fn drop(&mut self) {
+        self.stop_recording();
+    }
+}
+
+impl Default for EditState {
+    fn default() -> Self {
+        Self {
+            editing: None,
+            name_buf: String::new(),
+            color_buf: Color32::WHITE,
+            pending_music_slot: None,
+            pending_change_music: None,
+            hotkey_buf: None,
+            capturing_hotkey: false,
+            learning_midi: false,
+            pending_hotkey: None,
+            playback_buf: PlaybackSettings::default(),
+            tone_buf: None,
+            pending_tone_slot: None,
+            pending_paste_slot: None,
+            pending_replace: None,
+            pending_relink: None,
+            confirm_delete: false,
+        }
+    }
+}
+
+impl Default for AudioPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for MusicInterface {
+    fn default() -> Self {
+        Self::with_player(AudioPlayer::new())
+    }
+}
+
+impl MusicInterface {
+    // A fresh interface around a player that is already open, so the output is only opened once
+    fn with_player(audio_player: AudioPlayer) -> Self {
+        Self {
+            version: BOARD_VERSION,
+            tabs: vec![MusicTab { name: "Tab 1".to_string(), buttons: Vec::new(), pages: 1, settings: TabSettings::default() }],
+            current_tab: 0,
+            audio_player,
+            edit_mode: false,
+            current_playing: None,
+            edit_state: EditState::default(),
+            renaming_tab: None,
+            tab_rename_buf: String::new(),
+            show_mixer: false,
+            show_settings: false,
+            config: AppConfig::default(),
+            toasts: Vec::new(),
+            queued_triggers: Vec::new(),
+            ipc_server: None,
+            web_remote: None,
+            master_muted: false,
+            current_page: 0,
+            raise_hotkey: None,
+            pending_waveforms: HashMap::new(),
+            pending_normalize: HashSet::new(),
+            missing_paths: HashSet::new(),
+            waveform_progress: HashMap::new(),
+            waveform_jobs: WaveformChannel::default(),
+            confirm_remove_tab: None,
+            last_autosave: None,
+            saved_fingerprint: 0,
+            file_fingerprint: 0,
+            confirm_exit: false,
+            exit_confirmed: false,
+            offer_restore: false,
+            load_error: None,
+            previous_board: None,
+            dragging_tab: None,
+            dragging_button: None,
+            artwork_textures: HashMap::new(),
+            waveform_scales: HashMap::new(),
+            output_devices: None,
+            midi_ports: None,
+            midi_input: None,
+            last_output_reopen: None,
+            meter: 0.0,
+            search_query: String::new(),
+            flash: None,
+            clipboard: None,
+            history: UndoHistory::default(),
+            tab_drag_recorded: false,
+        }
+    }
+
+    fn new() -> Self {
+        let config = AppConfig::load();
+        let mut app = Self::with_player(AudioPlayer::open(config.output_device.as_deref()));
+        app.config = config;
+        // A missing headphone device shows in Settings; the show can go on without it
+        let _ = app.audio_player.set_prelisten_device(app.config.prelisten_device.as_deref());
+        app.open_startup_board();
+        if let Some(autosave) = Self::autosave_path() {
+            app.offer_restore = app.autosave_differs(&autosave, app.config.last_file.as_deref());
+        }
+        app.saved_fingerprint = app.fingerprint();
+        app.file_fingerprint = app.saved_fingerprint;
+        app.last_autosave = Some(Instant::now());
+        app
+    }
+
+    fn autosave_path() -> Option<PathBuf> {
+        AppConfig::path().map(|path| path.with_file_name("autosave.bin"))
+    }
+
+    // Cheap stand-in for a dirty flag: edits happen all over the UI, so compare snapshots of
+    // the tabs instead of flagging each one
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        // Plays happen during a show; they shouldn't count as unsaved changes
+        let tabs: Vec<MusicTab> = self
+            .tabs
+            .iter()
+            .map(|tab| MusicTab {
+                name: tab.name.clone(),
+                buttons: tab.buttons.iter().map(|button| MusicButton { info: TrackInfo { stats: PlayStats::default(), ..button.info.clone() }, ..button.clone() }).collect(),
+                pages: tab.pages,
+                settings: tab.settings,
+            })
+            .collect();
+        bincode::serialize(&tabs).unwrap_or_default().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Frame-driven; writes `path` once the interval is up, and only if the tabs changed since
+    // the last write. Returns whether it wrote.
+    fn autosave(&mut self, path: &PathBuf) -> Result<bool, Box<dyn std::error::Error>> {
+        // Don't overwrite an autosave the user hasn't decided about yet
+        if self.config.autosave_secs == 0 || self.offer_restore {
+            return Ok(false);
+        }
+        let interval = Duration::from_secs(self.config.autosave_secs as u64);
+        if self.last_autosave.is_some_and(|last| last.elapsed() < interval) {
+            return Ok(false);
+        }
+        self.last_autosave = Some(Instant::now());
+        let fingerprint = self.fingerprint();
+        if fingerprint == self.saved_fingerprint {
+            return Ok(false);
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.save_to_file(path)?;
+        self.saved_fingerprint = fingerprint;
+        Ok(true)
+    }
+
+    // Worth offering at startup: the autosave is newer than the last saved or opened board and
+    // holds something else than what was just loaded
+    fn autosave_differs(&self, autosave: &Path, last_file: Option<&Path>) -> bool {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        let Some(saved) = modified(autosave) else {
+            return false;
+        };
+        if last_file.and_then(modified).is_some_and(|last| last >= saved) {
+            return false;
+        }
+        std::fs::read(autosave)
+            .ok()
+            .and_then(|data| legacy::decode_board(&data).ok())
+            .is_some_and(|board| board.fingerprint() != self.fingerprint())
+    }
+
+    fn open_startup_board(&mut self) {
+        let path = match &self.config.startup_board {
+            StartupBoard::Empty => return,
+            StartupBoard::LastFile => match &self.config.last_file {
+                Some(path) => path.clone(),
+                None => return,
+            },
+            StartupBoard::Fixed(path) => path.clone(),
+        };
+        if !path.exists() {
+            self.toast(format!("Startup board not found: {}; starting with an empty board", path.display()));
+            return;
+        }
+        if let Err(err) = self.load_from_file(&path) {
+            self.report_load_error(&path, err);
+        }
+        // Nothing was replaced: the empty board it "backed up" never held anything
+        self.previous_board = None;
+    }
+
+    fn toast(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast { message: message.into(), created: Instant::now() });
+    }
+
+    // The board now matches a file on disk
+    fn mark_saved(&mut self) {
+        self.file_fingerprint = self.fingerprint();
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        self.fingerprint() != self.file_fingerprint
+    }
+
+    // Writes the board in the format its extension asks for and makes it the last file
+    fn save_board(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.validate_and_repair();
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            self.save_to_json(path)?;
+        } else {
+            self.save_to_file(&path.to_path_buf())?;
+        }
+        self.remember_file(path);
+        Ok(())
+    }
+
+    // Where "Save" on exit writes without asking: the last board, if it is a .bin board. One
+    // opened from JSON or unpacked from a show package gets a new file rather than a bare
+    // board.json written over the import.
+    fn save_back_path(&self) -> Option<PathBuf> {
+        self.config.last_file.clone().filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("bin")))
+    }
+
+    // Called after every save and load of a board file
+    fn remember_file(&mut self, path: &Path) {
+        self.mark_saved();
+        self.config.last_file = Some(path.to_path_buf());
+        self.config.push_recent(path);
+        let _ = self.config.save();
+    }
+
+    // From the Recent menu; a board that has gone is dropped from the list with a notice
+    fn open_recent(&mut self, path: &Path) {
+        if !path.exists() {
+            self.toast(format!("{} no longer exists", path.display()));
+            self.config.recent_files.retain(|recent| recent != path);
+            let _ = self.config.save();
+            return;
+        }
+        match self.load_from_file(&path.to_path_buf()) {
+            Ok(()) => self.remember_file(path),
+            Err(err) => self.report_load_error(path, err),
+        }
+    }
+
+    // Flag every file button whose audio isn't where the board says it is. Loading carries on
+    // regardless; the flagged pads are drawn differently and can be relinked from the edit popup.
+    // Kept by path rather than by slot, so the flags follow buttons through moves and undo.
+    // Returns how many buttons are flagged.
+    fn mark_missing_files(&mut self) -> usize {
+        self.missing_paths = self
+            .tabs
+            .iter()
+            .flat_map(|tab| &tab.buttons)
+            .filter(|button| !button.name.is_empty() && button.tone.is_none() && !button.path.as_os_str().is_empty())
+            .filter(|button| !button.path.exists())
+            .map(|button| button.path.clone())
+            .collect();
+        self.missing_files().len()
+    }
+
+    // (tab, index) of every button flagged by mark_missing_files()
+    fn missing_files(&self) -> Vec<(usize, usize)> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tab)| tab.buttons.iter().enumerate().filter(|(_, b)| b.is_missing(&self.missing_paths)).map(move |(i, _)| (t, i)))
+            .collect()
+    }
+
+    // Restore the invariants the UI relies on after a load or before a save: at least one tab,
+    // in-range indices, no populated button stranded beyond the grid, and start latencies a
+    // Duration can hold. Returns true if anything had to be fixed.
+    fn validate_and_repair(&mut self) -> bool {
+        let mut repaired = false;
+        if self.tabs.is_empty() {
+            self.tabs.push(MusicTab { name: "Tab 1".to_string(), buttons: Vec::new(), pages: 1, settings: TabSettings::default() });
+            repaired = true;
+        }
+        let mut t = 0;
+        while t < self.tabs.len() {
+            let tab = &mut self.tabs[t];
+            if tab.pages == 0 {
+                tab.pages = 1;
+                repaired = true;
+            }
+            // JSON exports can be edited by hand, and playback turns this into a Duration
+            for button in &mut tab.buttons {
+                if !(button.start_latency_ms.is_finite() && button.start_latency_ms >= 0.0) {
+                    button.start_latency_ms = 0.0;
+                    repaired = true;
+                }
+            }
+            while tab.buttons.last().is_some_and(|button| button.name.is_empty()) {
+                tab.buttons.pop();
+            }
+            let capacity = tab.capacity();
+            if tab.buttons.len() > capacity {
+                repaired = true;
+                // Pull stranded buttons into free slots, spilling the rest into a new tab
+                let overflow: Vec<MusicButton> = tab.buttons.drain(capacity..).filter(|b| !b.name.is_empty()).collect();
+                let mut spill = Vec::new();
+                for button in overflow {
+                    match tab.buttons.iter().position(|b| b.name.is_empty()) {
+                        Some(free) => tab.buttons[free] = button,
+                        None => spill.push(button),
+                    }
+                }
+                if !spill.is_empty() {
+                    let name = format!("{} (overflow)", tab.name);
+                    self.tabs.insert(t + 1, MusicTab { name, buttons: spill, pages: 1, settings: TabSettings::default() });
+                }
+            }
+            t += 1;
+        }
+        if self.current_tab >= self.tabs.len() {
+            self.current_tab = 0;
+            repaired = true;
+        }
+        if let Some((t, i)) = self.current_playing
+            && self.tabs.get(t).and_then(|tab| tab.buttons.get(i)).is_none_or(|b| b.name.is_empty())
+        {
+            self.current_playing = None;
+            repaired = true;
+        }
+        if self.edit_state.editing.is_some_and(|i| i >= self.tabs[self.current_tab].buttons.len()) {
+            self.edit_state.editing = None;
+            repaired = true;
+        }
+        if self.renaming_tab.is_some_and(|t| t >= self.tabs.len()) {
+            self.renaming_tab = None;
+            repaired = true;
+        }
+        repaired
+    }
+
+    fn trigger_button(&mut self, tab: usize, idx: usize) {
+        let Some(button) = self.tabs.get(tab).and_then(|t| t.buttons.get(idx)) else {
+            return;
+        };
+        if button.name.is_empty() {
+            return;
+        }
+        if !self.audio_player.has_output() {
+            self.toast("No audio output device: playback is unavailable");
+            return;
+        }
+        if self.cooling_down(tab, idx) {
+            if self.config.cooldown_action == CooldownAction::Queue && !self.queued_triggers.contains(&(tab, idx)) {
+                self.queued_triggers.push((tab, idx));
+            }
+            return;
+        }
+        // Fading a pad out fires it too, as far as the cooldown goes, but isn't a play
+        let mut started = true;
+        if Some((tab, idx)) == self.current_playing {
+            self.audio_player.fade_out();
+            self.current_playing = None;
+            started = false;
+        } else if self.config.polyphonic {
+            // Layered cues run side by side; each pad starts and stops only its own
+            if self.audio_player.fade_out_layer((tab, idx)) {
+                started = false;
+            } else if let Err(err) = self.audio_player.play_layer((tab, idx), button) {
+                self.report_play_error(tab, idx, err);
+                return;
+            }
+        } else {
+            let result = if self.current_playing.is_some() {
+                let crossfade = Duration::from_millis(self.config.crossfade_ms as u64);
+                self.audio_player.switch_to((tab, idx), button, self.config.transition, crossfade)
+            } else {
+                self.audio_player.play((tab, idx), button)
+            };
+            // The cue on air, if any, is only let go once the new one has opened
+            if let Err(err) = result {
+                self.report_play_error(tab, idx, err);
+                return;
+            }
+            self.audio_player.set_end_fade(self.end_fade_ms(button));
+            self.current_playing = Some((tab, idx));
+        }
+        let button = &mut self.tabs[tab].buttons[idx];
+        button.last_triggered = Some(Instant::now());
+        button.play_error = None;
+        if started {
+            button.info.stats.record_play();
+        }
+    }
+
+    // A press that would start the button again before its cooldown is over. Presses that stop
+    // it always go through, so a cue can be pulled right after it was fired.
+    fn cooling_down(&self, tab: usize, idx: usize) -> bool {
+        let Some(button) = self.tabs.get(tab).and_then(|t| t.buttons.get(idx)) else {
+            return false;
+        };
+        let stopping = Some((tab, idx)) == self.current_playing || (self.config.polyphonic && self.audio_player.is_layered((tab, idx)));
+        !stopping && button.last_triggered.is_some_and(|last| last.elapsed() < Duration::from_millis(button.playback.cooldown_ms as u64))
+    }
+
+    fn end_fade_ms(&self, button: &MusicButton) -> u32 {
+        // A looping cue never reaches its end, so there is nothing to fade into
+        if button.playback.loop_enabled && button.tone.is_none() {
+            0
+        } else {
+            button.playback.end_fade_ms.unwrap_or(self.config.end_fade_ms)
+        }
+    }
+
+    // Start a playing pad again from the top, cutting the running copy without a fade; the new
+    // sink is opened before the old one stops. Pads that aren't playing just trigger.
+    fn restart_button(&mut self, tab: usize, idx: usize) {
+        let key = (tab, idx);
+        let Some(button) = self.tabs.get(tab).and_then(|t| t.buttons.get(idx)) else {
+            return;
+        };
+        let end_fade_ms = self.end_fade_ms(button);
+        let result = if self.current_playing == Some(key) {
+            self.audio_player.play(key, button).map(|()| self.audio_player.set_end_fade(end_fade_ms))
+        } else if self.audio_player.is_layered(key) {
+            self.audio_player.play_layer(key, button)
+        } else {
+            self.trigger_button(tab, idx);
+            return;
+        };
+        if let Err(err) = result {
+            if self.current_playing == Some(key) {
+                self.current_playing = None;
+            }
+            self.report_play_error(tab, idx, err);
+            return;
+        }
+        let button = &mut self.tabs[tab].buttons[idx];
+        button.last_triggered = Some(Instant::now());
+        button.play_error = None;
+        button.info.stats.record_play();
+    }
+
+    fn report_play_error(&mut self, tab: usize, idx: usize, err: Box<dyn std::error::Error>) {
+        let button = &mut self.tabs[tab].buttons[idx];
+        let message = format!("Can't play \"{}\": {}", button.name, err);
+        button.play_error = Some(err.to_string());
+        self.toast(message);
+    }
+
+    // Start, restart or stop the control socket to match the settings
+    fn sync_ipc_server(&mut self) {
+        let wanted = self.config.ipc_enabled.then(|| self.config.ipc_address.clone());
+        let running = self.ipc_server.as_ref().map(|server| server.address.clone());
+        if wanted == running {
+            return;
+        }
+        self.ipc_server = None;
+        if let Some(address) = wanted {
+            match IpcServer::start(&address) {
+                Ok(server) => self.ipc_server = Some(server),
+                Err(err) => {
+                    self.config.ipc_enabled = false;
+                    self.toast(format!("Could not open control socket on {}: {}", address, err));
+                }
+            }
+        }
+    }
+
+    fn sync_web_remote(&mut self) {
+        let wanted = self.config.web_enabled.then(|| self.config.web_address.clone());
+        let running = self.web_remote.as_ref().map(|remote| remote.address.clone());
+        if wanted == running {
+            return;
+        }
+        self.web_remote = None;
+        if let Some(address) = wanted {
+            match WebRemote::start(&address) {
+                Ok(remote) => self.web_remote = Some(remote),
+                Err(err) => {
+                    self.config.web_enabled = false;
+                    self.toast(format!("Could not start the browser remote on {}: {}", address, err));
+                }
+            }
+        }
+    }
+
+    // Hand the web remote a fresh copy of the board a few times a second
+    fn publish_remote_state(&mut self) {
+        let Some(remote) = &mut self.web_remote else {
+            return;
+        };
+        if remote.last_publish.is_some_and(|at| at.elapsed() < WebRemote::PUBLISH_INTERVAL) {
+            return;
+        }
+        remote.last_publish = Some(Instant::now());
+        let (current, player) = (self.current_playing, &self.audio_player);
+        let json = WebRemote::state_json(&self.tabs, |key| current == Some(key) || player.is_layered(key));
+        if let Ok(mut state) = remote.state.lock() {
+            *state = json;
+        }
+        let cue = current.and_then(|(t, i)| self.tabs.get(t)?.buttons.get(i)).map(|button| (button, player.elapsed(), player.is_paused()));
+        if let Ok(mut now_playing) = remote.now_playing.lock() {
+            *now_playing = WebRemote::now_playing_json(cue);
+        }
+    }
+
+    // Open, switch or close the MIDI input to match the settings
+    fn sync_midi_input(&mut self) {
+        let wanted = self.config.midi_enabled.then(|| self.config.midi_port.clone());
+        let running = self.midi_input.as_ref().map(|midi| midi.port.clone());
+        if wanted == running {
+            return;
+        }
+        self.midi_input = None;
+        if let Some(port) = wanted {
+            match MidiListener::start(port.as_deref()) {
+                Ok(midi) => self.midi_input = Some(midi),
+                Err(err) => {
+                    self.config.midi_enabled = false;
+                    self.toast(format!("Could not open MIDI input: {}", err));
+                }
+            }
+        }
+    }
+
+    // Notes fire their buttons like hotkeys, or go to the edit popup while it is learning one
+    fn apply_midi_notes(&mut self) {
+        let Some(midi) = &self.midi_input else {
+            return;
+        };
+        let notes: Vec<u8> = midi.notes.try_iter().collect();
+        for note in notes {
+            if self.edit_state.learning_midi && self.edit_state.editing.is_some() {
+                self.edit_state.learning_midi = false;
+                self.assign_learned_midi_note(note);
+            } else if !self.edit_mode {
+                for (tab, idx) in self.midi_targets(note) {
+                    self.trigger_button(tab, idx);
+                }
+            }
+        }
+    }
+
+    // Register, re-register or drop the raise-window hotkey to match the settings
+    fn sync_raise_hotkey(&mut self) {
+        let wanted = self.config.raise_hotkey_enabled.then(|| self.config.raise_hotkey.trim().to_string());
+        let registered = self.raise_hotkey.as_ref().map(|raise| raise.spec.clone());
+        if wanted == registered {
+            return;
+        }
+        self.raise_hotkey = None;
+        if let Some(spec) = wanted {
+            match RaiseHotkey::register(&spec) {
+                Ok(raise) => self.raise_hotkey = Some(raise),
+                Err(err) => {
+                    self.config.raise_hotkey_enabled = false;
+                    self.toast(format!("Could not register global hotkey {}: {}", spec, err));
+                }
+            }
+        }
+    }
+
+    fn raise_on_hotkey(&self, ctx: &egui::Context) {
+        while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+            if event.state == HotKeyState::Pressed && self.raise_hotkey.as_ref().is_some_and(|raise| raise.hotkey.id() == event.id) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+        }
+    }
+
+    fn apply_remote_commands(&mut self) {
+        let mut commands: Vec<RemoteCommand> = Vec::new();
+        if let Some(server) = &self.ipc_server {
+            commands.extend(server.commands.try_iter());
+        }
+        if let Some(remote) = &self.web_remote {
+            commands.extend(remote.commands.try_iter());
+        }
+        for command in commands {
+            match command {
+                RemoteCommand::Play(tab, idx) => {
+                    if self.current_playing != Some((tab, idx)) && !self.audio_player.is_layered((tab, idx)) {
+                        self.trigger_button(tab, idx);
+                    }
+                }
+                RemoteCommand::Stop => self.stop_all(),
+                RemoteCommand::Fade => self.fade_current(),
+                RemoteCommand::Load(path) => match self.load_from_file(&path) {
+                    Ok(()) => self.remember_file(&path),
+                    Err(err) => self.toast(format!("Remote load of {} failed: {}", path.display(), err)),
+                },
+            }
+        }
+    }
+
+    // (tab, index) of every button whose name contains `query`, ignoring case
+    fn find_buttons(&self, query: &str) -> Vec<(usize, usize)> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.tabs
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tab)| tab.buttons.iter().enumerate().map(move |(idx, button)| (t, idx, button)))
+            .filter(|(_, _, button)| !button.name.is_empty() && button.name.to_lowercase().contains(&query))
+            .map(|(t, idx, _)| (t, idx))
+            .collect()
+    }
+
+    // Show the tab and page holding a button and flash its slot
+    fn reveal_button(&mut self, (tab, idx): (usize, usize)) {
+        self.current_tab = tab;
+        self.current_page = idx / GRID_SLOTS;
+        self.flash = Some(((tab, idx), Instant::now()));
+    }
+
+    // Next populated slot after `idx` in a playlist tab, if any
+    fn playlist_next(&self, tab: usize, idx: usize) -> Option<usize> {
+        let tab = self.tabs.get(tab).filter(|tab| tab.settings.playlist_mode)?;
+        (idx + 1..tab.buttons.len()).find(|&i| !tab.buttons[i].name.is_empty())
+    }
+
+    // Playlist tabs chain their cues: when the current one runs out, the next populated slot
+    // starts. Looping buttons never run out, so the chain holds there until stopped.
+    fn advance_playlist(&mut self) {
+        let Some((tab, idx)) = self.current_playing else {
+            return;
+        };
+        if !self.audio_player.finished() || !self.tabs.get(tab).is_some_and(|tab| tab.settings.playlist_mode) {
+            return;
+        }
+        // At the end of the list the cue just ends like any other
+        if let Some(next) = self.playlist_next(tab, idx) {
+            self.trigger_button(tab, next);
+        }
+    }
+
+    // Rebuild the player on another output device. Whatever was playing stops: its voices
+    // belong to the old stream. An air-check carries on, recording the new stream.
+    fn switch_output(&mut self, device: Option<String>) {
+        self.audio_player.stop();
+        let mut player = AudioPlayer::open(device.as_deref());
+        player.take_over(&mut self.audio_player);
+        self.audio_player = player;
+        self.current_playing = None;
+        self.queued_triggers.clear();
+        if let Some(err) = &self.audio_player.output_error {
+            let message = format!("Couldn't open the output device: {}", err);
+            self.toast(message);
+        }
+    }
+
+    // Frame-driven; reopens the chosen device (or the default, if it's gone) when the one in
+    // use stops pulling audio
+    fn watch_output(&mut self) {
+        if !self.audio_player.output_stalled() || self.last_output_reopen.is_some_and(|at| at.elapsed() < Duration::from_secs(5)) {
+            return;
+        }
+        self.last_output_reopen = Some(Instant::now());
+        self.toast("The audio output stopped responding; reopening it");
+        self.switch_output(self.config.output_device.clone());
+    }
+
+    // Panic button: silence every voice at once, including cues still fading out, and drop
+    // triggers waiting on a cooldown. Harmless when nothing plays.
+    fn stop_all(&mut self) {
+        self.audio_player.stop();
+        self.current_playing = None;
+        self.queued_triggers.clear();
+    }
+
+    // Alt+click: audition a pad on the pre-listen output without putting it on air
+    fn prelisten_button(&mut self, tab: usize, idx: usize) {
+        let Some(button) = self.tabs.get(tab).and_then(|tab| tab.buttons.get(idx)).filter(|button| !button.name.is_empty()) else {
+            return;
+        };
+        if self.audio_player.prelisten.is_none() {
+            self.toast("Pick a pre-listen device under Settings first");
+        } else if let Err(err) = self.audio_player.prelisten((tab, idx), button) {
+            let message = format!("Couldn't pre-listen to \"{}\": {}", button.name, err);
+            self.toast(message);
+        }
+    }
+
+    // Pause or resume the current cue; nothing happens when nothing is playing
+    fn toggle_pause(&mut self) {
+        if self.current_playing.is_none() {
+            return;
+        }
+        if self.audio_player.is_paused() {
+            self.audio_player.resume();
+        } else {
+            self.audio_player.pause();
+        }
+    }
+
+    // A cue that played out on its own is no longer current
+    fn detect_track_end(&mut self) {
+        if self.audio_player.release_finished() {
+            self.current_playing = None;
+        }
+    }
+
+    fn fire_queued_triggers(&mut self) {
+        let ready: Vec<(usize, usize)> = self
+            .queued_triggers
+            .iter()
+            .copied()
+            .filter(|&(t, i)| {
+                self.tabs.get(t).and_then(|tab| tab.buttons.get(i)).is_none_or(|button| {
+                    button.last_triggered.is_none_or(|last| last.elapsed() >= Duration::from_millis(button.playback.cooldown_ms as u64))
+                })
+            })
+            .collect();
+        self.queued_triggers.retain(|key| !ready.contains(key));
+        for (tab, idx) in ready {
+            self.trigger_button(tab, idx);
+        }
+    }
+
+    // All populated buttons bound to `key`, as (tab, index)
+    fn buttons_with_hotkey(&self, key: egui::Key) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        for (t, tab) in self.tabs.iter().enumerate() {
+            for (i, button) in tab.buttons.iter().enumerate() {
+                if !button.name.is_empty() && button.hotkey == Some(key) {
+                    found.push((t, i));
+                }
+            }
+        }
+        found
+    }
+
+    // A fresh key press that may fire a pad. Chords with Ctrl/Alt/Cmd are left to shortcuts
+    // (including the global raise hotkey), so Ctrl+Alt+R doesn't also fire the pad bound to R.
+    fn pad_key(event: &egui::Event) -> Option<egui::Key> {
+        match event {
+            egui::Event::Key { key, pressed: true, repeat: false, modifiers, .. }
+                if !(modifiers.ctrl || modifiers.alt || modifiers.command) =>
+            {
+                Some(*key)
+            }
+            _ => None,
+        }
+    }
+
+    fn buttons_with_midi_note(&self, note: u8) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        for (t, tab) in self.tabs.iter().enumerate() {
+            for (i, button) in tab.buttons.iter().enumerate() {
+                if !button.name.is_empty() && button.playback.midi_note == Some(note) {
+                    found.push((t, i));
+                }
+            }
+        }
+        found
+    }
+
+    // Which buttons a press of `key` should fire under the configured conflict policy
+    fn hotkey_targets(&self, key: egui::Key) -> Vec<(usize, usize)> {
+        self.conflict_targets(self.buttons_with_hotkey(key))
+    }
+
+    // MIDI notes shared by several buttons follow the hotkey policy
+    fn midi_targets(&self, note: u8) -> Vec<(usize, usize)> {
+        self.conflict_targets(self.buttons_with_midi_note(note))
+    }
+
+    // Firing all of them only works as layers: on the single-cue player each would fade out
+    // the one before, so outside polyphonic mode TriggerAll fires like PreferCurrentTab
+    fn conflict_targets(&self, found: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        if (self.config.hotkey_conflict == HotkeyConflict::TriggerAll && self.config.polyphonic) || found.len() <= 1 {
+            return found;
+        }
+        let preferred = found.iter().find(|(t, _)| *t == self.current_tab).unwrap_or(&found[0]);
+        vec![*preferred]
+    }
+
+    // Slot fired by a digit key: 1-9 then 0 map to the first ten slots of the visible page.
+    // A digit bound as a button's own hotkey belongs to that button instead.
+    fn quickfire_slot(&self, key: egui::Key) -> Option<usize> {
+        use egui::Key::*;
+        if !self.config.quickfire_enabled || !self.buttons_with_hotkey(key).is_empty() {
+            return None;
+        }
+        let n = [Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0].iter().position(|&digit| digit == key)?;
+        let tab = &self.tabs[self.current_tab];
+        if tab.settings.fit_to_content {
+            // The first ten pads shown, gaps closed up
+            return tab.fitted_slots().get(n).copied();
+        }
+        Some(self.current_page * GRID_SLOTS + n)
+    }
+
+    // The fade key, unless a button has it as its own hotkey
+    fn is_fade_key(&self, key: egui::Key) -> bool {
+        self.config.fade_key_enabled && key == FADE_KEY && self.buttons_with_hotkey(key).is_empty()
+    }
+
+    // Same as clicking the playing pad: a one-second fade of the current cue
+    fn fade_current(&mut self) {
+        if self.current_playing.take().is_some() {
+            self.audio_player.fade_out();
+        }
+    }
+
+    // Hotkeys shared by more than one button, for the warning badge
+    fn conflicting_hotkeys(&self) -> Vec<egui::Key> {
+        let mut seen = Vec::new();
+        let mut conflicts = Vec::new();
+        for button in self.tabs.iter().flat_map(|tab| &tab.buttons) {
+            if let Some(key) = button.hotkey.filter(|_| !button.name.is_empty()) {
+                if seen.contains(&key) && !conflicts.contains(&key) {
+                    conflicts.push(key);
+                }
+                seen.push(key);
+            }
+        }
+        conflicts
+    }
+
+    // Validate a key captured in the edit popup against the conflict policy
+    fn assign_captured_hotkey(&mut self, key: egui::Key) {
+        let editing = self.edit_state.editing.map(|idx| (self.current_tab, idx));
+        let others: Vec<_> = self.buttons_with_hotkey(key).into_iter().filter(|&k| Some(k) != editing).collect();
+        if let Some(&(t, i)) = others.first() {
+            let owner = self.tabs[t].buttons[i].name.clone();
+            if self.config.hotkey_conflict == HotkeyConflict::Reject {
+                self.toast(format!("{} is already assigned to \"{}\"", key.name(), owner));
+                return;
+            }
+            self.toast(format!("{} is also assigned to \"{}\"", key.name(), owner));
+        }
+        self.edit_state.hotkey_buf = Some(key);
+    }
+
+    // The same for a note picked up by MIDI Learn
+    fn assign_learned_midi_note(&mut self, note: u8) {
+        let editing = self.edit_state.editing.map(|idx| (self.current_tab, idx));
+        let others: Vec<_> = self.buttons_with_midi_note(note).into_iter().filter(|&k| Some(k) != editing).collect();
+        if let Some(&(t, i)) = others.first() {
+            let owner = self.tabs[t].buttons[i].name.clone();
+            if self.config.hotkey_conflict == HotkeyConflict::Reject {
+                self.toast(format!("MIDI note {} is already assigned to \"{}\"", MidiListener::note_name(note), owner));
+                return;
+            }
+            self.toast(format!("MIDI note {} is also assigned to \"{}\"", MidiListener::note_name(note), owner));
+        }
+        self.edit_state.playback_buf.midi_note = Some(note);
+    }
+
+    // Open a file's container without decoding any audio
+    fn probe(path: &Path) -> Option<ProbeResult> {
+        let file = std::fs::File::open(path).ok()?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+        get_probe().format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default()).ok()
+    }
+
+    fn get_duration_with_symphonia(path: &Path) -> Option<f32> {
+        let format = Self::probe(path)?.format;
+        let track = format.default_track()?;
+        let codec_params = &track.codec_params;
+        let duration = codec_params.n_frames?;
+        let sample_rate = codec_params.sample_rate?;
+        Some(duration as f32 / sample_rate as f32)
+    }
+
+    // Tags from the probe (e.g. ID3v2 in front of an MP3) and from the container itself
+    // (Vorbis comments, MP4 atoms). A file without tags, or one symphonia can't open, just
+    // yields an empty TrackInfo.
+    fn read_track_info(path: &Path) -> TrackInfo {
+        let mut info = TrackInfo::default();
+        let Some(mut probed) = Self::probe(path) else {
+            return info;
+        };
+        info.sample_rate = probed.format.default_track().and_then(|track| track.codec_params.sample_rate);
+        let mut collect = |revision: &MetadataRevision| {
+            for tag in revision.tags() {
+                let value = tag.value.to_string();
+                match tag.std_key {
+                    Some(StandardTagKey::TrackTitle) => info.title = Some(value.clone()),
+                    Some(StandardTagKey::Artist) => info.artist = Some(value.clone()),
+                    _ => {}
+                }
+                info.tags.push((tag.key.clone(), value));
+            }
+        };
+        if let Some(metadata) = probed.metadata.get()
+            && let Some(revision) = metadata.current()
+        {
+            collect(revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            collect(revision);
+        }
+        info
+    }
+
+    // Cover art embedded in the file (the front cover if one is marked, else the first
+    // picture), shrunk to a PNG thumbnail; None when there is none or it can't be decoded
+    fn read_artwork(path: &Path) -> Option<Vec<u8>> {
+        let mut probed = Self::probe(path)?;
+        let mut visuals = Vec::new();
+        if let Some(metadata) = probed.metadata.get()
+            && let Some(revision) = metadata.current()
+        {
+            visuals.extend(revision.visuals().iter().map(|visual| (visual.usage, visual.data.clone())));
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            visuals.extend(revision.visuals().iter().map(|visual| (visual.usage, visual.data.clone())));
+        }
+        let front = visuals.iter().position(|(usage, _)| *usage == Some(StandardVisualKey::FrontCover)).unwrap_or(0);
+        Self::artwork_thumbnail(&visuals.get(front)?.1)
+    }
+
+    fn artwork_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+        let thumbnail = image::load_from_memory(data).ok()?.thumbnail(ARTWORK_SIZE, ARTWORK_SIZE);
+        let mut png = std::io::Cursor::new(Vec::new());
+        thumbnail.write_to(&mut png, image::ImageOutputFormat::Png).ok()?;
+        Some(png.into_inner())
+    }
+
+    fn artwork_image(png: &[u8]) -> Option<egui::ColorImage> {
+        let rgba = image::load_from_memory(png).ok()?.to_rgba8();
+        Some(egui::ColorImage::from_rgba_unmultiplied([rgba.width() as usize, rgba.height() as usize], rgba.as_raw()))
+    }
+
+    // Button name for a newly picked file: its tags if it has a title, else the file name
+    fn imported_name(path: &Path, info: &TrackInfo) -> String {
+        info.display_name().unwrap_or_else(|| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+    }
+
+    // Returns (waveform, waveform_min, duration, loudness_db); waveform_min is empty in peak mode
+    // `progress` gets the share of the file decoded so far, at most once every
+    // WAVEFORM_PROGRESS_STEP; never for files whose container doesn't state a length
+    fn generate_waveform_and_duration(
+        path: &PathBuf,
+        mode: WaveformMode,
+        points: usize,
+        progress: &mut dyn FnMut(f32),
+    ) -> Result<WaveformData, Box<dyn std::error::Error>> {
+        let file = BufReader::new(File::open(path)?);
+        let decoder = Decoder::new(file)?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        let expected = Self::get_duration_with_symphonia(path).map(|secs| secs * sample_rate as f32 * channels as f32).filter(|&total| total > 0.0);
+        let mut samples = Vec::new();
+        let mut next_report = WAVEFORM_PROGRESS_STEP;
+        for sample in decoder.convert_samples::<f32>() {
+            samples.push(sample);
+            if let Some(total) = expected
+                && samples.len() as f32 >= next_report * total
+            {
+                let fraction = (samples.len() as f32 / total).min(1.0);
+                progress(fraction);
+                next_report = fraction + WAVEFORM_PROGRESS_STEP;
+            }
+        }
+        let mono = Self::downmix(&samples, channels);
+        let (waveform, waveform_min) = Self::waveform_from_samples(&mono, mode, points);
+        // Playback runs on this same decoder, so the progress line only ends with the track if the
+        // duration is measured here too; a container's stated length can disagree (VBR MP3s, odd
+        // headers) and is only used when nothing decodes
+        let duration = match mono.len() {
+            0 => Self::get_duration_with_symphonia(path).unwrap_or(0.0),
+            frames => frames as f32 / sample_rate.max(1) as f32,
+        };
+        Ok((waveform, waveform_min, duration, Self::measure_loudness(&mono, sample_rate)))
+    }
+
+    // Rough integrated loudness in dB: the mean power of 400 ms blocks, leaving out silent
+    // ones and those more than 10 dB under the average (the gating of BS.1770, without its
+    // K-weighting). LOUDNESS_FLOOR_DB for silence.
+    fn measure_loudness(mono: &[f32], sample_rate: u32) -> f32 {
+        let block = (sample_rate as usize * 2 / 5).max(1);
+        let powers: Vec<f32> = mono
+            .chunks(block)
+            .map(|chunk| chunk.iter().filter(|s| s.is_finite()).map(|s| s * s).sum::<f32>() / chunk.len() as f32)
+            .collect();
+        let db = |power: f32| 10.0 * power.max(1e-12).log10();
+        let gated_mean = |threshold: f32| {
+            let kept: Vec<f32> = powers.iter().copied().filter(|&power| db(power) > threshold).collect();
+            (!kept.is_empty()).then(|| kept.iter().sum::<f32>() / kept.len() as f32)
+        };
+        let Some(average) = gated_mean(LOUDNESS_FLOOR_DB) else {
+            return LOUDNESS_FLOOR_DB;
+        };
+        db(gated_mean(db(average) - 10.0).unwrap_or(average))
+    }
+
+    // Average the channels of each frame; chunking interleaved samples would mix channels
+    // into every point of the shape
+    fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+        let channels = channels.max(1) as usize;
+        if channels == 1 {
+            return samples.to_vec();
+        }
+        samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / frame.len() as f32).collect()
+    }
+
+    // `points` points however long the file, fewer only when it has fewer frames:
+    // (waveform, waveform_min), the latter only for MinMax
+    fn waveform_from_samples(mono: &[f32], mode: WaveformMode, points: usize) -> (Vec<f32>, Vec<f32>) {
+        let points = points.min(mono.len());
+        let chunks = (0..points).map(|i| &mono[i * mono.len() / points..(i + 1) * mono.len() / points]);
+        match mode {
+            WaveformMode::MinMax => chunks
+                .map(|chunk| {
+                    let max = chunk.iter().copied().fold(f32::MIN, f32::max);
+                    let min = chunk.iter().copied().fold(f32::MAX, f32::min);
+                    (max, min)
+                })
+                .unzip(),
+            // f32::max/min skip NaN, so a corrupt sample can't poison (or panic) a point
+            WaveformMode::Peak => (chunks.map(|chunk| chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))).collect(), Vec::new()),
+            WaveformMode::Rms => (
+                chunks
+                    .map(|chunk| (chunk.iter().filter(|s| s.is_finite()).map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+                    .collect(),
+                Vec::new(),
+            ),
+        }
+    }
+
+    // Decodes only on a cache miss; failing to write the cache is not worth reporting
+    fn cached_waveform(
+        cache: Option<&WaveformCache>,
+        path: &PathBuf,
+        mode: WaveformMode,
+        points: usize,
+        progress: &mut dyn FnMut(f32),
+    ) -> Result<WaveformData, Box<dyn std::error::Error>> {
+        if let Some(hit) = cache.and_then(|cache| cache.get(path, mode, points)) {
+            return Ok(hit);
+        }
+        let waveform = Self::generate_waveform_and_duration(path, mode, points, progress)?;
+        if let Some(cache) = cache {
+            let _ = cache.put(path, mode, points, &waveform);
+        }
+        Ok(waveform)
+    }
+
+    fn clear_waveform_cache(&mut self) {
+        match WaveformCache::open().map(|cache| cache.clear()) {
+            Some(Ok(())) => self.toast("Waveform cache cleared"),
+            Some(Err(err)) => self.toast(format!("Couldn't clear the waveform cache: {}", err)),
+            None => self.toast("No cache directory available"),
+        }
+    }
+
+    // Decoding a long file takes seconds, so it runs on its own thread; drain_waveforms()
+    // fills the button in once it's done
+    fn request_waveform(&mut self, key: (usize, usize), path: PathBuf) {
+        self.spawn_decode(key, path, false);
+    }
+
+    // The same for a file just put on a button: its tags are read on that thread too (a slow
+    // disk or share would stall the UI otherwise) and name the button once they're in
+    fn request_import(&mut self, key: (usize, usize), path: PathBuf) {
+        self.spawn_decode(key, path, true);
+    }
+
+    fn spawn_decode(&mut self, key: (usize, usize), path: PathBuf, read_info: bool) {
+        self.pending_waveforms.insert(key, path.clone());
+        let sender = self.waveform_jobs.sender.clone();
+        let (mode, points) = (self.config.waveform_mode, self.config.waveform_points());
+        thread::spawn(move || {
+            let info = read_info.then(|| TrackInfo { artwork: Self::read_artwork(&path).unwrap_or_default(), ..Self::read_track_info(&path) });
+            let mut progress = |fraction| {
+                let _ = sender.send(WaveformMessage::Progress(path.clone(), fraction));
+            };
+            let result = Self::cached_waveform(WaveformCache::open().as_ref(), &path, mode, points, &mut progress).map_err(|err| err.to_string());
+            let _ = sender.send(WaveformMessage::Done(Box::new(WaveformJob { key, path, result, info })));
+        });
+    }
+
+    fn drain_waveforms(&mut self) {
+        while let Ok(message) = self.waveform_jobs.receiver.try_recv() {
+            let job = match message {
+                WaveformMessage::Progress(path, fraction) => {
+                    self.waveform_progress.insert(path, fraction);
+                    continue;
+                }
+                WaveformMessage::Done(job) => *job,
+            };
+            self.waveform_progress.remove(&job.path);
+            // Superseded by a newer request for the same slot
+            if self.pending_waveforms.get(&job.key) != Some(&job.path) {
+                continue;
+            }
+            self.pending_waveforms.remove(&job.key);
+            let normalize = self.pending_normalize.remove(&job.key);
+            let (t, i) = job.key;
+            let Some(button) = self.tabs.get_mut(t).and_then(|tab| tab.buttons.get_mut(i)).filter(|button| button.path == job.path) else {
+                continue;
+            };
+            if let Some(info) = job.info {
+                // The file name stood in until now; a name typed meanwhile is kept
+                let placeholder = Self::imported_name(&job.path, &TrackInfo::default());
+                let name = Self::imported_name(&job.path, &info);
+                if button.name == placeholder {
+                    button.name = name.clone();
+                }
+                if t == self.current_tab && self.edit_state.editing == Some(i) && self.edit_state.name_buf == placeholder {
+                    self.edit_state.name_buf = name;
+                }
+                button.info = TrackInfo { stats: button.info.stats, ..info };
+            }
+            match job.result {
+                Ok((waveform, waveform_min, duration, loudness)) => {
+                    self.waveform_scales.remove(&job.path);
+                    button.set_waveform(waveform, waveform_min);
+                    button.duration = duration;
+                    button.info.loudness_db = Some(loudness);
+                    if normalize {
+                        button.normalize_to(self.config.loudness_target_db);
+                    }
+                }
+                Err(err) => {
+                    let message = format!("Couldn't read \"{}\": {}", button.name, err);
+                    self.toast(message);
+                }
+            }
+        }
+    }
+
+    // Swap two slots of a tab (dropping onto an empty slot swaps with a blank button) and keep
+    // everything that refers to a slot by index pointing at the same button
+    fn swap_buttons(&mut self, tab: usize, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        self.record_undo("move button");
+        self.tabs[tab].ensure_slot(a.max(b));
+        self.tabs[tab].buttons.swap(a, b);
+        let remap = |key: (usize, usize)| match key {
+            (t, i) if t == tab && i == a => (t, b),
+            (t, i) if t == tab && i == b => (t, a),
+            other => other,
+        };
+        self.current_playing = self.current_playing.map(remap);
+        for key in &mut self.queued_triggers {
+            *key = remap(*key);
+        }
+        self.pending_waveforms = self.pending_waveforms.drain().map(|(key, path)| (remap(key), path)).collect();
+        self.pending_normalize = self.pending_normalize.drain().map(remap).collect();
+        self.audio_player.remap_keys(remap);
+        if tab == self.current_tab {
+            self.edit_state.editing = self.edit_state.editing.map(|i| remap((tab, i)).1);
+        }
+    }
+
+    fn add_music_at(&mut self, slot: usize) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("Audio", AUDIO_EXTENSIONS)
+            .pick_file()
+        {
+            self.record_undo("add music");
+            let tab = &mut self.tabs[self.current_tab];
+            tab.ensure_slot(slot);
+            tab.buttons[slot] = MusicButton {
+                name: Self::imported_name(&path, &TrackInfo::default()),
+                path: path.clone(),
+                ..Default::default()
+            };
+            self.request_import((self.current_tab, slot), path);
+        }
+    }
+
+    // Fill the empty slots of the current tab with the folder's audio files in name order,
+    // continuing on new tabs named after the folder once it is full. Waveforms, tags and cover
+    // art are read in the background. Returns (imported, skipped), skipped being files that aren't supported audio.
+    fn import_folder(&mut self, dir: &Path) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort_by_key(|path| path.file_name().map(|name| name.to_string_lossy().to_lowercase()));
+        let (audio, skipped): (Vec<PathBuf>, Vec<PathBuf>) = files.into_iter().partition(|path| {
+            path.extension().is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        });
+        if audio.is_empty() {
+            return Ok((0, skipped.len()));
+        }
+        self.record_undo("import folder");
+        let folder = dir.file_name().map_or_else(|| "Imported".to_string(), |name| name.to_string_lossy().into_owned());
+        let mut tab = self.current_tab;
+        let mut slot = 0;
+        let mut overflow = 0;
+        for path in &audio {
+            // Next empty slot, opening a new tab when the current one is full
+            loop {
+                let current = &self.tabs[tab];
+                if slot >= current.capacity() {
+                    overflow += 1;
+                    let name = if overflow == 1 { folder.clone() } else { format!("{} ({})", folder, overflow) };
+                    self.tabs.push(MusicTab { name, buttons: Vec::new(), pages: 1, settings: TabSettings::default() });
+                    tab = self.tabs.len() - 1;
+                    slot = 0;
+                } else if current.buttons.get(slot).is_some_and(|button| !button.name.is_empty()) {
+                    slot += 1;
+                } else {
+                    break;
+                }
+            }
+            self.tabs[tab].ensure_slot(slot);
+            self.tabs[tab].buttons[slot] = MusicButton {
+                name: Self::imported_name(path, &TrackInfo::default()),
+                path: path.clone(),
+                ..Default::default()
+            };
+            self.request_import((tab, slot), path.clone());
+            slot += 1;
+        }
+        Ok((audio.len(), skipped.len()))
+    }
+
+    // Set the volume of every file button on `tab` (all tabs for None) so they play at the
+    // loudness target, as one undo step. Buttons not measured yet are decoded again and levelled
+    // when that finishes. Returns (levelled now, still being measured).
+    fn normalize_levels(&mut self, tab: Option<usize>) -> (usize, usize) {
+        let keys: Vec<(usize, usize)> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .filter(|(t, _)| tab.is_none_or(|only| only == *t))
+            .flat_map(|(t, tab)| {
+                tab.buttons
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, button)| !button.name.is_empty() && button.tone.is_none() && !button.is_missing(&self.missing_paths))
+                    .map(move |(i, _)| (t, i))
+            })
+            .collect();
+        if keys.is_empty() {
+            return (0, 0);
+        }
+        self.record_undo("normalize levels");
+        let target = self.config.loudness_target_db;
+        let (mut levelled, mut measuring) = (0, 0);
+        for (t, i) in keys {
+            let button = &mut self.tabs[t].buttons[i];
+            if button.normalize_to(target) {
+                levelled += 1;
+            } else if button.info.loudness_db.is_none() {
+                self.pending_normalize.insert((t, i));
+                measuring += 1;
+                let path = button.path.clone();
+                if !self.pending_waveforms.contains_key(&(t, i)) {
+                    self.request_waveform((t, i), path);
+                }
+            }
+        }
+        (levelled, measuring)
+    }
+
+    // Call before changing the board so the change can be undone
+    fn record_undo(&mut self, action: &'static str) {
+        self.history.record(action, &mut self.tabs, self.current_tab);
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.history.undo.pop() {
+            let current = self.history.capture(snapshot.action, &mut self.tabs, self.current_tab);
+            self.history.redo.push(current);
+            self.apply_snapshot(snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.history.redo.pop() {
+            let current = self.history.capture(snapshot.action, &mut self.tabs, self.current_tab);
+            self.history.undo.push(current);
+            self.apply_snapshot(snapshot);
+        }
+    }
+
+    // Playback carries on. What refers to a pad by slot (the current cue, layers, voices,
+    // queued triggers) follows its file to wherever the snapshot puts it, preferring the same
+    // slot; cues whose file is no longer on the board are let go.
+    fn apply_snapshot(&mut self, snapshot: Snapshot) {
+        let sources: Vec<((usize, usize), PathBuf, Option<TestTone>)> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tab)| tab.buttons.iter().enumerate().map(move |(i, button)| ((t, i), button)))
+            .map(|(key, button)| (key, button.path.clone(), button.tone))
+            .collect();
+        // Plays since the snapshot aren't undone; they follow their file
+        let stats: HashMap<PathBuf, PlayStats> = self
+            .tabs
+            .iter()
+            .flat_map(|tab| &tab.buttons)
+            .filter(|button| !button.path.as_os_str().is_empty())
+            .map(|button| (button.path.clone(), button.info.stats))
+            .collect();
+        self.current_tab = snapshot.current_tab;
+        self.tabs = self.history.restore(snapshot);
+        for button in self.tabs.iter_mut().flat_map(|tab| &mut tab.buttons) {
+            if let Some(stats) = stats.get(&button.path) {
+                button.info.stats = *stats;
+            }
+        }
+        let holds = |(t, i): (usize, usize), path: &PathBuf, tone: &Option<TestTone>| {
+            self.tabs.get(t).and_then(|tab| tab.buttons.get(i)).is_some_and(|button| &button.path == path && &button.tone == tone)
+        };
+        let moved: HashMap<(usize, usize), (usize, usize)> = sources
+            .iter()
+            .filter_map(|(key, path, tone)| {
+                let mut slots = self.tabs.iter().enumerate().flat_map(|(t, tab)| (0..tab.buttons.len()).map(move |i| (t, i)));
+                let found = if holds(*key, path, tone) { Some(*key) } else { slots.find(|&slot| holds(slot, path, tone)) };
+                found.map(|found| (*key, found))
+            })
+            .collect();
+        self.current_playing = self.current_playing.and_then(|key| moved.get(&key).copied());
+        self.queued_triggers = self.queued_triggers.iter().filter_map(|key| moved.get(key).copied()).collect();
+        self.audio_player.layers.retain(|key, _| moved.contains_key(key));
+        self.audio_player.remap_keys(|key| moved.get(&key).copied().unwrap_or(key));
+        let missing: Vec<_> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tab)| tab.buttons.iter().enumerate().map(move |(i, button)| ((t, i), button)))
+            .filter(|(_, button)| button.waveform.is_empty() && !button.path.as_os_str().is_empty())
+            .map(|(key, button)| (key, button.path.clone()))
+            .collect();
+        for (key, path) in missing {
+            self.request_waveform(key, path);
+        }
+        // Levelling still waiting for a decode is undone with the rest
+        self.pending_normalize.clear();
+        self.edit_state.editing = None;
+        self.edit_state.pending_replace = None;
+        self.renaming_tab = None;
+        self.confirm_remove_tab = None;
+        self.audio_player.preloaded.clear();
+        self.validate_and_repair();
+        self.current_page = self.current_page.min(self.tabs[self.current_tab].pages.max(1) - 1);
+    }
+
+    // A slot holding anything worth keeping: a named button or one with a file
+    fn slot_in_use(&self, (tab, idx): (usize, usize)) -> bool {
+        self.tabs
+            .get(tab)
+            .and_then(|tab| tab.buttons.get(idx))
+            .is_some_and(|button| !button.name.is_empty() || !button.path.as_os_str().is_empty())
+    }
+
+    // Point a button at another file. Its color, hotkey and playback settings stay; what came
+    // from the old file (name, tags, artwork, waveform) is replaced.
+    fn change_music(&mut self, (tab, idx): (usize, usize), path: PathBuf) {
+        self.record_undo("change music");
+        let name = Self::imported_name(&path, &TrackInfo::default());
+        let Some(button) = self.tabs.get_mut(tab).and_then(|tab| tab.buttons.get_mut(idx)) else {
+            return;
+        };
+        button.name = name.clone();
+        button.path = path.clone();
+        button.info = TrackInfo::default();
+        button.set_waveform(Vec::new(), Vec::new());
+        button.duration = 0.0;
+        button.start_latency_ms = 0.0;
+        button.tone = None;
+        button.play_error = None;
+        self.missing_paths.remove(&path);
+        // Trim points were set for the old file (its plays went with the info above)
+        button.playback.start_offset = 0.0;
+        button.playback.end_offset = 0.0;
+        self.request_import((tab, idx), path);
+        if tab == self.current_tab && self.edit_state.editing == Some(idx) {
+            self.edit_state.name_buf = name;
+            self.edit_state.playback_buf.start_offset = 0.0;
+            self.edit_state.playback_buf.end_offset = 0.0;
+        }
+    }
+
+    // Point a button whose file moved at its new location. Unlike change_music() the name,
+    // settings and tags stay; only the waveform is decoded again from the new file.
+    fn relink_button(&mut self, (tab, idx): (usize, usize), path: PathBuf) {
+        if self.tabs.get(tab).and_then(|tab| tab.buttons.get(idx)).is_none() {
+            return;
+        }
+        self.record_undo("relink");
+        let button = &mut self.tabs[tab].buttons[idx];
+        button.path = path.clone();
+        button.play_error = None;
+        self.missing_paths.remove(&path);
+        button.set_waveform(Vec::new(), Vec::new());
+        self.request_waveform((tab, idx), path);
+    }
+
+    // Put a slot back to the empty button, stopping playback first if it is on air
+    fn delete_button(&mut self, tab: usize, idx: usize) {
+        if self.tabs.get(tab).and_then(|tab| tab.buttons.get(idx)).is_none() {
+            return;
+        }
+        self.record_undo("delete");
+        let button = &mut self.tabs[tab].buttons[idx];
+        *button = MusicButton::default();
+        if self.current_playing == Some((tab, idx)) {
+            self.audio_player.stop();
+            self.current_playing = None;
+        }
+        if let Some(layer) = self.audio_player.layers.remove(&(tab, idx)) {
+            layer.sink.stop();
+        }
+        self.queued_triggers.retain(|&key| key != (tab, idx));
+        self.pending_waveforms.remove(&(tab, idx));
+        self.pending_normalize.remove(&(tab, idx));
+        if tab == self.current_tab && self.edit_state.editing == Some(idx) {
+            self.edit_state.editing = None;
+        }
+    }
+
+    // Apply the edit popup's buffers to the button and close the popup
+    fn commit_edit(&mut self, edit_idx: usize) {
+        self.edit_state.editing = None;
+        let Some(mut button) = self.tabs[self.current_tab].buttons.get(edit_idx).cloned() else {
+            return;
+        };
+        button.name = self.edit_state.name_buf.clone();
+        button.color = self.edit_state.color_buf;
+        button.hotkey = self.edit_state.hotkey_buf;
+        button.playback = self.edit_state.playback_buf;
+        if let Some(tone) = self.edit_state.tone_buf
+            && button.tone != Some(tone)
+        {
+            // Keep a custom name, but follow the parameters if it was the generated one
+            if button.tone.is_some_and(|old| button.name == old.label()) {
+                button.name = tone.label();
+            }
+            button.tone = Some(tone);
+            button.duration = tone.duration;
+            button.set_waveform(Self::tone_waveform(&tone), Vec::new());
+        }
+        // Saving without changes (or clicking away in save mode) leaves no undo step
+        if button != self.tabs[self.current_tab].buttons[edit_idx] {
+            self.record_undo("edit");
+            self.tabs[self.current_tab].buttons[edit_idx] = button;
+        }
+    }
+
+    fn add_tone_at(&mut self, slot: usize) {
+        self.record_undo("add test tone");
+        let tone = TestTone::default();
+        let tab = &mut self.tabs[self.current_tab];
+        tab.ensure_slot(slot);
+        tab.buttons[slot] = MusicButton {
+            name: tone.label(),
+            color: Color32::from_rgb(120, 120, 120),
+            waveform: Self::tone_waveform(&tone),
+            duration: tone.duration,
+            tone: Some(tone),
+            ..Default::default()
+        };
+    }
+
+    // Puts a copy of the clipboard button into a slot of the current tab. The waveform, artwork
+    // and settings come with it, so nothing is decoded again.
+    fn paste_at(&mut self, slot: usize) {
+        if let Some(button) = self.clipboard.clone() {
+            self.place_copy(slot, button);
+        }
+    }
+
+    // Clones the button into the first empty slot of its tab, including the tab's later pages.
+    // Returns the new slot.
+    fn duplicate_button(&mut self, idx: usize) -> Result<usize, &'static str> {
+        let tab = &self.tabs[self.current_tab];
+        let button = tab.buttons.get(idx).filter(|button| !button.name.is_empty()).ok_or("No such button")?.clone();
+        let slot = (0..tab.pages * GRID_SLOTS)
+            .find(|&slot| tab.buttons.get(slot).is_none_or(|button| button.name.is_empty()))
+            .ok_or("No free slot left on this tab; add a page first")?;
+        self.place_copy(slot, button);
+        Ok(slot)
+    }
+
+    fn place_copy(&mut self, slot: usize, mut button: MusicButton) {
+        self.record_undo("paste");
+        button.last_triggered = None;
+        button.play_error = None;
+        // The copy hasn't been played yet
+        button.info.stats = PlayStats::default();
+        // A shared hotkey is only pasted along where the settings allow sharing
+        if let Some(key) = button.hotkey
+            && self.config.hotkey_conflict == HotkeyConflict::Reject
+            && !self.buttons_with_hotkey(key).is_empty()
+        {
+            button.hotkey = None;
+            self.toast(format!("{} is already assigned, so the copy has no hotkey", key.name()));
+        }
+        if let Some(note) = button.playback.midi_note
+            && self.config.hotkey_conflict == HotkeyConflict::Reject
+            && !self.buttons_with_midi_note(note).is_empty()
+        {
+            button.playback.midi_note = None;
+            self.toast(format!("MIDI note {} is already assigned, so the copy has none", MidiListener::note_name(note)));
+        }
+        // Copied while its waveform was still being decoded
+        let decode = (button.waveform.is_empty() && button.tone.is_none()).then(|| button.path.clone());
+        let tab = &mut self.tabs[self.current_tab];
+        tab.ensure_slot(slot);
+        tab.buttons[slot] = button;
+        if let Some(path) = decode {
+            self.request_waveform((self.current_tab, slot), path);
+        }
+    }
+
+    // What can go into an empty slot; its context menu, or a plain click while a button is copied
+    fn empty_slot_menu(ui: &mut egui::Ui, edit_state: &mut EditState, clipboard: Option<&MusicButton>, slot: usize) {
+        if let Some(copied) = clipboard
+            && ui.button(format!("Paste \"{}\" here", copied.name)).clicked()
+        {
+            edit_state.pending_paste_slot = Some(slot);
+            ui.close_menu();
+        }
+        if ui.button("Add music…").clicked() {
+            edit_state.pending_music_slot = Some(slot);
+            ui.close_menu();
+        }
+        if ui.button("Add test tone").clicked() {
+            edit_state.pending_tone_slot = Some(slot);
+            ui.close_menu();
+        }
+    }
+
+    // An empty or not yet created grid slot. A click adds music, or offers to paste while a
+    // button is copied; returns the slot's rect.
+    fn empty_slot_ui(ui: &mut egui::Ui, size: Vec2, id: egui::Id, config: &AppConfig, edit_state: &mut EditState, clipboard: Option<&MusicButton>, slot: usize) -> egui::Rect {
+        let (_, rect) = ui.allocate_space(size);
+        let resp = ui.interact(rect, id, egui::Sense::click()).on_hover_cursor(egui::CursorIcon::PointingHand);
+        let painter = ui.painter_at(rect);
+        Self::paint_backing(&painter, rect, config.dark_mode);
+        painter.rect_filled(rect, 8.0, Color32::DARK_GRAY.gamma_multiply(0.5));
+        painter.text(rect.center(), Align2::CENTER_CENTER, "Click to add...", FontId::proportional(20.0), Color32::WHITE);
+        if resp.hovered() {
+            Self::paint_hover(&painter, rect, config.hover_style);
+        }
+        let menu = ui.make_persistent_id(("slot_menu", slot));
+        if resp.clicked() {
+            if clipboard.is_some() {
+                ui.memory_mut(|memory| memory.toggle_popup(menu));
+            } else {
+                edit_state.pending_music_slot = Some(slot);
+            }
+        }
+        egui::popup_below_widget(ui, menu, &resp, |ui| Self::empty_slot_menu(ui, edit_state, clipboard, slot));
+        resp.context_menu(|ui| Self::empty_slot_menu(ui, edit_state, clipboard, slot));
+        rect
+    }
+
+    // Tones are steady, so their "waveform" is a flat line at the output level
+    fn tone_waveform(tone: &TestTone) -> Vec<f32> {
+        vec![tone.gain().min(1.0); 64]
+    }
+
+    // Returns how many buttons had stats to clear. Like the plays themselves, not undoable.
+    fn reset_play_stats(&mut self, tab: usize) -> usize {
+        let played = self.tabs[tab].buttons.iter().filter(|button| button.info.stats != PlayStats::default()).count();
+        self.tabs[tab].buttons.iter_mut().for_each(|button| button.info.stats = PlayStats::default());
+        played
+    }
+
+    // Tab selectors, rename field and "+" button; laid out by the caller's ui
+    fn tabs_ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        let mut moved = None; // (from, to)
+        let mut renamed = None; // (tab, new name)
+        let mut recolored = None; // (tab, new color)
+        let mut reset_stats = None;
+        let removable = self.edit_mode && self.tabs.len() > 1;
+        let pointer = ui.input(|i| i.pointer.hover_pos());
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if self.renaming_tab == Some(i) {
+                let resp = ui.text_edit_singleline(&mut self.tab_rename_buf);
+                let enter_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if (resp.lost_focus() && ui.input(|i| !i.pointer.any_pressed())) || resp.clicked_elsewhere() || enter_pressed {
+                    let name = self.tab_rename_buf.trim();
+                    if !name.is_empty() && name != tab.name {
+                        renamed = Some((i, name.to_string()));
+                    }
+                    self.renaming_tab = None;
+                }
+            } else {
+                // Dragging a label in edit mode moves the tab live; a drag never counts as a (double) click
+                let text = if tab.settings.playlist_mode { format!("⏭ {}", tab.name) } else { tab.name.clone() };
+                let label = ui.selectable_label(self.current_tab == i, text).interact(egui::Sense::drag());
+                if let Some(color) = tab.settings.color {
+                    let rect = label.rect;
+                    ui.painter().rect_filled(rect, 2.0, color.gamma_multiply(0.2));
+                    ui.painter().line_segment([rect.left_bottom(), rect.right_bottom()], Stroke::new(3.0, color));
+                }
+                let label = label.context_menu(|ui| {
+                    ui.label("Tab color");
+                    ui.horizontal(|ui| {
+                        for &(name, color) in TAB_COLORS {
+                            let swatch = egui::Button::new("").fill(color).min_size(Vec2::splat(18.0));
+                            if ui.add(swatch).on_hover_text(name).clicked() {
+                                recolored = Some((i, Some(color)));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    if ui.button("No color").clicked() {
+                        recolored = Some((i, None));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Reset play stats").on_hover_text("Clear the play count and last-played time of every button on this tab").clicked() {
+                        reset_stats = Some(i);
+                        ui.close_menu();
+                    }
+                });
+                // Reordering is an edit: a live show can't shuffle its tabs by accident
+                if label.drag_started() && self.edit_mode {
+                    self.dragging_tab = Some(i);
+                }
+                if let Some(from) = self.dragging_tab
+                    && from != i
+                    && pointer.is_some_and(|pos| label.rect.contains(pos))
+                {
+                    moved = Some((from, i));
+                }
+                if label.clicked() {
+                    self.current_tab = i;
+                    self.current_page = 0;
+                }
+                if label.double_clicked() {
+                    self.renaming_tab = Some(i);
+                    self.tab_rename_buf = tab.name.clone();
+                }
+                // A tab with buttons asks once more before it goes
+                if removable {
+                    let confirming = self.confirm_remove_tab == Some(i);
+                    let text = if confirming { "Remove tab and its buttons?" } else { "×" };
+                    if ui.small_button(text).on_hover_text("Remove this tab").clicked() {
+                        if confirming || tab.buttons.iter().all(|button| button.name.is_empty()) {
+                            remove = Some(i);
+                        } else {
+                            self.confirm_remove_tab = Some(i);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(i) = remove
+            && let Err(err) = self.remove_tab(i)
+        {
+            self.toast(err);
+        }
+        if let Some((i, name)) = renamed {
+            self.record_undo("rename tab");
+            self.tabs[i].name = name;
+        }
+        if let Some((i, color)) = recolored
+            && self.tabs[i].settings.color != color
+        {
+            self.record_undo("tab color");
+            self.tabs[i].settings.color = color;
+        }
+        if let Some(i) = reset_stats {
+            let reset = self.reset_play_stats(i);
+            self.toast(format!("Play stats reset for {} button{}", reset, if reset == 1 { "" } else { "s" }));
+        }
+        if let Some((from, to)) = moved {
+            // A drag passing over several tabs is still one step
+            if !self.tab_drag_recorded {
+                self.record_undo("move tab");
+                self.tab_drag_recorded = true;
+            }
+            self.move_tab(from, to);
+            self.dragging_tab = Some(to);
+        }
+        if ui.input(|i| i.pointer.any_released()) {
+            self.dragging_tab = None;
+            self.tab_drag_recorded = false;
+        }
+        if ui.button("+").clicked() {
+            self.add_tab();
+        }
+    }
+
+    fn add_tab(&mut self) {
+        self.record_undo("add tab");
+        let name = self.new_tab_name();
+        self.tabs.push(MusicTab { name, buttons: Vec::new(), pages: 1, settings: TabSettings::default() });
+        self.current_tab = self.tabs.len() - 1;
+        self.current_page = 0;
+    }
+
+    // Lowest "Tab N" no tab is called yet; counting tabs would repeat a name after a removal
+    fn new_tab_name(&self) -> String {
+        (1..)
+            .map(|n| format!("Tab {}", n))
+            .find(|name| self.tabs.iter().all(|tab| &tab.name != name))
+            .unwrap_or_default()
+    }
+
+    // Move a tab to position `to`, shifting the ones in between, and keep every (tab, index)
+    // reference on the same tab
+    fn move_tab(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.tabs.len() || to >= self.tabs.len() {
+            return;
+        }
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        let follow = |t: usize| {
+            if t == from {
+                to
+            } else if from < to && (from + 1..=to).contains(&t) {
+                t - 1
+            } else if to < from && (to..from).contains(&t) {
+                t + 1
+            } else {
+                t
+            }
+        };
+        let remap = |(t, idx): (usize, usize)| (follow(t), idx);
+        self.current_tab = follow(self.current_tab);
+        self.current_playing = self.current_playing.map(remap);
+        self.queued_triggers.iter_mut().for_each(|key| *key = remap(*key));
+        self.pending_waveforms = self.pending_waveforms.drain().map(|(key, path)| (remap(key), path)).collect();
+        self.pending_normalize = self.pending_normalize.drain().map(remap).collect();
+        self.audio_player.remap_keys(remap);
+        self.renaming_tab = self.renaming_tab.map(follow);
+        self.confirm_remove_tab = None;
+    }
+
+    // Remove a tab, keeping the selection and every (tab, index) reference in range. Whatever was
+    // playing from the tab is stopped.
+    fn remove_tab(&mut self, i: usize) -> Result<(), &'static str> {
+        if self.tabs.len() <= 1 {
+            return Err("The last tab can't be removed");
+        }
+        if i >= self.tabs.len() {
+            return Err("No such tab");
+        }
+        self.record_undo("remove tab");
+        self.tabs.remove(i);
+        self.confirm_remove_tab = None;
+        if self.current_playing.is_some_and(|(t, _)| t == i) {
+            self.audio_player.stop();
+            self.current_playing = None;
+        }
+        for voice in self.audio_player.voices.iter().filter(|voice| voice.key.0 == i) {
+            voice.sink.stop();
+        }
+        self.audio_player.layers.retain(|&(t, _), _| t != i);
+        let shift = |(t, idx): (usize, usize)| if t > i { (t - 1, idx) } else { (t, idx) };
+        self.current_playing = self.current_playing.map(shift);
+        self.queued_triggers.retain(|&(t, _)| t != i);
+        self.queued_triggers.iter_mut().for_each(|key| *key = shift(*key));
+        self.pending_waveforms = self
+            .pending_waveforms
+            .drain()
+            .filter(|((t, _), _)| *t != i)
+            .map(|(key, path)| (shift(key), path))
+            .collect();
+        self.pending_normalize = self.pending_normalize.drain().filter(|&(t, _)| t != i).map(shift).collect();
+        // Re-armed for the new indices on the next frame
+        self.audio_player.preloaded.clear();
+        self.audio_player.remap_keys(shift);
+        if self.current_tab == i {
+            self.edit_state.editing = None;
+            self.current_page = 0;
+        }
+        if self.current_tab >= i && self.current_tab > 0 {
+            self.current_tab -= 1;
+        }
+        self.renaming_tab = None;
+        Ok(())
+    }
+
+    // Page switcher for a tab with more than one page
+    fn pages_ui(&mut self, ui: &mut egui::Ui) {
+        let pages = self.tabs[self.current_tab].pages.max(1);
+        if ui.add_enabled(self.current_page > 0, egui::Button::new("◀")).clicked() {
+            self.current_page -= 1;
+        }
+        ui.label(format!("Page {}/{}", self.current_page + 1, pages));
+        if ui.add_enabled(self.current_page + 1 < pages, egui::Button::new("▶")).clicked() {
+            self.current_page += 1;
+        }
+    }
+
+    // Slim cell after the last slot of the last page, in edit mode: grows the tab by a page, or
+    // shrinks it again. Some(true) to add, Some(false) to remove.
+    fn page_slot_ui(ui: &mut egui::Ui, height: f32, pages: usize) -> Option<bool> {
+        let mut action = None;
+        ui.allocate_ui_with_layout(Vec2::new(PAGE_SLOT_WIDTH, height), egui::Layout::top_down(egui::Align::Center), |ui| {
+            ui.set_min_size(Vec2::new(PAGE_SLOT_WIDTH, height));
+            if ui.small_button("+").on_hover_text("Add an empty page of slots to this tab").clicked() {
+                action = Some(true);
+            }
+            if pages > 1 && ui.small_button("−").on_hover_text("Remove the last page if it is empty").clicked() {
+                action = Some(false);
+            }
+        });
+        action
+    }
+
+    fn add_page(&mut self) {
+        let tab = &mut self.tabs[self.current_tab];
+        tab.pages = tab.pages.max(1) + 1;
+        self.current_page = tab.pages - 1;
+    }
+
+    fn remove_last_page(&mut self) -> Result<(), &'static str> {
+        let tab = &mut self.tabs[self.current_tab];
+        if tab.pages <= 1 {
+            return Err("A tab needs at least one page");
+        }
+        let last_page = (tab.pages - 1) * GRID_SLOTS;
+        if tab.buttons.iter().skip(last_page).any(|button| !button.name.is_empty()) {
+            return Err("Move or delete the buttons on the last page first");
+        }
+        tab.pages -= 1;
+        tab.buttons.truncate(last_page);
+        if let Some(edit_idx) = self.edit_state.editing
+            && edit_idx >= last_page
+        {
+            self.edit_state.editing = None;
+        }
+        self.current_page = self.current_page.min(tab.pages - 1);
+        Ok(())
+    }
+
+    // Size of one grid slot for the space left in the central panel
+    fn grid_button_size(available: Vec2) -> Vec2 {
+        let cols = GRID_COLS as f32;
+        let rows = GRID_ROWS as f32;
+        Vec2::new(
+            (available.x - 2.0 * GRID_PADDING - (cols - 1.0) * GRID_SPACING) / cols,
+            (available.y - 2.0 * GRID_PADDING - (rows - 1.0) * GRID_SPACING) / rows,
+        )
+    }
+
+    // Narrows pads wider than `max_aspect` (width / height); their height is kept
+    fn limit_pad_aspect(size: Vec2, max_aspect: Option<f32>) -> Vec2 {
+        match max_aspect {
+            Some(aspect) => Vec2::new(size.x.min(size.y * aspect), size.y),
+            None => size,
+        }
+    }
+
+    // Columns and pad size for a fit-to-content tab: as many FIT_PAD_SIZE pads per row as the
+    // width allows, shrinking their height only when the rows wouldn't fit otherwise
+    fn fitted_layout(available: Vec2, slots: usize) -> (usize, Vec2) {
+        let cols = (((available.x - 2.0 * GRID_PADDING + GRID_SPACING) / (FIT_PAD_SIZE.x + GRID_SPACING)) as usize).max(1);
+        let rows = slots.div_ceil(cols).max(1) as f32;
+        let width = (available.x - 2.0 * GRID_PADDING - (cols as f32 - 1.0) * GRID_SPACING) / cols as f32;
+        let height = (available.y - 2.0 * GRID_PADDING - (rows - 1.0) * GRID_SPACING) / rows;
+        (cols, Vec2::new(width, height.clamp(FIT_MIN_PAD_HEIGHT, FIT_PAD_SIZE.y)))
+    }
+
+    // Pads are translucent and carry white text; on the light theme they get a dark backing
+    // so they look the same as on the dark one
+    fn paint_backing(painter: &egui::Painter, rect: egui::Rect, dark_mode: bool) {
+        if !dark_mode {
+            painter.rect_filled(rect, 8.0, Color32::from_gray(40));
+        }
+    }
+
+    // Fill the pad with the image, cropped to the pad's shape rather than stretched
+    fn paint_artwork(painter: &egui::Painter, rect: egui::Rect, texture: &egui::TextureHandle) {
+        let [w, h] = texture.size();
+        let (image_aspect, pad_aspect) = (w as f32 / h.max(1) as f32, rect.aspect_ratio());
+        let uv_size = if image_aspect > pad_aspect {
+            Vec2::new(pad_aspect / image_aspect, 1.0)
+        } else {
+            Vec2::new(1.0, image_aspect / pad_aspect)
+        };
+        let mut shape = egui::epaint::RectShape::filled(rect, 8.0, Color32::WHITE);
+        shape.fill_texture_id = texture.id();
+        shape.uv = egui::Rect::from_center_size(Pos2::new(0.5, 0.5), uv_size);
+        painter.add(shape);
+    }
+
+    // Centred in the pad; a name too long for one line wraps onto a second, and is cut short
+    // with "…" if that isn't enough either
+    fn paint_name(painter: &egui::Painter, rect: egui::Rect, name: &str, size: f32) {
+        let font = FontId::proportional(size);
+        let (name_row, time_row) = painter.ctx().fonts(|fonts| (fonts.row_height(&font), fonts.row_height(&Self::time_font(size))));
+        let (area, rows) = Self::name_area(rect, name_row, time_row);
+        let mut job = egui::text::LayoutJob::simple(name.to_string(), font, Color32::WHITE, area.width());
+        job.wrap.max_rows = rows;
+        job.wrap.overflow_character = Some('…');
+        job.halign = egui::Align::Center;
+        let galley = painter.ctx().fonts(|fonts| fonts.layout_job(job));
+        painter.galley(Pos2::new(area.center().x, area.center().y - galley.size().y / 2.0), galley);
+    }
+
+    // Where a pad's name goes: clear of the time in the bottom corner, and as far from the top
+    // so it stays centred, with as many rows (up to two) as fit between
+    fn name_area(rect: egui::Rect, name_row: f32, time_row: f32) -> (egui::Rect, usize) {
+        let area = rect.shrink2(Vec2::new(8.0, 10.0 + time_row));
+        let area = if area.is_positive() { area } else { egui::Rect::from_center_size(rect.center(), Vec2::new((rect.width() - 16.0).max(1.0), 0.0)) };
+        let rows = ((area.height() / name_row.max(1.0)) as usize).clamp(1, 2);
+        (area, rows)
+    }
+
+    // The time in the bottom-right corner of a pad, scaled with its name
+    fn time_font(name_size: f32) -> FontId {
+        FontId::proportional((name_size * 0.72).max(10.0))
+    }
+
+    // Waveform behind a pad: the `span` of the file (fractions, see MusicButton::trim_span)
+    // across the rect as one (low, high) pair per pixel column, heights multiplied by `level`.
+    // Either filled as a mesh with anti-aliased outlines or drawn as the older 1px vertical lines.
+    fn paint_waveform(painter: &egui::Painter, rect: egui::Rect, button: &MusicButton, config: &AppConfig, span: (f32, f32), level: f32) {
+        let style = config.waveform_style;
+        let min_max = !button.waveform_min.is_empty() && button.waveform_min.len() == button.waveform.len();
+        let len = button.waveform.len();
+        let first = ((span.0 * len as f32) as usize).min(len.saturating_sub(1));
+        let last = ((span.1 * len as f32).ceil() as usize).clamp(first + 1, len.max(1)).min(len);
+        let wf = &button.waveform[first..last];
+        let wf_min = if min_max { &button.waveform_min[first..last] } else { &button.waveform_min[..] };
+        let width = rect.width();
+        let base_y = rect.bottom();
+        let top_y = rect.top();
+        let center_y = rect.center().y;
+        let color = button.playback.waveform_color.unwrap_or_else(|| button.color.gamma_multiply(0.3));
+        // Nothing decoded yet, a file too short for a single point, or pure silence: a flat
+        // shape would be invisible, so show a thin baseline instead
+        if wf.iter().chain(wf_min).all(|v| *v == 0.0) {
+            let y = if min_max || wf.is_empty() { center_y } else { base_y - 2.0 };
+            painter.line_segment([Pos2::new(rect.left() + 8.0, y), Pos2::new(rect.right() - 8.0, y)], Stroke::new(1.0, color));
+            return;
+        }
+        let step = wf.len() as f32 / width.max(1.0);
+        let wf_scale = rect.height() * config.waveform_height() * level;
+        let spans: Vec<(f32, f32, f32)> = (0..=width as usize)
+            .map(|x| {
+                let idx_wf = ((x as f32 * step) as usize).min(wf.len().saturating_sub(1));
+                let h = wf.get(idx_wf).copied().unwrap_or(0.0);
+                let (y_low, y_high) = if min_max {
+                    let low = wf_min.get(idx_wf).copied().unwrap_or(0.0);
+                    (center_y - low * (wf_scale * 0.5), center_y - h * (wf_scale * 0.5))
+                } else {
+                    (base_y, base_y - h * wf_scale)
+                };
+                (rect.left() + (x as f32).min(width), y_low.min(base_y), y_high.max(top_y))
+            })
+            .collect();
+        match style {
+            WaveformStyle::Lines => {
+                for &(x, y_low, y_high) in &spans {
+                    painter.line_segment([Pos2::new(x, y_low), Pos2::new(x, y_high)], Stroke::new(1.0, color));
+                }
+            }
+            WaveformStyle::Filled => {
+                // The waveform isn't convex, so fill a triangle strip between the two edges
+                let mut mesh = egui::Mesh::default();
+                for (i, &(x, y_low, y_high)) in spans.iter().enumerate() {
+                    mesh.colored_vertex(Pos2::new(x, y_low), color);
+                    mesh.colored_vertex(Pos2::new(x, y_high), color);
+                    if i > 0 {
+                        let v = 2 * i as u32;
+                        mesh.add_triangle(v - 2, v - 1, v);
+                        mesh.add_triangle(v - 1, v + 1, v);
+                    }
+                }
+                painter.add(egui::Shape::mesh(mesh));
+                // Feathered outlines smooth the edges the mesh leaves jagged
+                let stroke = Stroke::new(1.0, color);
+                painter.add(egui::Shape::line(spans.iter().map(|&(x, _, y_high)| Pos2::new(x, y_high)).collect(), stroke));
+                if min_max {
+                    painter.add(egui::Shape::line(spans.iter().map(|&(x, y_low, _)| Pos2::new(x, y_low)).collect(), stroke));
+                }
+            }
+        }
+    }
+
+    // The whole waveform with a handle at each end of the part that plays; dragging one moves
+    // that trim point. What is cut off is greyed out.
+    fn trim_ui(ui: &mut egui::Ui, button: &MusicButton, playback: &mut PlaybackSettings, config: &AppConfig) {
+        let duration = button.duration;
+        let (rect, resp) = ui.allocate_exact_size(Vec2::new(320.0, 56.0), egui::Sense::hover());
+        resp.on_hover_text("Drag the yellow handles to skip silence or chatter at either end; the file itself is left alone");
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, button.color.gamma_multiply(0.5));
+        let level = if config.normalize_waveform { button.waveform_scale() } else { 1.0 };
+        Self::paint_waveform(&painter, rect, button, config, (0.0, 1.0), level);
+        let (start, end) = playback.trim_range(duration);
+        let x_of = |secs: f32| rect.left() + rect.width() * secs / duration;
+        let shade = Color32::from_black_alpha(150);
+        painter.rect_filled(egui::Rect::from_x_y_ranges(rect.left()..=x_of(start), rect.y_range()), 0.0, shade);
+        painter.rect_filled(egui::Rect::from_x_y_ranges(x_of(end)..=rect.right(), rect.y_range()), 0.0, shade);
+        for (is_start, secs) in [(true, start), (false, end)] {
+            let x = x_of(secs);
+            let handle = egui::Rect::from_x_y_ranges(x - 5.0..=x + 5.0, rect.y_range());
+            let resp = ui
+                .interact(handle, ui.id().with(("trim_handle", is_start)), egui::Sense::drag())
+                .on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+            let width = if resp.hovered() || resp.dragged() { 3.0 } else { 2.0 };
+            painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], Stroke::new(width, Color32::YELLOW));
+            if resp.dragged()
+                && let Some(pos) = resp.interact_pointer_pos()
+            {
+                let secs = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0) * duration;
+                if is_start {
+                    playback.start_offset = secs.min(end - MIN_TRIMMED_SECS).max(0.0);
+                } else {
+                    playback.end_offset = (duration - secs).min(duration - start - MIN_TRIMMED_SECS).max(0.0);
+                }
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.label("Skip:");
+            ui.add(egui::DragValue::new(&mut playback.start_offset).clamp_range(0.0..=duration).speed(0.01).max_decimals(2).suffix(" s"));
+            ui.label("Cut from end:");
+            ui.add(egui::DragValue::new(&mut playback.end_offset).clamp_range(0.0..=duration).speed(0.01).max_decimals(2).suffix(" s"));
+            if start > 0.0 || end < duration {
+                ui.weak(format!("plays {}", Self::format_time(end - start)));
+                if ui.small_button("Reset").clicked() {
+                    playback.start_offset = 0.0;
+                    playback.end_offset = 0.0;
+                }
+            }
+        });
+    }
+
+    // Search field; Enter jumps to the first match
+    fn search_ui(&mut self, ui: &mut egui::Ui) {
+        let resp = ui.add(egui::TextEdit::singleline(&mut self.search_query).hint_text("Search buttons").desired_width(140.0));
+        if self.search_query.trim().is_empty() {
+            return;
+        }
+        let matches = self.find_buttons(&self.search_query);
+        if matches.is_empty() {
+            ui.colored_label(Color32::LIGHT_RED, "No match");
+        } else {
+            ui.label(format!("{} found", matches.len()));
+        }
+        if resp.lost_focus()
+            && ui.input(|i| i.key_pressed(egui::Key::Enter))
+            && let Some(&first) = matches.first()
+        {
+            self.reveal_button(first);
+        }
+    }
+
+    // Master level for everything that goes out (applied at the mix output, so it also
+    // affects whatever is already playing or fading)
+    fn master_volume_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Master:");
+        let slider = ui.add(egui::Slider::new(&mut self.config.master_volume, 0.0..=1.0).show_value(false));
+        // Write the config once the drag ends rather than on every frame of it
+        if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+            let _ = self.config.save();
+        }
+        ui.toggle_value(&mut self.master_muted, "Mute");
+    }
+
+    // A new peak shows at once; without one the reading falls away smoothly
+    fn update_meter(&mut self, dt: f32) {
+        let peak = self.audio_player.take_peak();
+        self.meter = peak.max(self.meter * METER_FALL.powf(dt));
+    }
+
+    // Peak meter of what goes out, on a dB scale: green, yellow from -12 dB, red from -3 dB
+    fn meter_ui(&self, ui: &mut egui::Ui) {
+        let (rect, resp) = ui.allocate_exact_size(Vec2::new(100.0, 12.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, Color32::from_gray(40));
+        let db = 20.0 * self.meter.max(1e-6).log10();
+        let fill = ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0);
+        if fill > 0.0 {
+            let color = if db >= -3.0 {
+                Color32::RED
+            } else if db >= -12.0 {
+                Color32::YELLOW
+            } else {
+                Color32::GREEN
+            };
+            let bar = egui::Rect::from_min_size(rect.min, Vec2::new(rect.width() * fill, rect.height()));
+            painter.rect_filled(bar, 2.0, color);
+        }
+        resp.on_hover_text(if fill > 0.0 { format!("Output peak: {:.1} dB", db) } else { "Output peak: silent".to_string() });
+    }
+
+    // Text field that only writes back when editing finishes, so settings that act on their
+    // value (socket address, global hotkey) never see half-typed input. Returns true on commit.
+    fn committed_text_edit(ui: &mut egui::Ui, value: &mut String) -> bool {
+        let id = ui.next_auto_id();
+        let mut buf = ui.data_mut(|data| data.get_temp::<String>(id)).unwrap_or_else(|| value.clone());
+        let resp = ui.text_edit_singleline(&mut buf);
+        if resp.lost_focus() {
+            ui.data_mut(|data| data.remove::<String>(id));
+            if buf != *value {
+                *value = buf;
+                return true;
+            }
+        } else if resp.has_focus() {
+            ui.data_mut(|data| data.insert_temp(id, buf));
+        }
+        false
+    }
+
+    fn paint_hover(painter: &egui::Painter, rect: egui::Rect, style: HoverStyle) {
+        match style {
+            HoverStyle::Off => {}
+            HoverStyle::Border => painter.rect_stroke(rect.shrink(1.0), 8.0, Stroke::new(2.0, Color32::WHITE)),
+            HoverStyle::Brighten => painter.rect_filled(rect, 8.0, Color32::from_white_alpha(28)),
+        }
+    }
+
+    // What's behind a pad, for telling apart buttons with similar names or spotting a wrong file
+    fn button_tooltip(ui: &mut egui::Ui, button: &MusicButton, playing: bool, missing: bool) {
+        ui.label(egui::RichText::new(&button.name).strong());
+        match &button.tone {
+            Some(tone) => ui.label(tone.label()),
+            None => ui.label(button.path.display().to_string()),
+        };
+        if missing {
+            ui.colored_label(Color32::from_rgb(255, 160, 0), "File not found; relink it from the edit popup");
+        }
+        let mut details = format!("Duration: {}", Self::format_time(button.play_length()));
+        if button.trim().is_some() {
+            details.push_str(&format!(" (trimmed from {})", Self::format_time(button.duration / button.speed())));
+        }
+        if let Some(rate) = button.info.sample_rate.filter(|_| button.tone.is_none()) {
+            details.push_str(&format!(" · {} Hz", rate));
+        }
+        ui.weak(details);
+        if let Some(stats) = button.info.stats.summary(SystemTime::now()) {
+            ui.weak(stats);
+        }
+        if let Some(err) = &button.play_error {
+            ui.colored_label(Color32::LIGHT_RED, format!("Last play failed: {}", err));
+        }
+        if playing {
+            ui.weak("Click to fade out · Shift+click to pause · Ctrl+click to restart from the top");
+        }
+    }
+
+    // Yellow while there is time; under the warning threshold it ramps from orange to red
+    fn remaining_color(remaining: f32, warn_secs: u32) -> Color32 {
+        if remaining >= warn_secs as f32 {
+            return Color32::YELLOW;
+        }
+        let urgency = 1.0 - remaining / warn_secs as f32;
+        let green = (165.0 * (1.0 - urgency)).round() as u8;
+        Color32::from_rgb(255, green, 0)
+    }
+
+    fn format_time(secs: f32) -> String {
+        let secs = secs.max(0.0) as u64;
+        let h = secs / 3600;
+        let m = (secs % 3600) / 60;
+        let s = secs % 60;
+        if h > 0 {
+            format!("{:02}:{:02}:{:02}", h, m, s)
+        } else {
+            format!("{:02}:{:02}", m, s)
+        }
+    }
+
+    fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let data = bincode::serialize(self)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    // JSON export: readable and diffable. Button paths are stored relative to the file when they
+    // sit below it, so a folder holding the board and its audio can be moved as a whole.
+    fn save_to_json(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(base) = path.parent() {
+            Self::rebase_paths(&mut value, |file| file.strip_prefix(base).unwrap_or(file).to_path_buf());
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&value)?)?;
+        Ok(())
+    }
+
+    fn load_from_json(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::rebase_paths(&mut value, |file| base.join(file));
+        let loaded: MusicInterface = serde_json::from_value(value)?;
+        legacy::check_version(loaded.version)?;
+        self.adopt_board(loaded);
+        Ok(())
+    }
+
+    // Rewrite the path of every file button in a JSON board
+    fn rebase_paths(value: &mut serde_json::Value, mut rebase: impl FnMut(&Path) -> PathBuf) {
+        let Some(tabs) = value.get_mut("tabs").and_then(|tabs| tabs.as_array_mut()) else {
+            return;
+        };
+        let buttons = tabs.iter_mut().filter_map(|tab| tab.get_mut("buttons").and_then(|buttons| buttons.as_array_mut()));
+        for button in buttons.flatten() {
+            if let Some(path) = button.get_mut("path")
+                && let Some(file) = path.as_str().filter(|file| !file.is_empty())
+            {
+                *path = rebase(Path::new(file)).to_string_lossy().into_owned().into();
+            }
+        }
+    }
+
+    // A zip holding board.json plus a copy of every audio file it uses under audio/, with paths
+    // relative to the archive root. Files are streamed into the archive, never read whole.
+    fn export_package(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut value = serde_json::to_value(self)?;
+        let mut files: Vec<(PathBuf, String)> = Vec::new(); // (source, name in the archive)
+        Self::rebase_paths(&mut value, |file| {
+            if let Some((_, name)) = files.iter().find(|(source, _)| source == file) {
+                return PathBuf::from(name);
+            }
+            let file_name = file.file_name().map_or("audio".into(), |name| name.to_string_lossy());
+            let mut name = format!("audio/{}", file_name);
+            if files.iter().any(|(_, taken)| *taken == name) {
+                name = format!("audio/{}-{}", files.len(), file_name);
+            }
+            files.push((file.to_path_buf(), name.clone()));
+            PathBuf::from(name)
+        });
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        zip.start_file("board.json", zip::write::FileOptions::default())?;
+        zip.write_all(serde_json::to_string_pretty(&value)?.as_bytes())?;
+        // Audio is already compressed, deflating it again only costs time
+        let stored = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored).large_file(true);
+        for (source, name) in &files {
+            zip.start_file(name.as_str(), stored)?;
+            std::io::copy(&mut File::open(source)?, &mut zip)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    // Unpack a show package into `dest` and load its board from there
+    fn import_package(&mut self, path: &Path, dest: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            // Refuse entries that would land outside `dest` ("../", absolute paths)
+            let target = dest.join(entry.enclosed_name().ok_or("the package contains an unsafe path")?);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&target)?;
+                continue;
+            }
+            if let Some(dir) = target.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::io::copy(&mut entry, &mut File::create(&target)?)?;
+        }
+        self.load_from_json(&dest.join("board.json"))
+    }
+
+    // Boards ending in .json are read as JSON exports, anything else as bincode
+    fn load_from_file(&mut self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            return self.load_from_json(path);
+        }
+        let data = std::fs::read(path)?;
+        self.adopt_board(legacy::decode_board(&data)?);
+        Ok(())
+    }
+
+    // A failed load never touches the open board, so say so along with the reason
+    fn report_load_error(&mut self, path: &Path, err: impl std::fmt::Display) {
+        self.load_error = Some(format!(
+            "{} couldn't be read:\n{}\n\nThe open board was left as it was.",
+            path.display(),
+            err
+        ));
+    }
+
+    // Go back to the board the last load replaced; the current one becomes the previous in turn
+    fn restore_previous_board(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let data = self.previous_board.take().ok_or("no previous board")?;
+        self.adopt_board(legacy::decode_board(&data)?);
+        Ok(())
+    }
+
+    // Swap a decoded board in for the current one, keeping the current one as a backup
+    fn adopt_board(&mut self, mut loaded: MusicInterface) {
+        loaded.previous_board = bincode::serialize(&*self).ok();
+        // Keep the running player (and any recording) but silence the old board
+        std::mem::swap(&mut loaded.audio_player, &mut self.audio_player);
+        loaded.audio_player.stop();
+        loaded.validate_and_repair();
+        loaded.mark_missing_files();
+        loaded.show_settings = self.show_settings;
+        loaded.config = std::mem::take(&mut self.config);
+        loaded.toasts = std::mem::take(&mut self.toasts);
+        loaded.ipc_server = self.ipc_server.take();
+        loaded.web_remote = self.web_remote.take();
+        loaded.midi_input = self.midi_input.take();
+        loaded.raise_hotkey = self.raise_hotkey.take();
+        // Switching boards mid-show shouldn't unmute the output
+        loaded.master_muted = self.master_muted;
+        loaded.last_autosave = self.last_autosave;
+        // Whatever it was read from, it is saved in the current format
+        loaded.version = BOARD_VERSION;
+        // A board fresh off disk has nothing to autosave yet
+        loaded.saved_fingerprint = loaded.fingerprint();
+        *self = loaded;
+    }
+}
+
+impl eframe::App for MusicInterface {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.watch_output();
+        self.audio_player.prune_voices();
+        self.audio_player.apply_end_fade();
+        self.audio_player.apply_fade_ins();
+        self.update_meter(ctx.input(|i| i.stable_dt));
+        let master = if self.master_muted { 0.0 } else { self.config.master_volume.clamp(0.0, 1.0) };
+        self.audio_player.set_master_gain(master);
+        self.audio_player.set_mono(self.config.mono_output);
+        self.audio_player.fade_curve = self.config.fade_curve;
+        self.audio_player.snap_to_zero = self.config.snap_to_zero;
+        if self.config.preload_sinks {
+            self.audio_player.sync_preloaded(&self.tabs);
+        } else {
+            self.audio_player.preloaded.clear();
+        }
+        self.sync_ipc_server();
+        self.sync_web_remote();
+        self.publish_remote_state();
+        self.apply_remote_commands();
+        self.sync_midi_input();
+        self.apply_midi_notes();
+        self.sync_raise_hotkey();
+        self.raise_on_hotkey(ctx);
+        self.fire_queued_triggers();
+        self.advance_playlist();
+        self.detect_track_end();
+        self.drain_waveforms();
+        if !self.pending_waveforms.is_empty() {
+            // Nothing else wakes the UI when a decode finishes
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if let Some(path) = Self::autosave_path()
+            && let Err(err) = self.autosave(&path)
+        {
+            self.toast(format!("Autosave failed: {}", err));
+        }
+        // Escape stops everything, in edit mode too, unless a text field or hotkey capture has it
+        if !ctx.wants_keyboard_input()
+            && !self.edit_state.capturing_hotkey
+            && ctx.input(|i| i.key_pressed(egui::Key::Escape))
+        {
+            self.stop_all();
+        }
+        // Edit mode only, so a stray Ctrl+Z on air can't rewrite the board; text fields have
+        // their own undo
+        if self.edit_mode && !ctx.wants_keyboard_input() && !self.edit_state.capturing_hotkey {
+            let (undo, redo) = ctx.input_mut(|i| {
+                let redo = i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)
+                    || i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y);
+                (i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z), redo)
+            });
+            if undo {
+                self.undo();
+            }
+            if redo {
+                self.redo();
+            }
+        }
+        if !self.edit_mode && !ctx.wants_keyboard_input() {
+            let pressed: Vec<egui::Key> = ctx.input(|i| i.events.iter().filter_map(Self::pad_key).collect());
+            for key in pressed {
+                for (tab, idx) in self.hotkey_targets(key) {
+                    self.trigger_button(tab, idx);
+                }
+                if let Some(idx) = self.quickfire_slot(key) {
+                    self.trigger_button(self.current_tab, idx);
+                }
+                if self.is_fade_key(key) {
+                    self.fade_current();
+                }
+            }
+        }
+        if let Some(err) = self.audio_player.take_recording_error() {
+            self.toast(format!("Recording stopped: {}", err));
+        }
+        if let Some(((tab, idx), latency_ms)) = self.audio_player.take_start_latency()
+            && let Some(button) = self.tabs.get_mut(tab).and_then(|tab| tab.buttons.get_mut(idx))
+        {
+            // Smooth over runs so one slow disk read doesn't dominate the estimate
+            button.start_latency_ms = if button.start_latency_ms > 0.0 {
+                (button.start_latency_ms + latency_ms) / 2.0
+            } else {
+                latency_ms
+            };
+        }
+        if self.show_mixer {
+            egui::SidePanel::right("mixer_panel").show(ctx, |ui| {
+                ui.heading("Mixer");
+                ui.separator();
+                if self.audio_player.voices.is_empty() {
+                    ui.label("No active voices");
+                }
+                let mut stopped = Vec::new();
+                for (i, voice) in self.audio_player.voices.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.label(egui::RichText::new(&voice.name).strong());
+                        ui.horizontal(|ui| {
+                            // A fading voice's level belongs to its ramp; Stop still cuts it short
+                            if ui.add_enabled(!voice.fading, egui::Slider::new(&mut voice.volume, 0.0..=1.0).show_value(false)).changed() {
+                                voice.apply_volume();
+                            }
+                            if ui.add_enabled(!voice.fading, egui::Checkbox::new(&mut voice.muted, "Mute")).changed() {
+                                voice.apply_volume();
+                            }
+                            if ui.button("Stop").clicked() {
+                                voice.sink.stop();
+                                stopped.push(voice.key);
+                            }
+                        });
+                    });
+                    ui.separator();
+                }
+                for key in stopped {
+                    if self.current_playing == Some(key) {
+                        self.current_playing = None;
+                    }
+                    self.audio_player.layers.remove(&key);
+                }
+            });
+        }
+        if self.config.tab_bar == TabBarPosition::Left {
+            egui::SidePanel::left("tab_sidebar").show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| self.tabs_ui(ui));
+                });
+            });
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Save/Import buttons
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    if let Some(path) = FileDialog::new().set_file_name("radio_conductor_save.bin").save_file() {
+                        self.validate_and_repair();
+                        if self.save_to_file(&path).is_ok() {
+                            self.remember_file(&path);
+                        }
+                    }
+                }
+                if ui.button("Export JSON").on_hover_text("Readable board file; paths are kept relative to it").clicked()
+                    && let Some(path) = FileDialog::new()
+                        .set_file_name("radio_conductor_save.json")
+                        .add_filter("JSON", &["json"])
+                        .save_file()
+                {
+                    self.validate_and_repair();
+                    if self.save_to_json(&path).is_ok() {
+                        self.remember_file(&path);
+                    }
+                }
+                if ui.button("Export Package").on_hover_text("Zip the board together with copies of its audio files").clicked()
+                    && let Some(path) = FileDialog::new()
+                        .set_file_name("radio_conductor_show.zip")
+                        .add_filter("Show package", &["zip"])
+                        .save_file()
+                {
+                    self.validate_and_repair();
+                    if let Err(err) = self.export_package(&path) {
+                        self.toast(format!("Export failed: {}", err));
+                    }
+                }
+                if ui.button("Import Package").clicked()
+                    && let Some(path) = FileDialog::new().add_filter("Show package", &["zip"]).pick_file()
+                    && let Some(dest) = FileDialog::new().set_title("Folder to unpack the show into").pick_folder()
+                {
+                    match self.import_package(&path, &dest) {
+                        Ok(()) => self.remember_file(&dest.join("board.json")),
+                        Err(err) => self.report_load_error(&path, err),
+                    }
+                }
+                if ui.button("Import").clicked()
+                    && let Some(path) = FileDialog::new().pick_file()
+                {
+                    match self.load_from_file(&path) {
+                        Ok(()) => self.remember_file(&path),
+                        Err(err) => self.report_load_error(&path, err),
+                    }
+                }
+                let mut reopen = None;
+                ui.add_enabled_ui(!self.config.recent_files.is_empty(), |ui| {
+                    ui.menu_button("Recent", |ui| {
+                        for path in &self.config.recent_files {
+                            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+                            if ui.button(name).on_hover_text(path.display().to_string()).clicked() {
+                                reopen = Some(path.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if let Some(path) = reopen {
+                    self.open_recent(&path);
+                }
+                if ui.button("Import Folder").on_hover_text("Fill the empty slots with a folder's audio files, in name order").clicked()
+                    && let Some(dir) = FileDialog::new().pick_folder()
+                {
+                    match self.import_folder(&dir) {
+                        Ok((0, skipped)) => self.toast(format!("No audio files found in {} ({} other files skipped)", dir.display(), skipped)),
+                        Ok((imported, skipped)) => self.toast(format!("Imported {} files, skipped {}", imported, skipped)),
+                        Err(err) => self.toast(format!("Could not read {}: {}", dir.display(), err)),
+                    }
+                }
+                if self.previous_board.is_some()
+                    && ui.button("↶ Previous board").on_hover_text("Go back to the board that was open before the last import").clicked()
+                    && let Err(err) = self.restore_previous_board()
+                {
+                    self.toast(format!("Could not restore the previous board: {}", err));
+                }
+                let missing = self.missing_files();
+                if let Some(&first) = missing.first() {
+                    let label = egui::RichText::new(format!("⚠ {} missing file{}", missing.len(), if missing.len() == 1 { "" } else { "s" }))
+                        .color(Color32::from_rgb(255, 160, 0));
+                    if ui.button(label).on_hover_text("Audio files this board points at weren't found. Click to show the first one; relink it from its edit popup").clicked() {
+                        self.reveal_button(first);
+                    }
+                }
+                ui.separator();
+                let stop_all = egui::Button::new(egui::RichText::new("⏹ Stop All").strong().color(Color32::WHITE)).fill(Color32::from_rgb(180, 30, 30));
+                if ui.add(stop_all).on_hover_text("Silence everything at once, without fading (Esc)").clicked() {
+                    self.stop_all();
+                }
+                ui.separator();
+                self.master_volume_ui(ui);
+                self.meter_ui(ui);
+                ui.separator();
+                ui.toggle_value(&mut self.show_mixer, "Mixer");
+                ui.toggle_value(&mut self.show_settings, "Settings");
+                let theme = if self.config.dark_mode { "☀ Light" } else { "🌙 Dark" };
+                if ui.button(theme).on_hover_text("Switch between the light and dark theme").clicked() {
+                    self.config.dark_mode = !self.config.dark_mode;
+                    ui.ctx().set_visuals(self.config.visuals());
+                    let _ = self.config.save();
+                }
+                ui.separator();
+                self.search_ui(ui);
+                ui.separator();
+                if let Some(recording) = &self.audio_player.recording {
+                    let elapsed = Self::format_time(recording.started.elapsed().as_secs_f32());
+                    if ui.button("Stop Recording").on_hover_text(recording.path.display().to_string()).clicked() {
+                        self.audio_player.stop_recording();
+                    }
+                    ui.colored_label(Color32::RED, format!("● REC {}", elapsed));
+                } else if ui.button("Record").on_hover_text("Record the output to a WAV file (air-check)").clicked()
+                    && let Some(path) = FileDialog::new()
+                        .set_file_name("aircheck.wav")
+                        .add_filter("WAV", &["wav"])
+                        .save_file()
+                    && let Err(err) = self.audio_player.start_recording(path)
+                {
+                    self.toast(format!("Could not start recording: {}", err));
+                }
+            });
+            // No-audio banner
+            if !self.audio_player.has_output() {
+                ui.horizontal(|ui| {
+                    let reason = self.audio_player.output_error.as_deref().unwrap_or("unknown error");
+                    ui.colored_label(Color32::from_rgb(255, 90, 90), format!("No audio output ({}). You can still edit the board.", reason));
+                    if ui.button("Retry Audio").clicked() && !self.audio_player.retry_output(self.config.output_device.as_deref()) {
+                        self.toast("Still no audio output device");
+                    }
+                });
+                ui.add_space(8.0);
+            }
+            // Edit mode banner
+            if self.edit_mode {
+                let banner = if self.config.dark_mode { Color32::from_rgb(255, 200, 0) } else { Color32::from_rgb(170, 110, 0) };
+                ui.colored_label(
+                    banner,
+                    egui::RichText::new("YOU ARE IN EDIT MODE: You can edit music buttons. Click 'Exit Edit Mode' to return to normal mode.")
+                        .strong()
+                        .size(20.0),
+                );
+                ui.add_space(8.0);
+            }
+            // Tabs
+            if self.config.tab_bar == TabBarPosition::Top {
+                ui.horizontal(|ui| self.tabs_ui(ui));
+            }
+            ui.separator();
+            // Edit mode toggle and page switcher
+            self.current_page = self.current_page.min(self.tabs[self.current_tab].pages.max(1) - 1);
+            ui.horizontal(|ui| {
+                if ui.button(if self.edit_mode { "Exit Edit Mode" } else { "Enter Edit Mode" }).clicked() {
+                    self.edit_mode = !self.edit_mode;
+                }
+                if self.edit_mode {
+                    let undo = self.history.undo.last().map(|step| step.action);
+                    let redo = self.history.redo.last().map(|step| step.action);
+                    if ui
+                        .add_enabled(undo.is_some(), egui::Button::new("↶ Undo"))
+                        .on_hover_text(format!("Undo {} (Ctrl+Z)", undo.unwrap_or_default()))
+                        .clicked()
+                    {
+                        self.undo();
+                    }
+                    if ui
+                        .add_enabled(redo.is_some(), egui::Button::new("↷ Redo"))
+                        .on_hover_text(format!("Redo {} (Ctrl+Y)", redo.unwrap_or_default()))
+                        .clicked()
+                    {
+                        self.redo();
+                    }
+                    let mut normalize = None;
+                    ui.menu_button("Normalize levels", |ui| {
+                        if ui.button("Normalize tab").clicked() {
+                            normalize = Some(Some(self.current_tab));
+                            ui.close_menu();
+                        }
+                        if ui.button("Normalize all").clicked() {
+                            normalize = Some(None);
+                            ui.close_menu();
+                        }
+                    })
+                    .response
+                    .on_hover_text(format!("Set button volumes so every file plays at {:.0} dB (target under Settings)", self.config.loudness_target_db));
+                    if let Some(scope) = normalize {
+                        let message = match self.normalize_levels(scope) {
+                            (0, 0) => "No audio buttons to normalize".to_string(),
+                            (levelled, 0) => format!("Normalized {} button{}", levelled, if levelled == 1 { "" } else { "s" }),
+                            (levelled, measuring) => format!("Normalized {} buttons; {} more once they are measured", levelled, measuring),
+                        };
+                        self.toast(message);
+                    }
+                }
+                ui.separator();
+                if !self.tabs[self.current_tab].settings.fit_to_content && self.tabs[self.current_tab].pages > 1 {
+                    self.pages_ui(ui);
+                    ui.separator();
+                }
+                let tab = &mut self.tabs[self.current_tab];
+                ui.checkbox(&mut tab.settings.playlist_mode, "Playlist")
+                    .on_hover_text("When a cue ends, start the next button of this tab");
+                if ui
+                    .checkbox(&mut tab.settings.fit_to_content, "Fit to content")
+                    .on_hover_text("Show only this tab's buttons plus one free slot, as many per row as fit, instead of fixed pages")
+                    .changed()
+                {
+                    self.current_page = 0;
+                }
+                ui.separator();
+                let (count, total) = tab.runtime();
+                ui.weak(format!("{} button{} · {} total", count, if count == 1 { "" } else { "s" }, Self::format_time(total)));
+            });
+            ui.separator();
+            // Responsive grid showing one page of slots
+            let conflicting_hotkeys = self.conflicting_hotkeys();
+            let search_matches = self.find_buttons(&self.search_query);
+            // The slot the search jumped to blinks for a moment
+            let flashing = self
+                .flash
+                .filter(|(_, at)| at.elapsed() < Duration::from_millis(1500))
+                .map(|(key, at)| (key, at.elapsed().as_millis() / 250 % 2 == 0));
+            let mut triggered = None;
+            let mut seek_to = None; // (index, fraction of the track)
+            let mut pause_toggled = false;
+            let mut restarted = None;
+            let mut prelistened = None;
+            let prelistening = self.audio_player.prelistening();
+            let mut slot_rects = Vec::new(); // (index, visible part) of every slot, for drag-and-drop
+            let mut dropped = None; // (from, to)
+            if !self.edit_mode {
+                self.dragging_button = None;
+            }
+            let tab = &mut self.tabs[self.current_tab];
             let available_size = ui.available_size();
-            let cols = 5;
-            let rows = 4;
-            let hpad = 12.0; // horizontal padding on each side
-            let vpad = 12.0; // vertical padding on top and bottom
-            let col_spacing = 8.0;
-            let row_spacing = 8.0;
-            let btn_w = (available_size.x - 2.0 * hpad - (cols as f32 - 1.0) * col_spacing) / cols as f32;
-            let btn_h = (available_size.y - 2.0 * vpad - (rows as f32 - 1.0) * row_spacing) / rows as f32;
-            let idx = 0;
-            let add_requests = Vec::new();
-            ui.add_space(vpad);
-            egui::Grid::new("button_grid").spacing(Vec2::new(col_spacing, row_spacing)).show(ui, |ui| {
-                for row in 0..rows {
-                    for col in 0..cols {
-                        let idx = row * cols + col;
-                        let button_opt = tab.buttons.get_mut(idx);
-                        if let Some(button) = button_opt {
-                            if !button.name.is_empty() {
-                                let (id, rect) = ui.allocate_space(Vec2::new(btn_w, btn_h));
-                                let painter = ui.painter_at(rect);
-                                // Draw waveform background
-                                let wf = &button.waveform;
-                                let wf_len = wf.len().max(1);
-                                let step = wf_len as f32 / btn_w.max(1.0);
-                                let base_y = rect.bottom();
-                                let top_y = rect.top();
-                                let color = button.color.gamma_multiply(0.3);
-                                for x in 0..btn_w as usize {
-                                    let idx_wf = (x as f32 * step) as usize;
-                                    let h = wf.get(idx_wf).copied().unwrap_or(0.0);
-                                    let y = base_y - h * (btn_h * 0.8);
-                                    painter.line_segment([
-                                        Pos2::new(rect.left() + x as f32, base_y),
-                                        Pos2::new(rect.left() + x as f32, y.max(top_y))
-                                    ], Stroke::new(1.0, color));
-                                }
-                                // Draw button overlay
-                                painter.rect_filled(rect, 8.0, button.color.gamma_multiply(0.7));
-                                // Draw name
-                                painter.text(
-                                    rect.center(),
-                                    Align2::CENTER_CENTER,
-                                    &button.name,
-                                    FontId::proportional(22.0),
-                                    Color32::WHITE,
-                                );
-                                // Draw duration/remaining
-                                let (time_str, time_color) = if Some((self.current_tab, idx)) == self.current_playing {
-                                    let elapsed = self.audio_player.elapsed();
-                                    let remaining = (button.duration - elapsed).max(0.0);
-                                    (Self::format_time(remaining), Color32::YELLOW)
-                                } else {
-                                    (Self::format_time(button.duration), Color32::WHITE)
+            let (slots, cols, rows, Vec2 { x: btn_w, y: btn_h }) = if tab.settings.fit_to_content {
+                let slots = tab.fitted_slots();
+                let (cols, size) = Self::fitted_layout(available_size, slots.len());
+                let rows = slots.len().div_ceil(cols);
+                (slots, cols, rows, size)
+            } else {
+                let page_start = self.current_page * GRID_SLOTS;
+                ((page_start..page_start + GRID_SLOTS).collect(), GRID_COLS, GRID_ROWS, Self::grid_button_size(available_size))
+            };
+            let Vec2 { x: btn_w, y: btn_h } = Self::limit_pad_aspect(Vec2::new(btn_w, btn_h), self.config.max_pad_aspect());
+            let pages = tab.pages.max(1);
+            let page_slot = self.edit_mode && !tab.settings.fit_to_content && self.current_page + 1 == pages;
+            let mut page_action = None;
+            // Pads held to the aspect limit leave spare width; split it either side to keep the grid centred
+            let mut grid_width = cols as f32 * btn_w + (cols as f32 - 1.0) * GRID_SPACING;
+            if page_slot {
+                grid_width += GRID_SPACING + PAGE_SLOT_WIDTH;
+            }
+            let margin = ((available_size.x - 2.0 * GRID_PADDING - grid_width) / 2.0).max(0.0);
+            // A faint wash of the tab color; pads keep their own fills, so their text reads the same on any tab
+            if let Some(color) = tab.settings.color {
+                ui.painter().rect_filled(egui::Rect::from_min_size(ui.cursor().min, available_size), 8.0, color.gamma_multiply(0.12));
+            }
+            ui.add_space(GRID_PADDING);
+            // Fit-to-content tabs can outgrow the window; fixed pages always fit
+            egui::ScrollArea::vertical().id_source("button_grid").auto_shrink([false, false]).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.add_space(margin);
+                    egui::Grid::new("button_grid").spacing(Vec2::splat(GRID_SPACING)).show(ui, |ui| {
+                        for row in 0..rows {
+                            for col in 0..cols {
+                                let Some(&idx) = slots.get(row * cols + col) else {
+                                    continue;
                                 };
-                                painter.text(
-                                    Pos2::new(rect.right() - 10.0, rect.bottom() - 10.0),
-                                    Align2::RIGHT_BOTTOM,
-                                    time_str,
-                                    FontId::proportional(16.0),
-                                    time_color,
-                                );
-                                // Draw progress slider if playing
-                                if Some((self.current_tab, idx)) == self.current_playing {
-                                    let elapsed = self.audio_player.elapsed();
-                                    let progress = (elapsed / button.duration).min(1.0);
-                                    let x = rect.left() + progress * rect.width();
-                                    painter.line_segment([
-                                        Pos2::new(x, rect.top()),
-                                        Pos2::new(x, rect.bottom())
-                                    ], Stroke::new(2.0, Color32::RED));
-                                }
-                                // Interactivity
-                                let resp = ui.interact(rect, ui.make_persistent_id((row, col)), egui::Sense::click());
-                                if self.edit_mode {
-                                    if resp.clicked() {
-                                        self.edit_state.editing = Some(idx);
-                                        self.edit_state.name_buf = button.name.clone();
-                                        self.edit_state.color_buf = button.color;
-                                    }
-                                } else if resp.clicked() {
-                                    if Some((self.current_tab, idx)) == self.current_playing {
-                                        self.audio_player.fade_out();
-                                        self.current_playing = None;
-                                    } else {
-                                        if self.current_playing.is_some() {
-                                            self.audio_player.fade_out();
+                                let button_opt = tab.buttons.get_mut(idx);
+                                if let Some(button) = button_opt {
+                                    if !button.name.is_empty() {
+                                        let (id, rect) = ui.allocate_space(Vec2::new(btn_w, btn_h));
+                                        let painter = ui.painter_at(rect);
+                                        Self::paint_backing(&painter, rect, self.config.dark_mode);
+                                        // Cover art goes underneath everything; the overlay keeps it faint
+                                        if !button.info.artwork.is_empty() {
+                                            let texture = self.artwork_textures.entry(button.path.clone()).or_insert_with(|| {
+                                                let name = format!("artwork:{}", button.path.display());
+                                                Self::artwork_image(&button.info.artwork).map(|image| ui.ctx().load_texture(name, image, Default::default()))
+                                            });
+                                            if let Some(texture) = texture {
+                                                Self::paint_artwork(&painter, rect, texture);
+                                            }
+                                        }
+                                        // Draw waveform background; tones have no file to key the scale by
+                                        let level = if !self.config.normalize_waveform {
+                                            1.0
+                                        } else if button.tone.is_some() {
+                                            button.waveform_scale()
+                                        } else {
+                                            *self.waveform_scales.entry(button.path.clone()).or_insert_with(|| button.waveform_scale())
+                                        };
+                                        Self::paint_waveform(&painter, rect, button, &self.config, button.trim_span(), level);
+                                        // Draw button overlay
+                                        painter.rect_filled(rect, 8.0, button.color.gamma_multiply(0.7));
+                                        // Draw name
+                                        let name_size = self.config.name_font_size(rect.size());
+                                        Self::paint_name(&painter, rect, &button.name, name_size);
+                                        // Draw duration/remaining
+                                        let looping = button.playback.loop_enabled && button.tone.is_none();
+                                        // Pause and seeking only act on the current cue; layers just show their own clock
+                                        let playing = Some((self.current_tab, idx)) == self.current_playing;
+                                        let elapsed = if playing {
+                                            Some(self.audio_player.elapsed())
+                                        } else {
+                                            self.audio_player.layer_elapsed((self.current_tab, idx))
+                                        };
+                                        let decoding = self.pending_waveforms.contains_key(&(self.current_tab, idx));
+                                        let (time_str, time_color) = if decoding {
+                                            ("Loading…".to_string(), Color32::LIGHT_GRAY)
+                                        } else if let Some(elapsed) = elapsed {
+                                            let remaining = (button.play_length() - elapsed).max(0.0);
+                                            let time = Self::format_time(if looping { button.play_length() } else { remaining });
+                                            if playing && self.audio_player.is_paused() {
+                                                (format!("⏸ {}", time), Color32::LIGHT_BLUE)
+                                            } else if looping {
+                                                (time, Color32::YELLOW)
+                                            } else {
+                                                (time, Self::remaining_color(remaining, self.config.warn_remaining_secs))
+                                            }
+                                        } else {
+                                            (Self::format_time(button.play_length()), Color32::WHITE)
+                                        };
+                                        painter.text(
+                                            Pos2::new(rect.right() - 10.0, rect.bottom() - 10.0),
+                                            Align2::RIGHT_BOTTOM,
+                                            time_str,
+                                            Self::time_font(name_size),
+                                            time_color,
+                                        );
+                                        // Decode progress along the bottom edge, or a spinner when the file doesn't state its length
+                                        if decoding {
+                                            match self.waveform_progress.get(&button.path) {
+                                                Some(&fraction) => {
+                                                    let track = egui::Rect::from_min_max(
+                                                        Pos2::new(rect.left() + 10.0, rect.bottom() - 6.0),
+                                                        Pos2::new(rect.right() - 10.0, rect.bottom() - 3.0),
+                                                    );
+                                                    painter.rect_filled(track, 1.5, Color32::from_black_alpha(120));
+                                                    let done = egui::Rect::from_min_size(track.min, Vec2::new(track.width() * fraction, track.height()));
+                                                    painter.rect_filled(done, 1.5, Color32::LIGHT_GRAY);
+                                                }
+                                                None => {
+                                                    let spot = egui::Rect::from_min_size(Pos2::new(rect.left() + 10.0, rect.bottom() - 26.0), Vec2::splat(16.0));
+                                                    egui::Spinner::new().size(16.0).color(Color32::LIGHT_GRAY).paint_at(ui, spot);
+                                                }
+                                            }
+                                        }
+                                        // Draw progress slider if playing
+                                        if let Some(elapsed) = elapsed {
+                                            // A loop wraps the marker back to the start on every pass
+                                            let length = button.play_length();
+                                            let elapsed = if looping && length > 0.0 { elapsed % length } else { elapsed };
+                                            let progress = (elapsed / length).min(1.0);
+                                            let x = rect.left() + progress * rect.width();
+                                            painter.line_segment([
+                                                Pos2::new(x, rect.top()),
+                                                Pos2::new(x, rect.bottom())
+                                            ], Stroke::new(2.0, Color32::RED));
+                                        }
+                                        // Draw hotkey badge, flagged when shared with another button
+                                        if let Some(key) = button.hotkey {
+                                            let (badge, badge_color) = if conflicting_hotkeys.contains(&key) {
+                                                (format!("⚠ {}", key.name()), Color32::from_rgb(255, 160, 0))
+                                            } else {
+                                                (key.name().to_string(), Color32::WHITE)
+                                            };
+                                            painter.text(
+                                                Pos2::new(rect.left() + 10.0, rect.top() + 8.0),
+                                                Align2::LEFT_TOP,
+                                                badge,
+                                                FontId::proportional(14.0),
+                                                badge_color,
+                                            );
+                                        }
+                                        if prelistening == Some((self.current_tab, idx)) {
+                                            painter.text(
+                                                Pos2::new(rect.center().x, rect.top() + 8.0),
+                                                Align2::CENTER_TOP,
+                                                "🎧 pre-listen",
+                                                FontId::proportional(14.0),
+                                                Color32::LIGHT_BLUE,
+                                            );
+                                        }
+                                        // Outline search matches
+                                        if search_matches.contains(&(self.current_tab, idx)) {
+                                            painter.rect_stroke(rect.shrink(1.5), 8.0, Stroke::new(3.0, Color32::LIGHT_BLUE));
+                                        }
+                                        if flashing == Some(((self.current_tab, idx), true)) {
+                                            painter.rect_filled(rect, 8.0, Color32::WHITE.gamma_multiply(0.4));
+                                        }
+                                        // Dashed outline and a marker for a file that wasn't found on load
+                                        let missing = button.is_missing(&self.missing_paths);
+                                        if missing {
+                                            let missing_color = Color32::from_rgb(255, 160, 0);
+                                            let r = rect.shrink(3.0);
+                                            let corners = [r.left_top(), r.right_top(), r.right_bottom(), r.left_bottom(), r.left_top()];
+                                            painter.extend(egui::Shape::dashed_line(&corners, Stroke::new(2.0, missing_color), 8.0, 5.0));
+                                            painter.text(
+                                                Pos2::new(rect.right() - 10.0, rect.top() + 8.0),
+                                                Align2::RIGHT_TOP,
+                                                "⚠ missing",
+                                                FontId::proportional(12.0),
+                                                missing_color,
+                                            );
+                                        }
+                                        // Flag a pad whose last trigger failed (moved or deleted file, ...)
+                                        if button.play_error.is_some() {
+                                            painter.rect_stroke(rect.shrink(1.5), 8.0, Stroke::new(3.0, Color32::RED));
+                                        }
+                                        // Interactivity; buttons can be dragged to another slot in edit mode
+                                        slot_rects.push((idx, rect.intersect(ui.clip_rect())));
+                                        let sense = if self.edit_mode { egui::Sense::click_and_drag() } else { egui::Sense::click() };
+                                        let mut resp = ui
+                                            .interact(rect, ui.make_persistent_id((row, col)), sense)
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                        if resp.drag_started() {
+                                            self.dragging_button = Some(idx);
                                         }
-                                        self.audio_player.play(&button.path, button.duration);
-                                        self.current_playing = Some((self.current_tab, idx));
+                                        resp = resp.on_hover_ui(|ui| Self::button_tooltip(ui, button, elapsed.is_some() && !self.edit_mode, missing));
+                                        if resp.hovered() {
+                                            Self::paint_hover(&painter, rect, self.config.hover_style);
+                                        }
+                                        if self.edit_mode {
+                                            if resp.clicked() {
+                                                self.edit_state.editing = Some(idx);
+                                                self.edit_state.name_buf = button.name.clone();
+                                                self.edit_state.color_buf = button.color;
+                                                self.edit_state.hotkey_buf = button.hotkey;
+                                                self.edit_state.playback_buf = button.playback;
+                                                self.edit_state.tone_buf = button.tone;
+                                                self.edit_state.capturing_hotkey = false;
+                                                self.edit_state.learning_midi = false;
+                                                self.edit_state.confirm_delete = false;
+                                            }
+                                        } else if resp.clicked() {
+                                            let seek_strip = rect.bottom() - rect.height() * SEEK_STRIP;
+                                            // Shift+click pauses the playing cue instead of fading it out, Ctrl+click restarts it,
+                                            // Alt+click plays it on the pre-listen output
+                                            let (shift, ctrl, alt) = ui.input(|i| (i.modifiers.shift, i.modifiers.command, i.modifiers.alt));
+                                            match resp.interact_pointer_pos() {
+                                                _ if alt => prelistened = Some(idx),
+                                                _ if playing && shift => pause_toggled = true,
+                                                _ if elapsed.is_some() && ctrl => restarted = Some(idx),
+                                                Some(pos) if playing && pos.y >= seek_strip => {
+                                                    seek_to = Some((idx, ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0)));
+                                                }
+                                                _ => triggered = Some(idx),
+                                            }
+                                        } else if playing
+                                            && let Some(pos) = resp.hover_pos()
+                                            && pos.y >= rect.bottom() - rect.height() * SEEK_STRIP
+                                        {
+                                            // Show the seek target under the pointer
+                                            painter.line_segment([Pos2::new(pos.x, rect.top()), Pos2::new(pos.x, rect.bottom())], Stroke::new(1.0, Color32::WHITE));
+                                        }
+                                    } else {
+                                        // Empty slot
+                                        let id = ui.make_persistent_id((row, col));
+                                        let rect = Self::empty_slot_ui(ui, Vec2::new(btn_w, btn_h), id, &self.config, &mut self.edit_state, self.clipboard.as_ref(), idx);
+                                        slot_rects.push((idx, rect.intersect(ui.clip_rect())));
                                     }
-                                }
-                            } else {
-                                // Empty slot
-                                let (id, rect) = ui.allocate_space(Vec2::new(btn_w, btn_h));
-                                let resp = ui.interact(rect, ui.make_persistent_id((row, col)), egui::Sense::click());
-                                ui.painter_at(rect).rect_filled(rect, 8.0, Color32::DARK_GRAY.gamma_multiply(0.5));
-                                ui.painter_at(rect).text(
-                                    rect.center(),
-                                    Align2::CENTER_CENTER,
-                                    "Click to add...",
-                                    FontId::proportional(20.0),
-                                    Color32::WHITE,
-                                );
-                                if resp.clicked() {
-                                    self.edit_state.pending_music_slot = Some(idx);
+                                } else {
+                                    // Slot not yet created
+                                    let id = ui.make_persistent_id((row, col));
+                                    let rect = Self::empty_slot_ui(ui, Vec2::new(btn_w, btn_h), id, &self.config, &mut self.edit_state, self.clipboard.as_ref(), idx);
+                                    slot_rects.push((idx, rect.intersect(ui.clip_rect())));
                                 }
                             }
-                        } else {
-                            // Slot not yet created
-                            let (id, rect) = ui.allocate_space(Vec2::new(btn_w, btn_h));
-                            let resp = ui.interact(rect, ui.make_persistent_id((row, col)), egui::Sense::click());
-                            ui.painter_at(rect).rect_filled(rect, 8.0, Color32::DARK_GRAY.gamma_multiply(0.5));
-                            ui.painter_at(rect).text(
-                                rect.center(),
-                                Align2::CENTER_CENTER,
-                                "Click to add...",
-                                FontId::proportional(20.0),
-                                Color32::WHITE,
-                            );
-                            if resp.clicked() {
-                                self.edit_state.pending_music_slot = Some(idx);
+                            if page_slot && row + 1 == rows {
+                                page_action = Self::page_slot_ui(ui, btn_h, pages);
                             }
+                            ui.end_row();
                         }
+                    });
+                });
+            });
+            // Drag-and-drop: the button follows the pointer and lands on the slot under it
+            if let Some(from) = self.dragging_button {
+                let pointer = ctx.pointer_interact_pos();
+                let target = pointer.and_then(|pos| slot_rects.iter().find(|(_, rect)| rect.contains(pos)));
+                if ctx.input(|i| i.pointer.any_released()) {
+                    self.dragging_button = None;
+                    dropped = target.map(|&(to, _)| (from, to));
+                } else if let (Some(pos), Some(button)) = (pointer, tab.buttons.get(from)) {
+                    if let Some((_, rect)) = target {
+                        ui.painter().rect_stroke(*rect, 8.0, Stroke::new(2.0, Color32::YELLOW));
                     }
-                    ui.end_row();
+                    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Tooltip, egui::Id::new("dragged_button")));
+                    let rect = egui::Rect::from_center_size(pos, Vec2::new(btn_w, btn_h));
+                    painter.rect_filled(rect, 8.0, button.color.gamma_multiply(0.5));
+                    Self::paint_name(&painter, rect, &button.name, self.config.name_font_size(rect.size()));
                 }
-            });
+            }
             // Edit popup
             if let Some(edit_idx) = self.edit_state.editing {
-                egui::Window::new("Edit Music Button")
+                let dismiss = self.config.edit_dismiss;
+                if dismiss == EditDismiss::Modal {
+                    // Full-screen backdrop under the window that swallows clicks on everything else
+                    let screen = ctx.screen_rect();
+                    egui::Area::new("edit_modal_backdrop")
+                        .order(egui::Order::Middle)
+                        .fixed_pos(screen.min)
+                        .show(ctx, |ui| {
+                            ui.allocate_response(screen.size(), egui::Sense::click_and_drag());
+                            ui.painter().rect_filled(screen, 0.0, Color32::from_black_alpha(120));
+                        });
+                }
+                let mut save_edit = false;
+                let mut delete_edit = false;
+                let mut copy_edit = false;
+                let mut duplicate_edit = false;
+                let edit_missing = tab.buttons.get(edit_idx).is_some_and(|button| button.is_missing(&self.missing_paths));
+                let window = egui::Window::new("Edit Music Button")
                     .collapsible(false)
                     .resizable(false)
                     .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
@@ -498,12 +5818,116 @@ impl eframe::App for MusicInterface {
                         ui.label("Color:");
                         ui.color_edit_button_srgba(&mut self.edit_state.color_buf);
                         ui.horizontal(|ui| {
-                            if ui.button("Save").clicked() {
-                                if let Some(button) = tab.buttons.get_mut(edit_idx) {
-                                    button.name = self.edit_state.name_buf.clone();
-                                    button.color = self.edit_state.color_buf;
+                            let mut custom = self.edit_state.playback_buf.waveform_color.is_some();
+                            if ui.checkbox(&mut custom, "Waveform color:").changed() {
+                                // Start from the shade it is drawn in now
+                                self.edit_state.playback_buf.waveform_color = custom.then(|| self.edit_state.color_buf.gamma_multiply(0.3));
+                            }
+                            match &mut self.edit_state.playback_buf.waveform_color {
+                                Some(color) => {
+                                    ui.color_edit_button_srgba(color);
                                 }
-                                self.edit_state.editing = None;
+                                None => {
+                                    ui.weak("from the button color");
+                                }
+                            }
+                        });
+                        if let Some(button) = tab.buttons.get(edit_idx)
+                            && button.start_latency_ms > 0.0
+                        {
+                            ui.label(format!("Start latency: {:.0} ms", button.start_latency_ms));
+                        }
+                        ui.label("Hotkey:");
+                        ui.horizontal(|ui| {
+                            if self.edit_state.capturing_hotkey {
+                                ui.label("Press a key... (Esc to cancel)");
+                                let captured = ui.input(|i| {
+                                    i.events.iter().find_map(|event| match event {
+                                        egui::Event::Key { key, pressed: true, .. } => Some(*key),
+                                        _ => None,
+                                    })
+                                });
+                                if let Some(key) = captured {
+                                    if key != egui::Key::Escape {
+                                        self.edit_state.pending_hotkey = Some(key);
+                                    }
+                                    self.edit_state.capturing_hotkey = false;
+                                }
+                            } else {
+                                let label = self.edit_state.hotkey_buf.map_or("None", |key| key.name());
+                                ui.label(label);
+                                if ui.button("Set...").clicked() {
+                                    self.edit_state.capturing_hotkey = true;
+                                }
+                                if self.edit_state.hotkey_buf.is_some() && ui.button("Clear").clicked() {
+                                    self.edit_state.hotkey_buf = None;
+                                }
+                            }
+                        });
+                        ui.label("MIDI note:");
+                        ui.horizontal(|ui| {
+                            if self.edit_state.learning_midi {
+                                ui.label("Hit a pad on the controller...");
+                                if ui.button("Cancel").clicked() {
+                                    self.edit_state.learning_midi = false;
+                                }
+                            } else {
+                                let note = self.edit_state.playback_buf.midi_note;
+                                ui.label(note.map_or("None".to_string(), MidiListener::note_name));
+                                let learn = ui
+                                    .add_enabled(self.midi_input.is_some(), egui::Button::new("Learn"))
+                                    .on_hover_text("Assign the next note received from the controller")
+                                    .on_disabled_hover_text("Turn on MIDI input in Settings first");
+                                if learn.clicked() {
+                                    self.edit_state.learning_midi = true;
+                                }
+                                if note.is_some() && ui.button("Clear").clicked() {
+                                    self.edit_state.playback_buf.midi_note = None;
+                                }
+                            }
+                        });
+                        if let Some(tone) = &mut self.edit_state.tone_buf {
+                            ui.label("Test tone:");
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut tone.kind, ToneKind::Sine, "Sine");
+                                ui.selectable_value(&mut tone.kind, ToneKind::PinkNoise, "Pink noise");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(
+                                    tone.kind == ToneKind::Sine,
+                                    egui::DragValue::new(&mut tone.frequency).clamp_range(20.0..=20_000.0).speed(10.0).suffix(" Hz"),
+                                );
+                                ui.add(egui::DragValue::new(&mut tone.level_db).clamp_range(-60.0..=0.0).speed(0.5).suffix(" dBFS"));
+                                ui.add(egui::DragValue::new(&mut tone.duration).clamp_range(0.5..=600.0).speed(0.5).suffix(" s"));
+                            });
+                        }
+                        self.edit_state.playback_buf.ui(ui, self.config.end_fade_ms);
+                        if self.edit_state.tone_buf.is_none()
+                            && let Some(button) = tab.buttons.get(edit_idx)
+                            && button.duration > 0.0
+                        {
+                            ui.label("Trim:");
+                            Self::trim_ui(ui, button, &mut self.edit_state.playback_buf, &self.config);
+                        }
+                        if let Some(button) = tab.buttons.get(edit_idx) {
+                            ui.weak(button.info.stats.summary(SystemTime::now()).unwrap_or_else(|| "Not played yet".to_string()));
+                        }
+                        if let Some(button) = tab.buttons.get(edit_idx)
+                            && !button.info.tags.is_empty()
+                        {
+                            ui.collapsing("File tags", |ui| {
+                                egui::Grid::new("file_tags").num_columns(2).show(ui, |ui| {
+                                    for (key, value) in &button.info.tags {
+                                        ui.weak(key);
+                                        ui.label(value);
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Save").clicked() {
+                                save_edit = true;
                             }
                             if ui.button("Cancel").clicked() {
                                 self.edit_state.editing = None;
@@ -511,60 +5935,562 @@ impl eframe::App for MusicInterface {
                             if ui.button("Change Music").clicked() {
                                 self.edit_state.pending_change_music = Some(edit_idx);
                             }
+                            if edit_missing && ui.button("Relink…").on_hover_text("Point the button at the file's new location, keeping its name and settings").clicked() {
+                                self.edit_state.pending_relink = Some(edit_idx);
+                            }
+                            if ui.button("Duplicate").on_hover_text("Put a copy of the button as saved into the first free slot of this tab").clicked() {
+                                duplicate_edit = true;
+                            }
+                            if ui.button("Copy").on_hover_text("Copy the button as saved; click an empty slot on any tab to paste it").clicked() {
+                                copy_edit = true;
+                            }
+                            let label = if self.edit_state.confirm_delete { "Are you sure?" } else { "Delete" };
+                            if ui.button(egui::RichText::new(label).color(Color32::LIGHT_RED)).clicked() {
+                                delete_edit = self.edit_state.confirm_delete;
+                                self.edit_state.confirm_delete = true;
+                            }
                         });
                     });
+                if let Some(window) = window {
+                    match dismiss {
+                        EditDismiss::Modal => ctx.move_to_top(window.response.layer_id),
+                        EditDismiss::ClickAwayCancel | EditDismiss::ClickAwaySave => {
+                            // Only presses that land on the board or panels count, not on popups like the colour picker
+                            let pressed_outside = ctx
+                                .input(|i| i.pointer.any_pressed().then_some(i.pointer.interact_pos()).flatten())
+                                .is_some_and(|pos| {
+                                    !window.response.rect.contains(pos)
+                                        && ctx.layer_id_at(pos).is_none_or(|layer| layer.order == egui::Order::Background)
+                                });
+                            if pressed_outside {
+                                if dismiss == EditDismiss::ClickAwaySave {
+                                    save_edit = true;
+                                } else {
+                                    self.edit_state.editing = None;
+                                }
+                            }
+                        }
+                    }
+                }
+                if save_edit {
+                    self.commit_edit(edit_idx);
+                }
+                if copy_edit && let Some(button) = self.tabs[self.current_tab].buttons.get(edit_idx) {
+                    let message = format!("Copied \"{}\"", button.name);
+                    self.clipboard = Some(button.clone());
+                    self.toast(message);
+                }
+                if duplicate_edit {
+                    match self.duplicate_button(edit_idx) {
+                        Ok(slot) => self.flash = Some(((self.current_tab, slot), Instant::now())),
+                        Err(err) => self.toast(err),
+                    }
+                }
+                if delete_edit {
+                    self.delete_button(self.current_tab, edit_idx);
+                }
             }
 
+            if let Some(idx) = triggered {
+                self.trigger_button(self.current_tab, idx);
+            }
+            if pause_toggled {
+                self.toggle_pause();
+            }
+            if let Some(idx) = restarted {
+                self.restart_button(self.current_tab, idx);
+            }
+            if let Some(idx) = prelistened {
+                self.prelisten_button(self.current_tab, idx);
+            }
+            if let Some((from, to)) = dropped {
+                self.swap_buttons(self.current_tab, from, to);
+            }
+            match page_action {
+                Some(true) => self.add_page(),
+                Some(false) => {
+                    if let Err(err) = self.remove_last_page() {
+                        self.toast(err);
+                    }
+                }
+                None => {}
+            }
+            if let Some((idx, fraction)) = seek_to
+                && let Some(button) = self.tabs[self.current_tab].buttons.get(idx)
+            {
+                let target = Duration::from_secs_f32(button.trimmed_duration() * fraction);
+                // A file that can't be seeked simply keeps playing
+                if let Err(err) = self.audio_player.seek(button, target) {
+                    let message = format!("Can't seek in \"{}\": {}", button.name, err);
+                    self.toast(message);
+                }
+            }
+            if let Some(key) = self.edit_state.pending_hotkey.take() {
+                self.assign_captured_hotkey(key);
+            }
             // After UI: process add requests
             if let Some(slot) = self.edit_state.pending_music_slot.take() {
-                if let Some(path) = FileDialog::new()
-                    .add_filter("Audio", &["mp3", "wav"])
+                self.add_music_at(slot);
+            }
+            if let Some(slot) = self.edit_state.pending_tone_slot.take() {
+                self.add_tone_at(slot);
+            }
+            if let Some(slot) = self.edit_state.pending_paste_slot.take() {
+                self.paste_at(slot);
+            }
+            if let Some(edit_idx) = self.edit_state.pending_change_music.take()
+                && let Some(path) = FileDialog::new()
+                    .add_filter("Audio", AUDIO_EXTENSIONS)
                     .pick_file()
-                {
-                    let name = path.file_name().unwrap().to_string_lossy().to_string();
-                    let (waveform, duration) = Self::generate_waveform_and_duration(&path);
-                    let tab = &mut self.tabs[self.current_tab];
-                    if tab.buttons.len() <= slot {
-                        tab.buttons.resize_with(slot + 1, || MusicButton {
-                            name: String::new(),
-                            path: PathBuf::new(),
-                            position: Vec2::ZERO,
-                            color: Color32::from_rgb(100, 100, 255),
-                            waveform: vec![],
-                            duration: 0.0,
-                        });
+            {
+                let key = (self.current_tab, edit_idx);
+                if self.slot_in_use(key) {
+                    self.edit_state.pending_replace = Some((key, path));
+                } else {
+                    self.change_music(key, path);
+                }
+            }
+            if let Some(edit_idx) = self.edit_state.pending_relink.take()
+                && let Some(path) = FileDialog::new()
+                    .add_filter("Audio", AUDIO_EXTENSIONS)
+                    .pick_file()
+            {
+                self.relink_button((self.current_tab, edit_idx), path);
+            }
+        });
+        if self.show_settings {
+            let mut open = true;
+            let mut changed = false;
+            let mut switch_output = false;
+            let mut switch_prelisten = false;
+            egui::Window::new("Settings")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label("On startup, open:");
+                    let startup = &mut self.config.startup_board;
+                    changed |= ui.radio_value(startup, StartupBoard::Empty, "An empty board").changed();
+                    changed |= ui.radio_value(startup, StartupBoard::LastFile, "The last used board").changed();
+                    ui.horizontal(|ui| {
+                        let fixed = matches!(startup, StartupBoard::Fixed(_));
+                        if (ui.radio(fixed, "A default board").clicked() || ui.button("Browse...").clicked())
+                            && let Some(path) = FileDialog::new().pick_file()
+                        {
+                            *startup = StartupBoard::Fixed(path);
+                            changed = true;
+                        }
+                        if let StartupBoard::Fixed(path) = startup {
+                            ui.label(path.display().to_string());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Autosave every:");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.autosave_secs).clamp_range(0..=3600).suffix(" s"))
+                            .on_hover_text("Only when the board changed; 0 = off")
+                            .changed();
+                    });
+                    ui.separator();
+                    if ui
+                        .add(egui::Slider::new(&mut self.config.ui_scale, UI_SCALE_RANGE).text("Interface scale").suffix("×"))
+                        .on_hover_text("Makes everything larger or smaller, on top of the system's display scaling")
+                        .changed()
+                    {
+                        self.config.apply_ui_scale(ui.ctx());
+                        changed = true;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Button names:");
+                        changed |= ui.add(egui::DragValue::new(&mut self.config.name_font_min).clamp_range(NAME_FONT_RANGE).prefix("min ").suffix(" pt")).changed();
+                        changed |= ui.add(egui::DragValue::new(&mut self.config.name_font_max).clamp_range(NAME_FONT_RANGE).prefix("max ").suffix(" pt")).changed();
+                    })
+                    .response
+                    .on_hover_text("Names grow and shrink with the buttons, within these sizes; long names wrap onto two lines");
+                    ui.horizontal(|ui| {
+                        let mut limited = self.config.max_pad_aspect().is_some();
+                        if ui.checkbox(&mut limited, "Limit button width to").changed() {
+                            self.config.max_pad_aspect = if limited { 3.0 } else { 0.0 };
+                            changed = true;
+                        }
+                        if limited {
+                            changed |= ui
+                                .add(egui::DragValue::new(&mut self.config.max_pad_aspect).clamp_range(PAD_ASPECT_RANGE).speed(0.05).max_decimals(2).suffix("× the height"))
+                                .changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text("On wide windows buttons stop getting wider at this shape, and the grid is centred instead");
+                    ui.separator();
+                    ui.label("Waveform style for new imports:");
+                    let mode = &mut self.config.waveform_mode;
+                    changed |= ui.radio_value(mode, WaveformMode::MinMax, "Min/max (centred)").changed();
+                    changed |= ui.radio_value(mode, WaveformMode::Peak, "Peak (from bottom)").changed();
+                    changed |= ui.radio_value(mode, WaveformMode::Rms, "RMS (from bottom)").changed();
+                    changed |= ui
+                        .add(egui::Slider::new(&mut self.config.waveform_points, WAVEFORM_POINTS_RANGE).logarithmic(true).text("Waveform detail (points)"))
+                        .on_hover_text(
+                            "Points per waveform, however long the track. More shows finer detail in short stingers; \
+                             fewer keeps long beds smooth and makes boards and the waveform cache smaller. \
+                             Applies to new imports.",
+                        )
+                        .changed();
+                    changed |= ui
+                        .add(egui::Slider::new(&mut self.config.waveform_height, WAVEFORM_HEIGHT_RANGE).text("Waveform height"))
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.config.normalize_waveform, "Normalize waveform height")
+                        .on_hover_text("Quiet tracks fill the pad too; turn off to compare levels between pads")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Draw waveforms as:");
+                        let style = &mut self.config.waveform_style;
+                        changed |= ui.selectable_value(style, WaveformStyle::Filled, "Filled").changed();
+                        changed |= ui.selectable_value(style, WaveformStyle::Lines, "Lines").changed();
+                    });
+                    if ui
+                        .button("Clear waveform cache")
+                        .on_hover_text("Imports decode their files again; existing pads keep their waveforms")
+                        .clicked()
+                    {
+                        self.clear_waveform_cache();
+                    }
+                    ui.separator();
+                    ui.label("When several buttons share a hotkey:");
+                    let conflict = &mut self.config.hotkey_conflict;
+                    changed |= ui.radio_value(conflict, HotkeyConflict::Reject, "Refuse the assignment").changed();
+                    changed |= ui.radio_value(conflict, HotkeyConflict::PreferCurrentTab, "Fire the one on the current tab").changed();
+                    changed |= ui
+                        .radio_value(conflict, HotkeyConflict::TriggerAll, "Fire all of them")
+                        .on_hover_text("Together, in polyphonic mode. Otherwise only one cue plays at a time, so the one on the current tab fires.")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.config.quickfire_enabled, "Number keys fire the first ten slots")
+                        .on_hover_text("1-9 and 0, on the page shown; a digit assigned as a button's hotkey keeps firing that button")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.config.fade_key_enabled, "F fades out the current cue")
+                        .on_hover_text("Like clicking the playing button. A button with F as its hotkey keeps it.")
+                        .changed();
+                    ui.separator();
+                    changed |= ui
+                        .checkbox(&mut self.config.polyphonic, "Polyphonic: buttons play on top of each other")
+                        .on_hover_text("Every button gets its own voice and clicking a playing one stops only that one. Transitions, pause and seeking apply to cues started with this off.")
+                        .changed();
+                    ui.label("When a new cue starts over a playing one:");
+                    let transition = &mut self.config.transition;
+                    changed |= ui.radio_value(transition, Transition::FadeOut, "Fade the old cue out").changed();
+                    changed |= ui.radio_value(transition, Transition::HardCut, "Cut everything immediately").changed();
+                    ui.horizontal(|ui| {
+                        changed |= ui.radio_value(transition, Transition::Crossfade, "Crossfade over").changed();
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.crossfade_ms).clamp_range(100..=10_000).speed(10).suffix(" ms"))
+                            .changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Fade curve:");
+                        let curve = &mut self.config.fade_curve;
+                        changed |= ui.selectable_value(curve, FadeCurve::Linear, "Linear").changed();
+                        changed |= ui
+                            .selectable_value(curve, FadeCurve::Log, "Logarithmic")
+                            .on_hover_text("Drops evenly in loudness, so the end of a fade doesn't vanish all at once")
+                            .changed();
+                        changed |= ui
+                            .selectable_value(curve, FadeCurve::EqualPower, "Equal power")
+                            .on_hover_text("Best for crossfades: the level doesn't dip halfway through")
+                            .changed();
+                    });
+                    changed |= ui
+                        .checkbox(&mut self.config.preload_sinks, "Preload pads for instant triggers")
+                        .on_hover_text("Keeps every pad's file open and primed. Uses more memory and file handles.")
+                        .changed();
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Tabs:");
+                        let tab_bar = &mut self.config.tab_bar;
+                        changed |= ui.selectable_value(tab_bar, TabBarPosition::Top, "Top bar").changed();
+                        changed |= ui.selectable_value(tab_bar, TabBarPosition::Left, "Left sidebar").changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Triggers during a cooldown:");
+                        let cooldown = &mut self.config.cooldown_action;
+                        changed |= ui.selectable_value(cooldown, CooldownAction::Ignore, "Ignore").changed();
+                        changed |= ui.selectable_value(cooldown, CooldownAction::Queue, "Queue").changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Clicking outside the edit popup:");
+                        let dismiss = &mut self.config.edit_dismiss;
+                        changed |= ui.selectable_value(dismiss, EditDismiss::Modal, "Is blocked").changed();
+                        changed |= ui.selectable_value(dismiss, EditDismiss::ClickAwayCancel, "Cancels").changed();
+                        changed |= ui.selectable_value(dismiss, EditDismiss::ClickAwaySave, "Saves").changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Warn when less than");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.warn_remaining_secs).clamp_range(0..=120).suffix(" s"))
+                            .on_hover_text("The remaining time turns orange, then red, as a track nears its end (0 = off)")
+                            .changed();
+                        ui.label("remain");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Default end fade:");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.end_fade_ms).clamp_range(0..=10_000).speed(10).suffix(" ms"))
+                            .on_hover_text("Fade out over the last part of every track (0 = off). Buttons can override this.")
+                            .changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Loudness target:");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.loudness_target_db).clamp_range(LOUDNESS_TARGET_RANGE).speed(0.1).suffix(" dB"))
+                            .on_hover_text("Level that Normalize levels (edit mode) brings each button to. Lower leaves more headroom for quiet files, which can only be raised 6 dB.")
+                            .changed();
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Output device:");
+                        let devices = self.output_devices.get_or_insert_with(AudioPlayer::output_devices);
+                        let mut choice = self.config.output_device.clone();
+                        egui::ComboBox::from_id_source("output_device")
+                            .selected_text(choice.as_deref().unwrap_or("System default"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut choice, None, "System default");
+                                for device in devices.iter() {
+                                    ui.selectable_value(&mut choice, Some(device.clone()), device);
+                                }
+                            });
+                        if ui.small_button("↻").on_hover_text("List the devices again").clicked() {
+                            self.output_devices = None;
+                        }
+                        if choice != self.config.output_device {
+                            self.config.output_device = choice;
+                            switch_output = true;
+                            changed = true;
+                        }
+                    });
+                    if self.config.output_device.is_some() && self.audio_player.device.is_none() && self.audio_player.has_output() {
+                        ui.weak("Not available, using the system default");
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Pre-listen device:");
+                        let devices = self.output_devices.get_or_insert_with(AudioPlayer::output_devices);
+                        let mut choice = self.config.prelisten_device.clone();
+                        egui::ComboBox::from_id_source("prelisten_device")
+                            .selected_text(choice.as_deref().unwrap_or("Off"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut choice, None, "Off");
+                                for device in devices.iter() {
+                                    ui.selectable_value(&mut choice, Some(device.clone()), device);
+                                }
+                            })
+                            .response
+                            .on_hover_text("Alt+click a button to hear it here (headphones) instead of on air; again to stop it");
+                        if choice != self.config.prelisten_device {
+                            self.config.prelisten_device = choice;
+                            switch_prelisten = true;
+                            changed = true;
+                        }
+                    });
+                    if self.config.prelisten_device.is_some() && self.audio_player.prelisten.is_none() {
+                        ui.weak("Not available");
                     }
-                    tab.buttons[slot] = MusicButton {
-                        name,
-                        path,
-                        position: Vec2::ZERO,
-                        color: Color32::from_rgb(100, 100, 255),
-                        waveform,
-                        duration,
-                    };
+                    changed |= ui
+                        .checkbox(&mut self.config.mono_output, "Mono output")
+                        .on_hover_text("Sums left and right to check how the mix sounds on a mono radio or phone. Recordings are mono too while it's on.")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut self.config.snap_to_zero, "Snap starts to zero crossings")
+                        .on_hover_text("Seeks and trimmed starts begin at the next point where the wave crosses zero (at most 10 ms later), so they don't click. Untick for exact positions.")
+                        .changed();
+                    ui.separator();
+                    changed |= ui
+                        .checkbox(&mut self.config.ipc_enabled, "Enable control socket")
+                        .on_hover_text("Accepts play/stop/fade/load commands over TCP. See the README for the protocol.")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Listen on:");
+                        changed |= Self::committed_text_edit(ui, &mut self.config.ipc_address);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Pad hover highlight:");
+                        let hover = &mut self.config.hover_style;
+                        changed |= ui.selectable_value(hover, HoverStyle::Border, "Border").changed();
+                        changed |= ui.selectable_value(hover, HoverStyle::Brighten, "Brighten").changed();
+                        changed |= ui.selectable_value(hover, HoverStyle::Off, "Off").changed();
+                    });
+                    ui.separator();
+                    changed |= ui
+                        .checkbox(&mut self.config.web_enabled, "Enable browser remote")
+                        .on_hover_text("Serves a page listing the board, to fire buttons from a phone. Anyone who can reach the address can use it.")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Listen on:");
+                        changed |= Self::committed_text_edit(ui, &mut self.config.web_address);
+                    });
+                    ui.separator();
+                    changed |= ui
+                        .checkbox(&mut self.config.midi_enabled, "MIDI input")
+                        .on_hover_text("Fire buttons from a MIDI pad controller; assign notes with Learn in the edit popup")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Port:");
+                        let ports = self.midi_ports.get_or_insert_with(MidiListener::ports);
+                        let mut choice = self.config.midi_port.clone();
+                        egui::ComboBox::from_id_source("midi_port")
+                            .selected_text(choice.as_deref().unwrap_or("First available"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut choice, None, "First available");
+                                for port in ports.iter() {
+                                    ui.selectable_value(&mut choice, Some(port.clone()), port);
+                                }
+                            });
+                        if ui.small_button("↻").on_hover_text("List the ports again").clicked() {
+                            self.midi_ports = None;
+                        }
+                        if choice != self.config.midi_port {
+                            self.config.midi_port = choice;
+                            changed = true;
+                        }
+                    });
+                    ui.separator();
+                    changed |= ui
+                        .checkbox(&mut self.config.raise_hotkey_enabled, "Global hotkey to bring this window to the front")
+                        .on_hover_text("Works while other applications have focus, so pick a combination nothing else uses")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Shortcut:");
+                        changed |= Self::committed_text_edit(ui, &mut self.config.raise_hotkey);
+                    });
+                });
+            self.show_settings = open;
+            if !open {
+                self.output_devices = None;
+                self.midi_ports = None;
+            }
+            if switch_output {
+                self.switch_output(self.config.output_device.clone());
+            }
+            if switch_prelisten && let Err(err) = self.audio_player.set_prelisten_device(self.config.prelisten_device.as_deref()) {
+                self.toast(format!("Couldn't open the pre-listen device: {}", err));
+            }
+            if changed {
+                let _ = self.config.save();
+            }
+        }
+        if let Some(((tab, idx), path)) = &self.edit_state.pending_replace {
+            let (mut replace, mut cancel) = (false, false);
+            let name = self.tabs.get(*tab).and_then(|tab| tab.buttons.get(*idx)).map_or("", |button| button.name.as_str());
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            let window = egui::Window::new("Replace music?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("\"{}\" will play {} instead.", name, file));
+                    ui.weak("Its name, waveform, tags and artwork are replaced; color, hotkey and playback settings stay.");
+                    ui.horizontal(|ui| {
+                        replace = ui.button("Replace").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            // Raised after the modal edit popup raises itself, so it stays on top
+            if let Some(window) = window {
+                ctx.move_to_top(window.response.layer_id);
+            }
+            if replace && let Some((key, path)) = self.edit_state.pending_replace.take() {
+                self.change_music(key, path);
+            } else if cancel {
+                self.edit_state.pending_replace = None;
+            }
+        }
+        // Closing with unsaved edits asks first; the close is cancelled until the dialog is answered
+        if ctx.input(|i| i.viewport().close_requested()) && !self.exit_confirmed && self.has_unsaved_changes() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.confirm_exit = true;
+        }
+        if self.confirm_exit {
+            let (mut save, mut discard, mut cancel) = (false, false, false);
+            egui::Window::new("Save before exiting?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label("The board has changes that haven't been saved.");
+                    ui.horizontal(|ui| {
+                        save = ui.button("Save").clicked();
+                        discard = ui.button("Discard").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            if save {
+                // Back to the board it came from, or ask where to put a new one
+                let target = self.save_back_path().or_else(|| {
+                    FileDialog::new().set_file_name("radio_conductor_save.bin").save_file()
+                });
+                match target.map(|path| self.save_board(&path)) {
+                    Some(Ok(())) => self.exit_confirmed = true,
+                    Some(Err(err)) => self.toast(format!("Save failed: {}", err)),
+                    None => {}
                 }
             }
-            if let Some(edit_idx) = self.edit_state.pending_change_music.take() {
-                if let Some(path) = FileDialog::new()
-                    .add_filter("Audio", &["mp3", "wav"])
-                    .pick_file()
-                {
-                    let name = path.file_name().unwrap().to_string_lossy().to_string();
-                    let (waveform, duration) = Self::generate_waveform_and_duration(&path);
-                    let tab = &mut self.tabs[self.current_tab];
-                    if let Some(button) = tab.buttons.get_mut(edit_idx) {
-                        button.name = name.clone();
-                        button.path = path;
-                        button.waveform = waveform;
-                        button.duration = duration;
-                    }
-                    self.edit_state.name_buf = name;
-                }
+            if discard {
+                self.exit_confirmed = true;
             }
-            for idx in add_requests {
-                self.add_music_at(idx);
+            if self.exit_confirmed {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
-        });
+            if cancel || self.exit_confirmed {
+                self.confirm_exit = false;
+            }
+        }
+        if self.offer_restore {
+            let (mut restore, mut discard) = (false, false);
+            egui::Window::new("Restore autosave?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label("An autosave is newer than the last board you saved or opened.");
+                    ui.horizontal(|ui| {
+                        restore = ui.button("Restore").clicked();
+                        discard = ui.button("Keep current board").clicked();
+                    });
+                });
+            if restore
+                && let Some(path) = Self::autosave_path()
+                && let Err(err) = self.load_from_file(&path)
+            {
+                self.report_load_error(&path, err);
+            }
+            if restore || discard {
+                self.offer_restore = false;
+            }
+        }
+        if let Some(message) = &self.load_error {
+            let mut dismissed = false;
+            egui::Window::new("Couldn't open board")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(message);
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.load_error = None;
+            }
+        }
+        self.toasts.retain(|toast| toast.created.elapsed() < TOAST_DURATION);
+        if !self.toasts.is_empty() {
+            egui::Area::new("toasts")
+                .anchor(Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+                .show(ctx, |ui| {
+                    for toast in &self.toasts {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(&toast.message);
+                        });
+                    }
+                });
+        }
         ctx.request_repaint();
     }
 }
@@ -578,6 +6504,1945 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Radio Conductor",
         options,
-        Box::new(|_cc| Box::new(MusicInterface::default())),
+        Box::new(|cc| {
+            let app = MusicInterface::new();
+            // Before the first frame, so a light theme doesn't flash dark at startup
+            cc.egui_ctx.set_visuals(app.config.visuals());
+            app.config.apply_ui_scale(&cc.egui_ctx);
+            Box::new(app)
+        }),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named_button(name: &str) -> MusicButton {
+        MusicButton { name: name.to_string(), ..Default::default() }
+    }
+
+    // A short 440 Hz sine written to the temp dir; the caller removes it
+    fn write_test_wav(name: &str, seconds: f32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("radio_conductor_{}_{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..(seconds * 8000.0) as usize {
+            let sample = (n as f32 * 440.0 * std::f32::consts::TAU / 8000.0).sin();
+            writer.write_sample((sample * i16::MAX as f32 * 0.5) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn rapid_alternating_plays_keep_the_current_cue_straight() {
+        let path = write_test_wav("fade_stress", 2.0);
+        let buttons = [MusicButton { path: path.clone(), ..named_button("a") }, MusicButton { path: path.clone(), ..named_button("b") }];
+        let mut player = AudioPlayer::new();
+        let mut faded: Vec<(Arc<Sink>, f32)> = Vec::new();
+        for round in 0..40 {
+            let key = (0, round % 2);
+            if let Some(sink) = player.sink.clone() {
+                player.fade_out();
+                faded.push((sink, f32::MAX));
+            }
+            player.play(key, &buttons[round % 2]).unwrap();
+            // What a UI frame does in between clicks, mixer included
+            player.prune_voices();
+            player.apply_end_fade();
+            player.apply_fade_ins();
+            assert!(!player.release_finished());
+            player.voices.iter().for_each(Voice::apply_volume);
+            let current = player.sink.clone().unwrap();
+            assert!(faded.iter().all(|(sink, _)| !Arc::ptr_eq(sink, &current)));
+            let voice = player.voices.iter().find(|voice| Arc::ptr_eq(&voice.sink, &current)).unwrap();
+            assert!(voice.key == key && !voice.fading);
+            assert_eq!(current.volume(), 1.0);
+            // Fading sinks only ever go down, whatever the frame did
+            for (sink, last) in &mut faded {
+                assert!(sink.volume() <= *last);
+                *last = sink.volume();
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        player.stop();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn play_during_fade_leaves_the_fading_sink_alone() {
+        let path = write_test_wav("fade_overlap", 2.0);
+        let first = MusicButton { path: path.clone(), ..named_button("first") };
+        let second = MusicButton { path: path.clone(), ..named_button("second") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &first).unwrap();
+        let fading = player.sink.clone().unwrap();
+        player.fade_out();
+        assert!(player.sink.is_none());
+        player.play((0, 1), &second).unwrap();
+        let playing = player.sink.clone().unwrap();
+        assert!(!Arc::ptr_eq(&fading, &playing));
+        assert_eq!(player.voices.len(), 2);
+        thread::sleep(Duration::from_millis(100));
+        // The old sink is still ramping down instead of being cut, and the new one is untouched
+        assert!(fading.volume() < 1.0 && fading.volume() > 0.0);
+        assert_eq!(playing.volume(), 1.0);
+        // A second fade can start while the first one is still running
+        player.fade_out();
+        assert!(player.sink.is_none());
+        thread::sleep(Duration::from_millis(100));
+        assert!(playing.volume() < 1.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn preloaded_pad_is_rearmed_after_triggering() {
+        let path = write_test_wav("preload", 1.0);
+        let tabs = vec![MusicTab { name: "Tab 1".to_string(), buttons: vec![MusicButton { path: path.clone(), ..named_button("pad") }], pages: 1, settings: TabSettings::default() }];
+        let mut player = AudioPlayer::new();
+        player.sync_preloaded(&tabs);
+        assert!(player.preloaded[&(0, 0)].ready.is_some());
+        player.play((0, 0), &tabs[0].buttons[0]).unwrap();
+        assert!(player.sink.is_some());
+        assert!(player.preloaded[&(0, 0)].ready.is_some());
+        player.sync_preloaded(&[]);
+        assert!(player.preloaded.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn sample_interface() -> MusicInterface {
+        let tabs = vec![
+            MusicTab {
+                name: "Jingles".to_string(),
+                buttons: vec![
+                    MusicButton {
+                        name: "Intro".to_string(),
+                        path: PathBuf::from("/music/intro.mp3"),
+                        position: Vec2::new(12.5, -3.0),
+                        color: Color32::from_rgb(200, 40, 90),
+                        waveform: vec![0.5, -0.25, 1.0],
+                        waveform_min: vec![-0.5, -0.75, 0.0],
+                        duration: 12.75,
+                        start_latency_ms: 8.5,
+                        hotkey: Some(egui::Key::F1),
+                        tone: None,
+                        playback: PlaybackSettings { cooldown_ms: 250, end_fade_ms: Some(1500), random_start: true, volume: 0.5, loop_enabled: true, speed: 1.25, fade_in_ms: 800, midi_note: Some(36), start_offset: 0.0, end_offset: 0.0, waveform_color: Some(Color32::from_rgb(255, 200, 0)) },
+                        info: TrackInfo {
+                            title: Some("Intro".to_string()),
+                            artist: Some("The Station".to_string()),
+                            tags: vec![("TIT2".to_string(), "Intro".to_string()), ("TPE1".to_string(), "The Station".to_string())],
+                            sample_rate: Some(44_100),
+                            loudness_db: Some(-14.5),
+                            artwork: vec![0x89, b'P', b'N', b'G'],
+                            stats: PlayStats { play_count: 7, last_played: Some(UNIX_EPOCH + Duration::from_secs(1_790_000_000)) },
+                        },
+                        last_triggered: None,
+                        play_error: None,
+                    },
+                    MusicButton::default(),
+                    named_button("Stinger"),
+                ],
+                pages: 1,
+                settings: TabSettings::default(),
+            },
+            MusicTab {
+                name: "Beds".to_string(),
+                buttons: vec![MusicButton {
+                    color: Color32::from_rgba_unmultiplied(10, 220, 30, 128),
+                    ..named_button("Ambient")
+                }],
+                pages: 2,
+                settings: TabSettings { playlist_mode: true, color: Some(Color32::from_rgb(60, 160, 80)), fit_to_content: false },
+            },
+        ];
+        let mut app = MusicInterface {
+            tabs,
+            current_tab: 1,
+            edit_mode: true,
+            tab_rename_buf: "Beds".to_string(),
+            ..Default::default()
+        };
+        app.edit_state.name_buf = "Ambient".to_string();
+        app.edit_state.color_buf = Color32::from_rgba_unmultiplied(1, 2, 3, 200);
+        app
+    }
+
+    fn assert_same_board(a: &MusicInterface, b: &MusicInterface) {
+        assert_eq!(a.tabs, b.tabs);
+        assert_eq!(a.current_tab, b.current_tab);
+        assert_eq!(a.edit_mode, b.edit_mode);
+        assert_eq!(a.current_playing, b.current_playing);
+        assert_eq!(a.edit_state, b.edit_state);
+        assert_eq!(a.renaming_tab, b.renaming_tab);
+        assert_eq!(a.tab_rename_buf, b.tab_rename_buf);
+    }
+
+    #[test]
+    fn vec2_and_color32_round_trip() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            #[serde(with = "vec2_serde")]
+            position: Vec2,
+            #[serde(with = "color32_serde")]
+            color: Color32,
+        }
+        for color in [Color32::WHITE, Color32::TRANSPARENT, Color32::from_rgba_unmultiplied(255, 128, 0, 77)] {
+            let wrapper = Wrapper { position: Vec2::new(-1.5, 1e6), color };
+            let decoded: Wrapper = bincode::deserialize(&bincode::serialize(&wrapper).unwrap()).unwrap();
+            assert_eq!(decoded.position, wrapper.position);
+            assert_eq!(decoded.color, wrapper.color);
+        }
+    }
+
+    #[test]
+    fn bincode_round_trip_preserves_the_board() {
+        let app = sample_interface();
+        let decoded: MusicInterface = bincode::deserialize(&bincode::serialize(&app).unwrap()).unwrap();
+        // Only the tabs are saved; the session state starts over
+        assert_same_board(&MusicInterface { tabs: app.tabs, ..Default::default() }, &decoded);
+    }
+
+    #[test]
+    fn save_and_load_file_round_trip() {
+        let app = sample_interface();
+        let path = std::env::temp_dir().join(format!("radio_conductor_roundtrip_{}.bin", std::process::id()));
+        app.save_to_file(&path).unwrap();
+        let mut loaded = MusicInterface::default();
+        let result = loaded.load_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        result.unwrap();
+        // The sample board is already valid, so validate_and_repair on load leaves it as saved
+        assert_eq!(loaded.tabs, app.tabs);
+        assert_eq!((loaded.current_tab, loaded.edit_mode), (0, false));
+    }
+
+    #[test]
+    fn json_export_round_trips_with_relative_paths() {
+        let dir = std::env::temp_dir().join(format!("radio_conductor_json_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("audio")).unwrap();
+        let path = dir.join("board.json");
+        let mut app = sample_interface();
+        app.tabs[0].buttons[0].path = dir.join("audio").join("intro.mp3");
+        app.save_to_json(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let button = &value["tabs"][0]["buttons"][0];
+        assert_eq!(button["path"], Path::new("audio").join("intro.mp3").to_string_lossy().as_ref());
+        // Playback settings are a plain object here, not an escaped blob
+        assert_eq!(button["playback"]["cooldown_ms"], 250);
+        let mut loaded = MusicInterface::default();
+        let result = loaded.load_from_file(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+        result.unwrap();
+        assert_eq!(loaded.tabs, app.tabs);
+        assert_eq!((loaded.current_tab, loaded.edit_mode), (0, false));
+    }
+
+    #[test]
+    fn loaded_boards_open_in_normal_mode_on_the_first_tab() {
+        let path = std::env::temp_dir().join(format!("radio_conductor_session_{}.json", std::process::id()));
+        // An export from before the session state was left out still carries it
+        let mut value = serde_json::to_value(sample_interface()).unwrap();
+        value["current_tab"] = 1.into();
+        value["edit_mode"] = true.into();
+        std::fs::write(&path, value.to_string()).unwrap();
+        let mut app = MusicInterface { current_tab: 1, edit_mode: true, ..sample_interface() };
+        let result = app.load_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        result.unwrap();
+        assert!(!app.edit_mode);
+        assert_eq!((app.current_tab, app.current_page), (0, 0));
+        assert_eq!(app.tabs.len(), 2);
+    }
+
+    #[test]
+    fn show_packages_carry_their_audio() {
+        let dir = std::env::temp_dir().join(format!("radio_conductor_package_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let audio = write_test_wav("package", 0.2);
+        let mut app = sample_interface();
+        app.tabs[0].buttons[0].path = audio.clone();
+        app.tabs[1].buttons = vec![MusicButton { path: audio.clone(), ..named_button("Again") }];
+        let package = dir.join("show.zip");
+        app.export_package(&package).unwrap();
+        let dest = dir.join("unpacked");
+        let mut loaded = MusicInterface::default();
+        loaded.import_package(&package, &dest).unwrap();
+        let unpacked = dest.join("audio").join(audio.file_name().unwrap());
+        assert_eq!(loaded.tabs[0].buttons[0].path, unpacked);
+        // A file used twice is packed once
+        assert_eq!(loaded.tabs[1].buttons[0].path, unpacked);
+        assert_eq!(std::fs::read(&unpacked).unwrap(), std::fs::read(&audio).unwrap());
+        assert_eq!(loaded.tabs[0].buttons[2], app.tabs[0].buttons[2]);
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_file(audio);
+    }
+
+    #[test]
+    fn autosave_writes_only_changed_boards() {
+        let dir = std::env::temp_dir().join(format!("radio_conductor_autosave_{}", std::process::id()));
+        let path = dir.join("autosave.bin");
+        let mut app = sample_interface();
+        app.saved_fingerprint = app.fingerprint();
+        assert!(!app.autosave(&path).unwrap());
+        app.tabs[0].buttons[0].name = "Renamed".to_string();
+        // Not due yet
+        assert!(!app.autosave(&path).unwrap());
+        app.last_autosave = Some(Instant::now() - Duration::from_secs(61));
+        assert!(app.autosave(&path).unwrap());
+        // Offered at startup only while it holds something else than the loaded board
+        let older = dir.join("older.bin");
+        assert!(!app.autosave_differs(&path, Some(&older)));
+        assert!(sample_interface().autosave_differs(&path, Some(&older)));
+        std::fs::write(&older, b"").unwrap();
+        assert!(!sample_interface().autosave_differs(&path, Some(&older)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_files_leave_the_open_board_alone() {
+        let path = std::env::temp_dir().join(format!("radio_conductor_corrupt_{}.bin", std::process::id()));
+        let mut app = sample_interface();
+        // Truncated save, then noise
+        let mut data = bincode::serialize(&app).unwrap();
+        data.truncate(data.len() / 2);
+        let noise = (0..4096u32).map(|n| (n.wrapping_mul(2_654_435_761) >> 13) as u8);
+        for contents in [data, noise.collect()] {
+            std::fs::write(&path, contents).unwrap();
+            assert!(app.load_from_file(&path).is_err());
+            assert_same_board(&app, &sample_interface());
+            assert!(app.previous_board.is_none());
+        }
+        // A good load keeps the replaced board as a backup
+        MusicInterface::default().save_to_file(&path).unwrap();
+        app.load_from_file(&path).unwrap();
+        assert_eq!(app.tabs.len(), 1);
+        app.last_autosave = Some(Instant::now() - Duration::from_secs(61));
+        let autosave = path.with_extension("autosave");
+        assert!(!app.autosave(&autosave).unwrap());
+        app.restore_previous_board().unwrap();
+        assert_eq!(app.tabs, sample_interface().tabs);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn the_startup_board_has_no_previous_board() {
+        let path = std::env::temp_dir().join(format!("radio_conductor_startup_{}.bin", std::process::id()));
+        sample_interface().save_to_file(&path).unwrap();
+        let mut app = MusicInterface::default();
+        app.config.startup_board = StartupBoard::Fixed(path.clone());
+        app.open_startup_board();
+        assert_eq!(app.tabs.len(), sample_interface().tabs.len());
+        assert!(app.previous_board.is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn loading_a_board_keeps_the_live_mix_state() {
+        let path = std::env::temp_dir().join(format!("radio_conductor_mix_state_{}.bin", std::process::id()));
+        sample_interface().save_to_file(&path).unwrap();
+        let mut app = MusicInterface::default();
+        app.config.master_volume = 0.4;
+        app.master_muted = true;
+        let result = app.load_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        result.unwrap();
+        assert_eq!(app.config.master_volume, 0.4);
+        assert!(app.master_muted);
+        // A fresh start always comes up unmuted
+        assert!(!MusicInterface::default().master_muted);
+    }
+
+    #[test]
+    fn boards_from_the_first_release_still_load() {
+        // Written by the code at 9cf7f8f, the first release
+        let data = include_bytes!("../tests/fixtures/board_9cf7f8f.bin");
+        let app = legacy::decode_board(data).unwrap();
+        let names: Vec<&str> = app.tabs.iter().map(|tab| tab.name.as_str()).collect();
+        assert_eq!(names, ["Jingles", "Ads"]);
+        let bed = &app.tabs[0].buttons[1];
+        assert_eq!((bed.name.as_str(), bed.path.as_path()), ("Weather bed", Path::new("/music/weather.mp3")));
+        assert_eq!((bed.position, bed.color, bed.duration), (Vec2::new(1.0, 0.0), Color32::from_rgb(60, 110, 200), 62.0));
+        assert_eq!(bed.waveform, [0.0, 0.25, 0.5, 1.0]);
+        assert_eq!(bed.playback, PlaybackSettings::default());
+        assert_eq!(app.tabs[1].buttons[0].name, "Spot");
+        assert!(app.tabs.iter().all(|tab| tab.pages == 1));
+        // The session state saved with it is dropped
+        assert_eq!((app.current_tab, app.edit_mode, app.current_playing), (0, false, None));
+        assert_eq!(app.version, BOARD_VERSION);
+        // Current saves must not be mistaken for the old layout
+        let current = sample_interface();
+        let decoded = legacy::decode_board(&bincode::serialize(&current).unwrap()).unwrap();
+        assert_eq!(decoded.tabs, current.tabs);
+    }
+
+    #[test]
+    fn random_start_backdates_the_progress_clock() {
+        let path = write_test_wav("random_start", 4.0);
+        let button = MusicButton {
+            path: path.clone(),
+            duration: 4.0,
+            playback: PlaybackSettings { random_start: true, ..Default::default() },
+            ..named_button("bed")
+        };
+        let mut player = AudioPlayer::new();
+        // Random offsets can land on 0, so try a few triggers
+        let started_late = (0..8).any(|_| {
+            player.play((0, 0), &button).unwrap();
+            player.elapsed() > 0.1
+        });
+        assert!(started_late);
+        assert!(player.elapsed() < 4.0);
+        let (_, actual) = SeekableSource::open(&path, Duration::from_secs(2), false).unwrap();
+        assert!((actual.as_secs_f32() - 2.0).abs() < 0.2, "seeked to {:?}", actual);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn snapped_starts_land_on_a_zero_crossing() {
+        let path = write_test_wav("snap", 3.0);
+        for millis in [500, 1234, 2001] {
+            let offset = Duration::from_millis(millis);
+            let (_, exact) = SeekableSource::open(&path, offset, false).unwrap();
+            let (mut source, snapped) = SeekableSource::open(&path, offset, true).unwrap();
+            // A 440 Hz sine at 8 kHz moves by ~0.17 of its amplitude per sample near zero
+            let first = source.next().unwrap();
+            assert!(first.abs() < 0.1, "starts at {first} from {millis} ms");
+            assert!(snapped >= exact && snapped - exact <= ZERO_CROSSING_WINDOW, "{exact:?} snapped to {snapped:?}");
+        }
+        // A trimmed loop's pass is as long with snapping as without
+        let (start, length) = (Duration::from_millis(1234), Duration::from_millis(500));
+        let exact = PreparedSink::loop_pass(&path, start, length, false).unwrap().count();
+        let mut snapped = PreparedSink::loop_pass(&path, start, length, true).unwrap();
+        assert!(snapped.next().unwrap().abs() < 0.1);
+        assert_eq!(snapped.count() + 1, exact);
+        assert!(exact.abs_diff(4000) <= 1, "{exact} samples for half a second at 8 kHz");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn cover_art_is_stored_as_a_small_thumbnail() {
+        let mut cover = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(600, 300, image::Rgb([200, 40, 90])))
+            .write_to(&mut cover, image::ImageOutputFormat::Jpeg(90))
+            .unwrap();
+        let thumbnail = MusicInterface::artwork_thumbnail(cover.get_ref()).unwrap();
+        assert!(thumbnail.len() < cover.get_ref().len());
+        let image = MusicInterface::artwork_image(&thumbnail).unwrap();
+        assert_eq!(image.size, [128, 64]);
+        assert!(MusicInterface::artwork_thumbnail(b"not an image").is_none());
+        // Files without pictures simply have no artwork
+        let path = write_test_wav("no_artwork", 0.1);
+        assert!(MusicInterface::read_artwork(&path).is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn imported_buttons_are_named_from_their_tags() {
+        let info = TrackInfo { title: Some(" Morning Show ".to_string()), artist: Some("Jingle Co".to_string()), ..Default::default() };
+        assert_eq!(info.display_name().as_deref(), Some("Jingle Co – Morning Show"));
+        let untitled = TrackInfo { artist: Some("Jingle Co".to_string()), ..Default::default() };
+        assert_eq!(MusicInterface::imported_name(Path::new("/music/bed.mp3"), &untitled), "bed.mp3");
+        // A file without tags (or that doesn't exist) reads as no info at all
+        let path = write_test_wav("untagged", 0.1);
+        assert_eq!(MusicInterface::read_track_info(&path), TrackInfo { sample_rate: Some(8000), ..Default::default() });
+        assert_eq!(MusicInterface::read_track_info(Path::new("/nonexistent/radio_conductor.mp3")), TrackInfo::default());
+        // Tags come back with the decode; a button renamed in the meantime keeps its name
+        let mut app = MusicInterface::default();
+        let bed = PathBuf::from("/music/bed.mp3");
+        app.tabs[0].buttons = vec![
+            MusicButton { path: bed.clone(), ..named_button("bed.mp3") },
+            MusicButton { path: bed.clone(), ..named_button("Morning bed") },
+        ];
+        for i in 0..2 {
+            app.pending_waveforms.insert((0, i), bed.clone());
+            let job = WaveformJob { key: (0, i), path: bed.clone(), result: Err("unsupported".to_string()), info: Some(info.clone()) };
+            app.waveform_jobs.sender.send(WaveformMessage::Done(Box::new(job))).unwrap();
+        }
+        app.drain_waveforms();
+        let names: Vec<_> = app.tabs[0].buttons.iter().map(|button| button.name.as_str()).collect();
+        assert_eq!(names, ["Jingle Co – Morning Show", "Morning bed"]);
+        assert_eq!(app.tabs[0].buttons[1].info, info);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn boards_from_a_newer_version_are_refused_clearly() {
+        let mut app = sample_interface();
+        app.version = BOARD_VERSION + 1;
+        let mut data = bincode::serialize(&app).unwrap();
+        let err = legacy::decode_board(&data).err().unwrap();
+        assert!(err.to_string().contains("newer"), "{}", err);
+        // Also when the newer layout doesn't parse at all
+        data.extend_from_slice(&[1, 2, 3]);
+        let err = legacy::decode_board(&data).err().unwrap();
+        assert!(err.to_string().contains("newer"), "{}", err);
+    }
+
+    #[test]
+    fn pages_grow_and_only_shrink_when_empty() {
+        let mut app = MusicInterface::default();
+        app.add_page();
+        assert_eq!(app.tabs[0].pages, 2);
+        assert_eq!(app.current_page, 1);
+        app.tabs[0].buttons.resize_with(GRID_SLOTS + 3, MusicButton::default);
+        app.tabs[0].buttons[GRID_SLOTS + 2] = named_button("late");
+        assert!(app.remove_last_page().is_err());
+        app.tabs[0].buttons[GRID_SLOTS + 2] = MusicButton::default();
+        app.remove_last_page().unwrap();
+        assert_eq!(app.tabs[0].pages, 1);
+        assert_eq!(app.tabs[0].buttons.len(), GRID_SLOTS);
+        assert_eq!(app.current_page, 0);
+        assert!(app.remove_last_page().is_err());
+    }
+
+    #[test]
+    fn tab_sidebar_narrows_the_grid() {
+        let grid_width = |tab_bar: TabBarPosition| {
+            let mut app = MusicInterface::default();
+            app.config.tab_bar = tab_bar;
+            let ctx = egui::Context::default();
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(Pos2::ZERO, Vec2::new(1000.0, 700.0))),
+                ..Default::default()
+            };
+            let mut width = 0.0;
+            let _ = ctx.run(input, |ctx| {
+                if app.config.tab_bar == TabBarPosition::Left {
+                    egui::SidePanel::left("tab_sidebar").show(ctx, |ui| app.tabs_ui(ui));
+                }
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    width = MusicInterface::grid_button_size(ui.available_size()).x;
+                });
+            });
+            width
+        };
+        let top = grid_width(TabBarPosition::Top);
+        let left = grid_width(TabBarPosition::Left);
+        assert!(left > 0.0);
+        assert!(left < top);
+    }
+
+    #[test]
+    fn fade_out_ramps_down_from_the_current_level() {
+        let path = write_test_wav("fade_level", 2.0);
+        let button = MusicButton {
+            path: path.clone(),
+            playback: PlaybackSettings { volume: 0.5, ..Default::default() },
+            ..named_button("quiet")
+        };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        let sink = player.sink.clone().unwrap();
+        player.fade_out();
+        let mut levels = Vec::new();
+        for _ in 0..20 {
+            levels.push(sink.volume());
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(levels.iter().all(|&level| level <= 0.5), "{:?}", levels);
+        assert!(levels.last() < Some(&0.5));
+        // Caught halfway through a crossfade: the ramp in stops where it is
+        player.play((0, 0), &button).unwrap();
+        player.switch_to((0, 1), &button, Transition::Crossfade, Duration::from_millis(400)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        player.apply_fade_ins();
+        let rising = player.sink.clone().unwrap();
+        let start = rising.volume();
+        player.fade_out();
+        for _ in 0..10 {
+            player.apply_fade_ins();
+            assert!(rising.volume() <= start, "{} > {}", rising.volume(), start);
+            thread::sleep(Duration::from_millis(10));
+        }
+        player.stop();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn restarting_a_playing_cue_cuts_it_back_to_the_top() {
+        let path = write_test_wav("restart", 2.0);
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![MusicButton { path: path.clone(), duration: 2.0, ..named_button("stinger") }];
+        app.audio_player.play((0, 0), &app.tabs[0].buttons[0]).unwrap();
+        app.current_playing = Some((0, 0));
+        let first = app.audio_player.sink.clone().unwrap();
+        run_for(&mut app.audio_player, 1);
+        assert_elapsed(&app.audio_player, 1.0);
+        app.restart_button(0, 0);
+        assert_eq!(app.current_playing, Some((0, 0)));
+        assert_elapsed(&app.audio_player, 0.0);
+        assert!(!Arc::ptr_eq(&first, app.audio_player.sink.as_ref().unwrap()));
+        // Cut rather than faded: nothing ramps the old sink down
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(first.volume(), 1.0);
+        assert!(app.tabs[0].buttons[0].last_triggered.is_some());
+        app.audio_player.stop();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn prelisten_without_a_device_leaves_the_air_alone() {
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![named_button("stinger")];
+        assert!(app.audio_player.set_prelisten_device(Some("No such headphones")).is_err());
+        assert!(app.audio_player.prelisten.is_none());
+        app.prelisten_button(0, 0);
+        assert_eq!(app.toasts.len(), 1);
+        assert_eq!((app.current_playing, app.audio_player.prelistening()), (None, None));
+        assert!(app.audio_player.voices.is_empty());
+        assert!(app.audio_player.set_prelisten_device(None).is_ok());
+    }
+
+    #[test]
+    fn button_volume_is_applied_and_defaults_to_unity() {
+        let path = write_test_wav("button_volume", 1.0);
+        let button = MusicButton {
+            path: path.clone(),
+            playback: PlaybackSettings { volume: 1.5, ..Default::default() },
+            ..named_button("loud")
+        };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        assert_eq!(player.sink.as_ref().unwrap().volume(), 1.5);
+        player.voices[0].volume = 0.5;
+        player.voices[0].apply_volume();
+        assert_eq!(player.sink.as_ref().unwrap().volume(), 0.75);
+        // Boards saved before the field existed come back at full level
+        let old: PlaybackSettings = serde_json::from_str(r#"{"cooldown_ms":0,"end_fade_ms":null,"random_start":false}"#).unwrap();
+        assert_eq!(old.volume, 1.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn master_gain_scales_the_output_live() {
+        let gain = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let mut tap = OutputTap {
+            inner: rodio::buffer::SamplesBuffer::new(1, 8000, vec![0.5f32; 16]),
+            record: Arc::new(Mutex::new(None)),
+            gain: gain.clone(),
+            buffer: Vec::new(),
+            epoch: Instant::now(),
+            pulled_ms: Arc::new(AtomicU64::new(0)),
+            peak: Arc::new(AtomicU32::new(0)),
+        };
+        assert_eq!(tap.next(), Some(0.5));
+        gain.store(0.5f32.to_bits(), Ordering::Relaxed);
+        assert_eq!(tap.next(), Some(0.25));
+    }
+
+    #[test]
+    fn mono_sum_averages_each_frame_when_enabled() {
+        let enabled = Arc::new(AtomicBool::new(false));
+        let stereo = rodio::buffer::SamplesBuffer::new(2, 8000, vec![1.0f32, 0.0, 0.5, -0.5, 0.2, 0.4]);
+        let mut mono = MonoSum::new(stereo, enabled.clone());
+        assert_eq!((mono.next(), mono.next()), (Some(1.0), Some(0.0)));
+        enabled.store(true, Ordering::Relaxed);
+        assert_eq!((mono.next(), mono.next()), (Some(0.0), Some(0.0)));
+        let last: Vec<f32> = mono.collect();
+        assert_eq!(last.len(), 2);
+        assert!((last[0] - 0.3).abs() < 1e-6 && last[0] == last[1]);
+    }
+
+    #[test]
+    fn the_meter_holds_peaks_and_falls_back() {
+        let peak = Arc::new(AtomicU32::new(0));
+        let mut samples = vec![0.1f32; TAP_CHUNK];
+        samples[METER_STRIDE * 3] = -0.8;
+        let tap = OutputTap {
+            inner: rodio::buffer::SamplesBuffer::new(1, 8000, samples),
+            record: Arc::new(Mutex::new(None)),
+            gain: Arc::new(AtomicU32::new(0.5f32.to_bits())),
+            buffer: Vec::new(),
+            epoch: Instant::now(),
+            pulled_ms: Arc::new(AtomicU64::new(0)),
+            peak: peak.clone(),
+        };
+        tap.for_each(drop);
+        // Measured after the master gain
+        assert_eq!(f32::from_bits(peak.load(Ordering::Relaxed)), 0.4);
+        let mut app = MusicInterface::default();
+        app.audio_player.peak = peak;
+        app.update_meter(0.016);
+        assert_eq!(app.meter, 0.4);
+        // Read once: the next frame only sees the decay
+        app.update_meter(0.5);
+        assert!(app.meter > 0.05 && app.meter < 0.4, "meter at {}", app.meter);
+    }
+
+    #[test]
+    fn the_output_tap_shows_when_the_device_last_pulled() {
+        let pulled_ms = Arc::new(AtomicU64::new(0));
+        let mut tap = OutputTap {
+            inner: Zero::<f32>::new(1, 8000),
+            record: Arc::new(Mutex::new(None)),
+            gain: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            buffer: Vec::new(),
+            epoch: Instant::now() - Duration::from_secs(3),
+            pulled_ms: pulled_ms.clone(),
+            peak: Arc::new(AtomicU32::new(0)),
+        };
+        tap.by_ref().take(TAP_CHUNK - 1).for_each(drop);
+        assert_eq!(pulled_ms.load(Ordering::Relaxed), 0);
+        tap.next();
+        assert!(pulled_ms.load(Ordering::Relaxed) >= 3000);
+        // A player without a device has nothing to watch
+        let mut player = AudioPlayer::new();
+        player.stream = None;
+        assert!(!player.output_stalled());
+    }
+
+    #[test]
+    fn an_air_check_survives_reopening_the_output() {
+        let path = std::env::temp_dir().join(format!("radio_conductor_aircheck_{}.wav", std::process::id()));
+        let mut app = MusicInterface::default();
+        app.audio_player.start_recording(path.clone()).unwrap();
+        app.switch_output(None);
+        assert_eq!(app.audio_player.recording.as_ref().map(|recording| &recording.path), Some(&path));
+        assert!(app.audio_player.record_slot.lock().unwrap().is_some());
+        app.audio_player.stop_recording();
+        assert_eq!(hound::WavReader::open(&path).unwrap().spec().channels, MIX_CHANNELS);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn crossfade_ramps_the_new_cue_up_while_the_old_fades() {
+        let path = write_test_wav("crossfade", 2.0);
+        let first = MusicButton { path: path.clone(), ..named_button("first") };
+        let second = MusicButton { path: path.clone(), ..named_button("second") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &first).unwrap();
+        let old = player.sink.clone().unwrap();
+        player.switch_to((0, 1), &second, Transition::Crossfade, Duration::from_millis(400)).unwrap();
+        let new = player.sink.clone().unwrap();
+        assert!(new.volume() < 0.1);
+        thread::sleep(Duration::from_millis(200));
+        player.apply_fade_ins();
+        assert!(new.volume() > 0.2 && new.volume() < 0.9, "new at {}", new.volume());
+        assert!(old.volume() > 0.1 && old.volume() < 0.8, "old at {}", old.volume());
+        thread::sleep(Duration::from_millis(250));
+        player.apply_fade_ins();
+        assert_eq!(new.volume(), 1.0);
+        assert!(player.voices.iter().all(|voice| voice.fade_in.is_none()));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn fade_in_eases_up_to_the_button_level() {
+        let path = write_test_wav("fade_in", 2.0);
+        let mut button = MusicButton { path: path.clone(), ..named_button("bed") };
+        button.playback.volume = 0.5;
+        button.playback.fade_in_ms = 300;
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        let sink = player.sink.clone().unwrap();
+        assert!(sink.volume() < 0.05);
+        thread::sleep(Duration::from_millis(350));
+        player.apply_fade_ins();
+        assert_eq!(sink.volume(), 0.5);
+        // Boards from before the setting start at full level straight away
+        let old: PlaybackSettings = serde_json::from_str(r#"{"cooldown_ms":0,"end_fade_ms":null,"random_start":false,"volume":1.0,"speed":1.0}"#).unwrap();
+        assert_eq!(old.fade_in_ms, 0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn seek_swaps_the_sink_and_moves_the_clock() {
+        let path = write_test_wav("seek", 4.0);
+        let button = MusicButton { path: path.clone(), duration: 4.0, ..named_button("long") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        player.voices[0].volume = 0.5;
+        player.voices[0].apply_volume();
+        let before = player.sink.clone().unwrap();
+        player.seek(&button, Duration::from_secs(2)).unwrap();
+        let after = player.sink.clone().unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert!((player.elapsed() - 2.0).abs() < 0.2, "elapsed {}", player.elapsed());
+        assert_eq!(after.volume(), 0.5);
+        player.prune_voices();
+        assert_eq!(player.voices.len(), 1);
+        let tone = MusicButton { tone: Some(TestTone::default()), ..named_button("tone") };
+        assert!(player.seek(&tone, Duration::from_secs(1)).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn flac_files_get_a_waveform_and_duration() {
+        // 0.25 s of 440 Hz, 8 kHz mono, stored verbatim
+        let path = std::env::temp_dir().join(format!("radio_conductor_fixture_{}.flac", std::process::id()));
+        std::fs::write(&path, include_bytes!("../tests/fixtures/sine_440hz.flac")).unwrap();
+        let (waveform, _, duration, _) = MusicInterface::generate_waveform_and_duration(&path, WaveformMode::Peak, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+        assert!(!waveform.is_empty());
+        assert!(waveform.iter().any(|peak| *peak > 0.1));
+        assert!((duration - 0.25).abs() < 0.01, "duration {}", duration);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn progress_reaches_the_end_with_the_playback_decoder() {
+        let path = write_test_wav("progress_end", 1.5);
+        let (.., duration, _) = MusicInterface::generate_waveform_and_duration(&path, WaveformMode::Peak, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+        // Everything the player will output, counted on the decoder it plays through
+        let decoder = Decoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let (channels, rate) = (decoder.channels() as usize, decoder.sample_rate());
+        let played = decoder.count() as f32 / channels as f32 / rate as f32;
+        let _ = std::fs::remove_file(&path);
+        assert!((duration - 1.5).abs() < 1e-3, "duration {}", duration);
+        let button = MusicButton { duration, ..named_button("timed") };
+        let progress = played / button.play_length();
+        assert!((progress - 1.0).abs() < 1e-4, "progress {}", progress);
+    }
+
+    #[test]
+    fn rms_follows_loudness_where_peak_follows_the_crest() {
+        // Full-scale sine, one period per point: peak 1, RMS 1/sqrt(2)
+        let sine: Vec<f32> = (0..WAVEFORM_POINTS * 64).map(|n| (n as f32 * std::f32::consts::TAU / 64.0).sin()).collect();
+        let (peak, _) = MusicInterface::waveform_from_samples(&sine, WaveformMode::Peak, WAVEFORM_POINTS);
+        let (rms, rms_min) = MusicInterface::waveform_from_samples(&sine, WaveformMode::Rms, WAVEFORM_POINTS);
+        assert!(rms_min.is_empty());
+        assert!(peak.iter().all(|p| (p - 1.0).abs() < 0.01));
+        assert!(rms.iter().all(|r| (r - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01));
+        // A lone click reads as full scale in peak mode but barely registers as RMS
+        let mut click = vec![0.0; WAVEFORM_POINTS * 64];
+        click[640] = 1.0;
+        assert_eq!(MusicInterface::waveform_from_samples(&click, WaveformMode::Peak, WAVEFORM_POINTS).0[10], 1.0);
+        assert!(MusicInterface::waveform_from_samples(&click, WaveformMode::Rms, WAVEFORM_POINTS).0[10] < 0.2);
+        // Stereo frames are averaged, not interleaved
+        assert_eq!(MusicInterface::downmix(&[1.0, -1.0, 0.5, 0.5], 2), vec![0.0, 0.5]);
+    }
+
+    #[test]
+    fn waveforms_have_a_fixed_resolution() {
+        for frames in [WAVEFORM_POINTS * 3 + 7, 48_000 * 60] {
+            let samples = vec![0.5; frames];
+            for mode in [WaveformMode::MinMax, WaveformMode::Peak, WaveformMode::Rms] {
+                assert_eq!(MusicInterface::waveform_from_samples(&samples, mode, WAVEFORM_POINTS).0.len(), WAVEFORM_POINTS);
+            }
+        }
+        // A file shorter than the resolution gets one point per frame
+        assert_eq!(MusicInterface::waveform_from_samples(&[0.5; 10], WaveformMode::Peak, WAVEFORM_POINTS).0.len(), 10);
+        // The detail setting picks the point count, within bounds
+        assert_eq!(MusicInterface::waveform_from_samples(&[0.5; 48_000], WaveformMode::MinMax, 500).1.len(), 500);
+        let mut config = AppConfig { waveform_points: 10_000_000, ..Default::default() };
+        assert_eq!(config.waveform_points(), *WAVEFORM_POINTS_RANGE.end());
+        config.waveform_points = 0;
+        assert_eq!(config.waveform_points(), *WAVEFORM_POINTS_RANGE.start());
+    }
+
+    #[test]
+    fn decoding_reports_progress_in_capped_steps() {
+        let path = write_test_wav("decode_progress", 3.0);
+        let mut reports = Vec::new();
+        MusicInterface::generate_waveform_and_duration(&path, WaveformMode::Peak, WAVEFORM_POINTS, &mut |fraction| reports.push(fraction)).unwrap();
+        assert!((90..=100).contains(&reports.len()), "{} reports", reports.len());
+        assert!(reports.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!(*reports.last().unwrap() > 0.98 && *reports.last().unwrap() <= 1.0);
+        // The slot shows the latest report until the result comes in
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![MusicButton { path: path.clone(), ..named_button("long") }];
+        app.pending_waveforms.insert((0, 0), path.clone());
+        let sender = app.waveform_jobs.sender.clone();
+        sender.send(WaveformMessage::Progress(path.clone(), 0.25)).unwrap();
+        sender.send(WaveformMessage::Progress(path.clone(), 0.5)).unwrap();
+        app.drain_waveforms();
+        assert_eq!(app.waveform_progress.get(&path), Some(&0.5));
+        let result = Ok((vec![0.5], Vec::new(), 3.0, -20.0));
+        sender.send(WaveformMessage::Done(Box::new(WaveformJob { key: (0, 0), path: path.clone(), result, info: None }))).unwrap();
+        app.waveform_scales.insert(path.clone(), 1.0);
+        app.drain_waveforms();
+        assert!(app.waveform_progress.is_empty() && app.pending_waveforms.is_empty());
+        // The scale worked out from the old waveform goes with it
+        assert!(app.waveform_scales.is_empty());
+        assert_eq!(app.tabs[0].buttons[0].duration, 3.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn silent_and_tiny_files_decode_and_draw() {
+        let spec = hound::WavSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let ctx = egui::Context::default();
+        let painter = egui::Painter::new(ctx, egui::LayerId::background(), egui::Rect::EVERYTHING);
+        let rect = egui::Rect::from_min_size(Pos2::ZERO, Vec2::new(200.0, 100.0));
+        for (name, frames) in [("empty", 0), ("tiny", 10), ("silent", 8000)] {
+            let path = std::env::temp_dir().join(format!("radio_conductor_{}_{}.wav", name, std::process::id()));
+            let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+            for _ in 0..frames * 2 {
+                writer.write_sample(0i16).unwrap();
+            }
+            writer.finalize().unwrap();
+            for mode in [WaveformMode::MinMax, WaveformMode::Peak, WaveformMode::Rms] {
+                let (waveform, waveform_min, _, _) = MusicInterface::generate_waveform_and_duration(&path, mode, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+                assert_eq!(waveform.len(), frames.min(WAVEFORM_POINTS), "{}", name);
+                let button = MusicButton { waveform, waveform_min, ..named_button(name) };
+                MusicInterface::paint_waveform(&painter, rect, &button, &AppConfig::default(), (0.0, 1.0), button.waveform_scale());
+            }
+            let _ = std::fs::remove_file(path);
+        }
+        // NaN samples don't panic the peak search or leak into the shape
+        let (peak, _) = MusicInterface::waveform_from_samples(&[f32::NAN, 0.5], WaveformMode::Peak, WAVEFORM_POINTS);
+        let (rms, _) = MusicInterface::waveform_from_samples(&[f32::NAN, 0.5], WaveformMode::Rms, WAVEFORM_POINTS);
+        assert_eq!((peak, rms), (vec![0.0, 0.5], vec![0.0, 0.5]));
+    }
+
+    #[test]
+    fn waveform_scale_stretches_the_loudest_point() {
+        let mut button = MusicButton { waveform: vec![0.1, 0.25], waveform_min: vec![-0.5, 0.0], ..named_button("quiet") };
+        assert_eq!(button.waveform_scale(), 2.0);
+        button.set_waveform(vec![0.2], Vec::new());
+        assert_eq!(button.waveform_scale(), 5.0);
+        button.set_waveform(vec![0.0; 4], Vec::new());
+        assert_eq!(button.waveform_scale(), 1.0);
+    }
+
+    #[test]
+    fn playing_a_missing_file_fails_without_touching_the_player() {
+        let path = write_test_wav("missing", 1.0);
+        let good = MusicButton { path: path.clone(), duration: 1.0, ..named_button("good") };
+        let gone = MusicButton { path: PathBuf::from("/nonexistent/radio_conductor.wav"), ..named_button("gone") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &good).unwrap();
+        let current = player.sink.clone().unwrap();
+        assert!(player.play((0, 1), &gone).is_err());
+        assert!(Arc::ptr_eq(&current, player.sink.as_ref().unwrap()));
+        assert!(!current.is_paused() && player.voices.len() == 1);
+        // Nor does any transition: the cue on air is only let go once the new file opened
+        for transition in [Transition::FadeOut, Transition::HardCut, Transition::Crossfade] {
+            assert!(player.switch_to((0, 1), &gone, transition, Duration::from_millis(400)).is_err());
+            assert!(Arc::ptr_eq(&current, player.sink.as_ref().unwrap()));
+            assert!(!current.empty() && current.volume() == 1.0);
+            assert!(player.voices.iter().all(|voice| !voice.fading));
+        }
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn waveforms_are_decoded_in_the_background() {
+        let path = write_test_wav("background", 1.0);
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![MusicButton { path: path.clone(), ..named_button("bed") }];
+        app.request_waveform((0, 0), path.clone());
+        // Results for a slot that no longer holds the file are dropped quietly
+        app.request_waveform((0, 1), PathBuf::from("/nonexistent/radio_conductor.wav"));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !app.pending_waveforms.is_empty() && Instant::now() < deadline {
+            app.drain_waveforms();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(app.pending_waveforms.is_empty());
+        assert!(!app.tabs[0].buttons[0].waveform.is_empty());
+        assert!((app.tabs[0].buttons[0].duration - 1.0).abs() < 0.01);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn normalize_levels_brings_buttons_to_the_target() {
+        let path = write_test_wav("loudness", 1.0);
+        // A steady sine at half scale has a mean power of 1/8
+        let (.., loudness) = MusicInterface::generate_waveform_and_duration(&path, WaveformMode::Peak, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+        assert!((loudness + 9.03).abs() < 0.1, "loudness was {}", loudness);
+        assert_eq!(MusicInterface::measure_loudness(&[0.0; 8000], 8000), LOUDNESS_FLOOR_DB);
+        let mut app = MusicInterface::default();
+        app.config.loudness_target_db = -16.0;
+        let mut measured = named_button("measured");
+        measured.info.loudness_db = Some(-10.0);
+        let tone = MusicButton { tone: Some(TestTone::default()), ..named_button("tone") };
+        app.tabs[0].buttons = vec![measured, MusicButton { path: path.clone(), ..named_button("new") }, tone];
+        assert_eq!(app.normalize_levels(Some(0)), (1, 1));
+        assert_eq!(app.pending_normalize, HashSet::from([(0, 1)]));
+        assert!((app.tabs[0].buttons[0].playback.volume - 0.501).abs() < 0.001);
+        assert_eq!(app.tabs[0].buttons[2].playback.volume, 1.0);
+        // The unmeasured file is levelled once its decode comes back
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !app.pending_waveforms.is_empty() && Instant::now() < deadline {
+            app.drain_waveforms();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let button = &app.tabs[0].buttons[1];
+        assert!(app.pending_normalize.is_empty());
+        assert!((button.playback.volume - 0.448).abs() < 0.01, "volume was {}", button.playback.volume);
+        app.undo();
+        assert_eq!(app.tabs[0].buttons[0].playback.volume, 1.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn waveform_cache_hit_repopulates_a_button() {
+        let path = write_test_wav("cache", 0.5);
+        let cache = WaveformCache { dir: std::env::temp_dir().join(format!("radio_conductor_cache_{}", std::process::id())) };
+        let mut button = MusicButton { path: path.clone(), ..named_button("cached") };
+        let decoded = MusicInterface::cached_waveform(Some(&cache), &path, WaveformMode::MinMax, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+        assert!(cache.get(&path, WaveformMode::MinMax, WAVEFORM_POINTS).is_some());
+        assert!(cache.get(&path, WaveformMode::Peak, WAVEFORM_POINTS).is_none());
+        assert!(cache.get(&path, WaveformMode::MinMax, 500).is_none());
+        button.waveform.clear();
+        (button.waveform, button.waveform_min, button.duration, _) = MusicInterface::cached_waveform(Some(&cache), &path, WaveformMode::MinMax, WAVEFORM_POINTS, &mut |_| {}).unwrap();
+        assert_eq!(button.waveform, decoded.0);
+        assert_eq!(button.duration, decoded.2);
+        cache.clear().unwrap();
+        assert!(cache.get(&path, WaveformMode::MinMax, WAVEFORM_POINTS).is_none());
+        cache.clear().unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn waveform_cache_keys_are_stable_and_the_cache_is_capped() {
+        // Published FNV-1a test vectors: entries must keep their names across Rust releases
+        assert_eq!(WaveformCache::fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(WaveformCache::fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        let cache = WaveformCache { dir: std::env::temp_dir().join(format!("radio_conductor_cache_cap_{}", std::process::id())) };
+        std::fs::create_dir_all(&cache.dir).unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["old", "middle", "new"].iter().enumerate() {
+            let file = File::create(cache.dir.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - Duration::from_secs(60 * (3 - i as u64))).unwrap();
+        }
+        cache.evict(250).unwrap();
+        let mut left: Vec<String> = std::fs::read_dir(&cache.dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        left.sort();
+        assert_eq!(left, ["middle", "new"]);
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn swapping_buttons_keeps_slot_references_on_the_same_button() {
+        let mut app = sample_interface();
+        app.current_playing = Some((0, 0));
+        app.queued_triggers = vec![(0, 2), (1, 0)];
+        app.current_tab = 0;
+        app.edit_state.editing = Some(2);
+        app.swap_buttons(0, 0, 2);
+        assert_eq!(app.tabs[0].buttons[2].name, "Intro");
+        assert_eq!(app.tabs[0].buttons[0].name, "Stinger");
+        assert_eq!(app.current_playing, Some((0, 2)));
+        assert_eq!(app.queued_triggers, vec![(0, 0), (1, 0)]);
+        assert_eq!(app.edit_state.editing, Some(0));
+        // Dropping past the end of the tab grows it with blank buttons
+        app.swap_buttons(0, 2, 7);
+        assert_eq!(app.tabs[0].buttons.len(), 8);
+        assert_eq!(app.tabs[0].buttons[7].name, "Intro");
+        assert!(app.tabs[0].buttons[2].name.is_empty());
+        assert_eq!(app.current_playing, Some((0, 7)));
+    }
+
+    #[test]
+    fn hotkeys_fire_on_plain_presses_only() {
+        let press = |modifiers, repeat| egui::Event::Key { key: egui::Key::R, pressed: true, repeat, modifiers };
+        assert_eq!(MusicInterface::pad_key(&press(egui::Modifiers::NONE, false)), Some(egui::Key::R));
+        assert_eq!(MusicInterface::pad_key(&press(egui::Modifiers::SHIFT, false)), Some(egui::Key::R));
+        assert_eq!(MusicInterface::pad_key(&press(egui::Modifiers::NONE, true)), None);
+        assert_eq!(MusicInterface::pad_key(&press(egui::Modifiers::CTRL | egui::Modifiers::ALT, false)), None);
+        let mut app = sample_interface();
+        app.tabs[0].buttons[0].hotkey = Some(egui::Key::R);
+        app.tabs[1].buttons = vec![MusicButton { hotkey: Some(egui::Key::R), ..named_button("Bed") }];
+        app.config.hotkey_conflict = HotkeyConflict::PreferCurrentTab;
+        assert_eq!(app.hotkey_targets(egui::Key::R), vec![(1, 0)]);
+        app.config.hotkey_conflict = HotkeyConflict::TriggerAll;
+        // One cue at a time: firing both would just leave the last one playing
+        assert_eq!(app.hotkey_targets(egui::Key::R), vec![(1, 0)]);
+        app.config.polyphonic = true;
+        assert_eq!(app.hotkey_targets(egui::Key::R), vec![(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn cooldowns_hold_back_starts_but_not_stops() {
+        let mut app = sample_interface();
+        app.tabs[0].buttons[0].playback.cooldown_ms = 60_000;
+        assert!(!app.cooling_down(0, 0));
+        app.tabs[0].buttons[0].last_triggered = Some(Instant::now());
+        assert!(app.cooling_down(0, 0));
+        app.current_playing = Some((0, 0));
+        assert!(!app.cooling_down(0, 0));
+        app.tabs[0].buttons[0].playback.cooldown_ms = 0;
+        app.current_playing = None;
+        assert!(!app.cooling_down(0, 0));
+    }
+
+    #[test]
+    fn search_finds_buttons_on_every_tab_ignoring_case() {
+        let mut app = sample_interface();
+        assert_eq!(app.find_buttons("t"), vec![(0, 0), (0, 2), (1, 0)]);
+        assert_eq!(app.find_buttons("in"), vec![(0, 0), (0, 2)]);
+        assert_eq!(app.find_buttons(" AMB "), vec![(1, 0)]);
+        assert!(app.find_buttons("").is_empty() && app.find_buttons("nothing").is_empty());
+        app.reveal_button((0, 2));
+        assert_eq!((app.current_tab, app.current_page), (0, 0));
+        assert_eq!(app.flash.map(|(key, _)| key), Some((0, 2)));
+    }
+
+    #[test]
+    fn playlist_tabs_advance_to_the_next_populated_slot() {
+        let mut app = sample_interface();
+        assert_eq!(app.playlist_next(0, 0), None);
+        app.tabs[0].settings.playlist_mode = true;
+        // Slot 1 is empty
+        assert_eq!(app.playlist_next(0, 0), Some(2));
+        assert_eq!(app.playlist_next(0, 2), None);
+    }
+
+    #[test]
+    fn pasting_copies_a_button_across_tabs() {
+        let mut app = sample_interface();
+        let original = app.tabs[0].buttons[0].clone();
+        app.clipboard = Some(original.clone());
+        app.current_tab = 1;
+        // Past the end of the tab, with a hotkey and MIDI note the default policy won't share
+        app.paste_at(3);
+        assert_eq!(app.tabs[1].buttons.len(), 4);
+        // Copies start without play stats
+        let playback = PlaybackSettings { midi_note: None, ..original.playback };
+        let info = TrackInfo { stats: PlayStats::default(), ..original.info.clone() };
+        assert_eq!(app.tabs[1].buttons[3], MusicButton { hotkey: None, playback, info: info.clone(), ..original.clone() });
+        assert!(app.pending_waveforms.is_empty());
+        app.config.hotkey_conflict = HotkeyConflict::TriggerAll;
+        app.paste_at(1);
+        assert_eq!(app.tabs[1].buttons[1], MusicButton { info, ..original });
+    }
+
+    #[test]
+    fn undo_and_redo_walk_through_edits() {
+        let mut app = sample_interface();
+        app.current_tab = 0;
+        let original = sample_interface().tabs;
+        app.delete_button(0, 0);
+        app.swap_buttons(0, 1, 2);
+        app.edit_state.editing = Some(1);
+        app.edit_state.name_buf = "Renamed".to_string();
+        app.edit_state.playback_buf = PlaybackSettings::default();
+        app.commit_edit(1);
+        assert_eq!(app.tabs[0].buttons[1].name, "Renamed");
+        // Saving again without changes adds no step
+        app.edit_state.editing = Some(1);
+        app.commit_edit(1);
+        assert_eq!(app.history.undo.iter().map(|step| step.action).collect::<Vec<_>>(), ["delete", "move button", "edit"]);
+        app.undo();
+        app.undo();
+        assert_eq!(app.tabs[0].buttons[2].name, "Stinger");
+        app.undo();
+        // The deleted button's waveform and artwork come back from the shared store
+        assert_eq!(app.tabs, original);
+        assert!(app.pending_waveforms.is_empty());
+        app.redo();
+        assert!(app.tabs[0].buttons[0].name.is_empty());
+        // A new edit drops what could still be redone
+        app.remove_tab(1).unwrap();
+        assert!(app.history.redo.is_empty());
+        app.undo();
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.history.media.len(), 1, "only Intro has decoded media");
+        // Whatever refers to a playing pad by slot follows it back to where it was
+        let mut app = sample_interface();
+        app.swap_buttons(0, 0, 2);
+        let (sink, _) = Sink::new_idle();
+        app.audio_player.layers.insert((0, 2), Layer { sink: Arc::new(sink), started: Instant::now() });
+        app.current_playing = Some((0, 2));
+        app.queued_triggers = vec![(0, 2), (1, 0)];
+        app.undo();
+        assert_eq!(app.current_playing, Some((0, 0)));
+        assert_eq!(app.queued_triggers, vec![(0, 0), (1, 0)]);
+        assert_eq!(app.audio_player.layers.keys().collect::<Vec<_>>(), [&(0, 0)]);
+        // and lets go of a pad whose file the undo takes off the board
+        app.record_undo("add music");
+        app.tabs[0].buttons[1] = MusicButton { path: PathBuf::from("/music/new.mp3"), ..named_button("New") };
+        app.current_playing = Some((0, 1));
+        app.undo();
+        assert_eq!(app.current_playing, None);
+    }
+
+    #[test]
+    fn remaining_time_turns_red_near_the_end() {
+        assert_eq!(MusicInterface::remaining_color(30.0, 10), Color32::YELLOW);
+        assert_eq!(MusicInterface::remaining_color(10.0, 10), Color32::YELLOW);
+        assert_eq!(MusicInterface::remaining_color(9.99, 10), Color32::from_rgb(255, 165, 0));
+        assert_eq!(MusicInterface::remaining_color(0.0, 10), Color32::from_rgb(255, 0, 0));
+        // Off
+        assert_eq!(MusicInterface::remaining_color(0.0, 0), Color32::YELLOW);
+    }
+
+    #[test]
+    fn only_slots_in_use_need_a_replace_confirmation() {
+        let mut app = sample_interface();
+        assert!(app.slot_in_use((0, 0)));
+        // Emptied, past the end of the tab, and on a missing tab
+        assert!(!app.slot_in_use((0, 1)) && !app.slot_in_use((0, 9)) && !app.slot_in_use((5, 0)));
+        app.tabs[0].buttons[1].path = PathBuf::from("/music/unnamed.mp3");
+        assert!(app.slot_in_use((0, 1)));
+    }
+
+    #[test]
+    fn missing_files_are_flagged_and_relinked() {
+        let audio = write_test_wav("relink", 0.2);
+        let mut app = sample_interface();
+        app.tabs[0].buttons[2].path = audio.clone();
+        app.tabs[1].buttons[0].path = PathBuf::from("/music/moved/ambient.wav");
+        app.tabs[1].buttons.push(MusicButton { path: PathBuf::from("/music/tone.wav"), tone: Some(TestTone::default()), ..named_button("Tone") });
+        // Intro and Ambient point nowhere; Stinger exists, and tones and emptied slots are never missing
+        assert_eq!(app.mark_missing_files(), 2);
+        assert_eq!(app.missing_files(), vec![(0, 0), (1, 0)]);
+        let (name, playback) = (app.tabs[0].buttons[0].name.clone(), app.tabs[0].buttons[0].playback);
+        app.relink_button((0, 0), audio.clone());
+        let relinked = &app.tabs[0].buttons[0];
+        assert!(!relinked.is_missing(&app.missing_paths) && relinked.path == audio);
+        assert_eq!((relinked.name.clone(), relinked.playback), (name, playback));
+        assert_eq!(app.pending_waveforms.get(&(0, 0)), Some(&audio));
+        assert_eq!(app.missing_files(), vec![(1, 0)]);
+        // The flag goes with the button when it moves
+        app.swap_buttons(1, 0, 1);
+        assert_eq!(app.missing_files(), vec![(1, 1)]);
+        let _ = std::fs::remove_file(audio);
+    }
+
+    #[test]
+    fn folder_import_fills_free_slots_then_new_tabs() {
+        let dir = std::env::temp_dir().join(format!("radio_conductor_folder_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav = write_test_wav("folder", 0.1);
+        for name in ["b.wav", "A.WAV", "c.wav"] {
+            std::fs::copy(&wav, dir.join(name)).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "cue sheet").unwrap();
+        let mut app = sample_interface();
+        app.current_tab = 1;
+        app.tabs[1].pages = 1;
+        app.tabs[1].buttons.resize_with(GRID_SLOTS - 1, || named_button("Filler"));
+        let result = app.import_folder(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_file(wav);
+        assert_eq!(result.unwrap(), (3, 1));
+        assert_eq!(app.tabs[1].buttons[GRID_SLOTS - 1].path, dir.join("A.WAV"));
+        let overflow = &app.tabs[2];
+        assert_eq!(overflow.name, dir.file_name().unwrap().to_string_lossy());
+        assert_eq!(overflow.buttons.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(), ["b.wav", "c.wav"]);
+        assert_eq!(app.pending_waveforms.len(), 3);
+        // One undo step takes the whole import back
+        app.undo();
+        assert_eq!(app.tabs.len(), 2);
+    }
+
+    #[test]
+    fn new_tabs_never_repeat_a_default_name() {
+        let mut app = MusicInterface::default();
+        app.add_tab();
+        app.add_tab();
+        app.add_tab();
+        let names = |app: &MusicInterface| app.tabs.iter().map(|tab| tab.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&app), ["Tab 1", "Tab 2", "Tab 3", "Tab 4"]);
+        app.remove_tab(2).unwrap();
+        app.add_tab();
+        assert_eq!(names(&app), ["Tab 1", "Tab 2", "Tab 4", "Tab 3"]);
+        app.add_tab();
+        assert_eq!(app.tabs.last().unwrap().name, "Tab 5");
+    }
+
+    #[test]
+    fn midi_note_ons_fire_their_buttons() {
+        assert_eq!(MidiListener::note_on(&[0x90, 36, 100]), Some(36));
+        assert_eq!(MidiListener::note_on(&[0x9F, 36, 1]), Some(36));
+        // Note-on at velocity 0, note-off, control change, running status
+        assert_eq!(MidiListener::note_on(&[0x90, 36, 0]), None);
+        assert_eq!(MidiListener::note_on(&[0x80, 36, 64]), None);
+        assert_eq!(MidiListener::note_on(&[0xB0, 36, 127]), None);
+        assert_eq!(MidiListener::note_on(&[36, 100]), None);
+        assert_eq!(MidiListener::note_name(60), "C4 (60)");
+        assert_eq!(MidiListener::note_name(37), "C#2 (37)");
+        let mut app = sample_interface();
+        app.tabs[1].buttons[0].playback.midi_note = Some(36);
+        // Intro (tab 1) has note 36 too; the current tab wins unless all are to fire
+        assert_eq!(app.midi_targets(36), vec![(1, 0)]);
+        app.config.hotkey_conflict = HotkeyConflict::TriggerAll;
+        app.config.polyphonic = true;
+        assert_eq!(app.midi_targets(36), vec![(0, 0), (1, 0)]);
+        assert!(app.midi_targets(37).is_empty());
+        // MIDI Learn follows the conflict policy like hotkey capture
+        app.edit_state.editing = Some(1);
+        app.config.hotkey_conflict = HotkeyConflict::Reject;
+        app.assign_learned_midi_note(36);
+        assert_eq!(app.edit_state.playback_buf.midi_note, None);
+        app.assign_learned_midi_note(37);
+        assert_eq!(app.edit_state.playback_buf.midi_note, Some(37));
+        app.config.hotkey_conflict = HotkeyConflict::PreferCurrentTab;
+        app.assign_learned_midi_note(36);
+        assert_eq!(app.edit_state.playback_buf.midi_note, Some(36));
+    }
+
+    #[test]
+    fn tab_runtime_sums_populated_buttons() {
+        let mut app = sample_interface();
+        app.tabs[0].buttons[2].duration = 30.0;
+        // Intro runs 1.25x fast; the emptied slot doesn't count
+        let (count, total) = app.tabs[0].runtime();
+        assert_eq!(count, 2);
+        assert!((total - (app.tabs[0].buttons[0].duration / 1.25 + 30.0)).abs() < 0.01);
+        assert_eq!(MusicTab { name: String::new(), buttons: Vec::new(), pages: 1, settings: TabSettings::default() }.runtime(), (0, 0.0));
+    }
+
+    #[test]
+    fn fit_to_content_tabs_show_their_buttons_and_one_free_slot() {
+        let mut app = sample_interface();
+        let tab = &mut app.tabs[0];
+        // Intro, Stinger, then the emptied slot in between as the free one
+        assert_eq!(tab.fitted_slots(), vec![0, 2, 1]);
+        tab.buttons[1] = named_button("Gap filled");
+        assert_eq!(tab.fitted_slots(), vec![0, 1, 2, 3]);
+        // Adding past the fixed pages grows them, so repair leaves the button where it was put
+        tab.buttons.resize_with(GRID_SLOTS, || named_button("Filler"));
+        assert_eq!(tab.fitted_slots().last(), Some(&GRID_SLOTS));
+        app.current_tab = 0;
+        app.clipboard = Some(named_button("Pasted"));
+        app.paste_at(GRID_SLOTS);
+        assert_eq!(app.tabs[0].pages, 2);
+        assert!(!app.validate_and_repair());
+        assert_eq!(app.tabs[0].buttons[GRID_SLOTS].name, "Pasted");
+        // Pads wrap by width and only shrink in height once the rows run out of room
+        let (cols, size) = MusicInterface::fitted_layout(Vec2::new(1000.0, 800.0), 3);
+        assert_eq!((cols, size.y), (4, FIT_PAD_SIZE.y));
+        let (_, size) = MusicInterface::fitted_layout(Vec2::new(1000.0, 800.0), 100);
+        assert_eq!(size.y, FIT_MIN_PAD_HEIGHT);
+        assert_eq!(MusicInterface::fitted_layout(Vec2::new(50.0, 800.0), 3).0, 1);
+    }
+
+    #[test]
+    fn duplicate_fills_the_first_free_slot() {
+        let mut app = sample_interface();
+        app.current_tab = 0;
+        app.config.hotkey_conflict = HotkeyConflict::TriggerAll;
+        // Slot 1 is an emptied button
+        assert_eq!(app.duplicate_button(0), Ok(1));
+        let info = TrackInfo { stats: PlayStats::default(), ..app.tabs[0].buttons[0].info.clone() };
+        assert_eq!(app.tabs[0].buttons[1], MusicButton { info, ..app.tabs[0].buttons[0].clone() });
+        assert_eq!(app.duplicate_button(2), Ok(3));
+        assert_eq!(app.tabs[0].buttons[3].name, "Stinger");
+        assert_eq!(app.duplicate_button(4), Err("No such button"));
+        app.tabs[0].buttons.resize_with(GRID_SLOTS, || named_button("Filler"));
+        assert!(app.duplicate_button(0).is_err());
+    }
+
+    #[test]
+    fn layered_cues_play_and_stop_independently() {
+        let path = write_test_wav("layers", 0.5);
+        let button = MusicButton { path: path.clone(), duration: 0.5, ..named_button("bed") };
+        let mut player = AudioPlayer::default();
+        player.play_layer((0, 0), &button).unwrap();
+        player.play_layer((0, 1), &button).unwrap();
+        assert!(player.sink.is_none() && player.voices.len() == 2);
+        assert!(player.layer_elapsed((0, 1)).is_some() && player.layer_elapsed((0, 2)).is_none());
+        // An exclusive cue leaves the layers alone
+        player.play((1, 0), &button).unwrap();
+        assert!(player.is_layered((0, 0)) && player.is_layered((0, 1)));
+        assert!(player.fade_out_layer((0, 0)));
+        assert!(!player.fade_out_layer((0, 0)));
+        assert!(!player.is_layered((0, 0)) && player.is_layered((0, 1)));
+        player.remap_keys(|(tab, idx)| (tab, idx + 1));
+        assert!(player.is_layered((0, 2)));
+        player.stop();
+        assert!(player.layers.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn buttons_stay_usable_without_an_output_device() {
+        let path = write_test_wav("no_output", 0.2);
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![MusicButton { path: path.clone(), duration: 0.2, ..named_button("jingle") }];
+        // As opened on a machine without a sound card
+        app.audio_player.stream = None;
+        app.audio_player.output_error = Some("no default output device".to_string());
+        app.trigger_button(0, 0);
+        let _ = std::fs::remove_file(path);
+        assert_eq!(app.current_playing, None);
+        assert!(app.audio_player.sink.is_none() && app.audio_player.voices.is_empty());
+        assert!(app.toasts.iter().any(|toast| toast.message.contains("No audio output")));
+        // The board itself is still there to edit
+        assert_eq!(app.tabs[0].buttons[0].name, "jingle");
+    }
+
+    #[test]
+    fn a_cue_that_plays_out_is_no_longer_current() {
+        let path = write_test_wav("track_end", 0.05);
+        let button = MusicButton { path: path.clone(), duration: 0.05, ..named_button("blip") };
+        let copy = MusicButton { path: path.clone(), duration: 0.05, ..named_button("blip") };
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![copy];
+        app.audio_player.play((0, 0), &button).unwrap();
+        app.current_playing = Some((0, 0));
+        // Queued but not pulled yet: still starting, not finished
+        app.detect_track_end();
+        assert_eq!(app.current_playing, Some((0, 0)));
+        app.audio_player.sink = Some(Arc::new(Sink::new_idle().0));
+        app.detect_track_end();
+        assert_eq!(app.current_playing, None);
+        assert!(app.audio_player.sink.is_none() && app.audio_player.start_time.is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn quickfire_maps_digits_to_the_visible_page() {
+        let mut app = sample_interface();
+        assert_eq!(app.quickfire_slot(egui::Key::Num1), None);
+        app.config.quickfire_enabled = true;
+        assert_eq!(app.quickfire_slot(egui::Key::Num1), Some(0));
+        assert_eq!(app.quickfire_slot(egui::Key::Num0), Some(9));
+        assert_eq!(app.quickfire_slot(egui::Key::A), None);
+        app.current_page = 1;
+        assert_eq!(app.quickfire_slot(egui::Key::Num3), Some(GRID_SLOTS + 2));
+        app.tabs[0].buttons[0].hotkey = Some(egui::Key::Num3);
+        assert_eq!(app.quickfire_slot(egui::Key::Num3), None);
+        // Fit-to-content tabs count the pads shown, gaps closed up
+        app.current_tab = 0;
+        app.tabs[0].settings.fit_to_content = true;
+        assert_eq!(app.quickfire_slot(egui::Key::Num2), Some(2));
+        assert_eq!(app.quickfire_slot(egui::Key::Num9), None);
+    }
+
+    #[test]
+    fn the_fade_key_yields_to_button_hotkeys() {
+        let mut app = sample_interface();
+        assert!(app.is_fade_key(egui::Key::F) && !app.is_fade_key(egui::Key::G));
+        app.tabs[1].buttons[0].hotkey = Some(egui::Key::F);
+        assert!(!app.is_fade_key(egui::Key::F));
+        app.tabs[1].buttons[0].hotkey = None;
+        app.config.fade_key_enabled = false;
+        assert!(!app.is_fade_key(egui::Key::F));
+        app.current_playing = Some((0, 0));
+        app.fade_current();
+        assert_eq!(app.current_playing, None);
+    }
+
+    #[test]
+    fn deleting_a_playing_button_clears_the_slot_and_stops_it() {
+        let mut app = sample_interface();
+        app.current_tab = 0;
+        app.current_playing = Some((0, 0));
+        app.edit_state.editing = Some(0);
+        app.delete_button(0, 0);
+        assert_eq!(app.tabs[0].buttons[0], MusicButton::default());
+        assert_eq!(app.current_playing, None);
+        assert_eq!(app.edit_state.editing, None);
+        // Slots keep their places
+        assert_eq!(app.tabs[0].buttons[2].name, "Stinger");
+    }
+
+    #[test]
+    fn removing_a_tab_keeps_references_in_range() {
+        let mut app = sample_interface();
+        app.tabs.push(MusicTab { name: "Spare".to_string(), buttons: vec![named_button("Sweeper")], pages: 1, settings: TabSettings::default() });
+        app.current_tab = 2;
+        app.current_playing = Some((2, 0));
+        app.queued_triggers = vec![(0, 0), (2, 0)];
+        app.remove_tab(0).unwrap();
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.current_tab, 1);
+        assert_eq!(app.current_playing, Some((1, 0)));
+        assert_eq!(app.queued_triggers, vec![(1, 0)]);
+        // Removing the playing tab stops it
+        app.remove_tab(1).unwrap();
+        assert_eq!(app.current_tab, 0);
+        assert_eq!(app.current_playing, None);
+        assert!(app.remove_tab(0).is_err());
+    }
+
+    #[test]
+    fn moving_a_tab_keeps_references_on_the_same_tab() {
+        let mut app = sample_interface();
+        app.tabs.push(MusicTab { name: "Spare".to_string(), buttons: vec![named_button("Sweeper")], pages: 1, settings: TabSettings::default() });
+        app.current_tab = 1;
+        app.current_playing = Some((0, 0));
+        app.queued_triggers = vec![(2, 0)];
+        app.move_tab(0, 2);
+        let names: Vec<_> = app.tabs.iter().map(|tab| tab.name.as_str()).collect();
+        assert_eq!(names, ["Beds", "Spare", "Jingles"]);
+        assert_eq!(app.current_tab, 0);
+        assert_eq!(app.current_playing, Some((2, 0)));
+        assert_eq!(app.queued_triggers, vec![(1, 0)]);
+        app.move_tab(2, 0);
+        assert_eq!(app.tabs[0].name, "Jingles");
+        assert_eq!((app.current_tab, app.current_playing), (1, Some((0, 0))));
+    }
+
+    #[test]
+    fn looping_pads_repeat_past_the_end_of_the_file() {
+        let path = write_test_wav("loop", 0.1); // 800 samples
+        let tail = |looping| {
+            let prepared = PreparedSink::open(&path, looping).unwrap();
+            prepared.output.take(2400).skip(1600).filter(|sample| *sample != 0.0).count()
+        };
+        assert!(tail(true) > 0);
+        assert_eq!(tail(false), 0);
+        let (from_offset, _) = PreparedSink::open_at(&path, None, Duration::from_millis(50), true, false).unwrap();
+        assert!(from_offset.output.take(2400).skip(1600).any(|sample| sample != 0.0));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn end_fade_ramps_the_current_voice_down() {
+        let path = write_test_wav("end_fade", 2.0);
+        let button = MusicButton { path: path.clone(), duration: 2.0, ..named_button("outro") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        player.set_end_fade(1000);
+        player.apply_end_fade();
+        assert_eq!(player.voices[0].end_gain, 1.0);
+        // Halfway through the last second
+        player.start_time = Some(Instant::now() - Duration::from_millis(1500));
+        player.apply_end_fade();
+        let gain = player.voices[0].end_gain;
+        assert!((0.4..=0.6).contains(&gain), "gain was {}", gain);
+        // A manual fade owns the sink from then on
+        player.fade_out();
+        player.start_time = Some(Instant::now() - Duration::from_millis(1900));
+        player.apply_end_fade();
+        assert_eq!(player.voices[0].end_gain, gain);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // Pretend the current run started `secs` earlier
+    fn run_for(player: &mut AudioPlayer, secs: u64) {
+        player.start_time = player.start_time.map(|start| start - Duration::from_secs(secs));
+    }
+
+    fn assert_elapsed(player: &AudioPlayer, expected: f32) {
+        let elapsed = player.elapsed();
+        assert!((elapsed - expected).abs() < 0.1, "elapsed was {}, expected {}", elapsed, expected);
+    }
+
+    #[test]
+    fn stop_all_silences_fading_cues_too() {
+        let path = write_test_wav("stop_all", 2.0);
+        let button = MusicButton { path: path.clone(), duration: 2.0, ..named_button("bed") };
+        let mut app = MusicInterface::default();
+        app.audio_player.play((0, 0), &button).unwrap();
+        let fading = app.audio_player.sink.clone().unwrap();
+        app.audio_player.fade_out();
+        app.audio_player.play((0, 1), &button).unwrap();
+        app.current_playing = Some((0, 1));
+        app.queued_triggers.push((0, 0));
+        // The fading sink is no longer current but still a voice, which is what stop() reaches
+        assert!(app.audio_player.voices.iter().any(|voice| Arc::ptr_eq(&voice.sink, &fading)));
+        app.stop_all();
+        assert_eq!((app.current_playing, app.queued_triggers.len()), (None, 0));
+        // Again with nothing left to stop
+        app.stop_all();
+        assert!(app.audio_player.sink.is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn pausing_freezes_the_clock_until_resumed() {
+        let mut player = AudioPlayer::new();
+        player.sink = Some(Arc::new(Sink::new_idle().0));
+        player.set_position(Duration::ZERO, Instant::now());
+        run_for(&mut player, 2);
+        player.pause();
+        assert!(player.is_paused() && player.sink.as_ref().unwrap().is_paused());
+        assert_elapsed(&player, 2.0);
+        // However long the pause lasts, the clock doesn't move
+        run_for(&mut player, 10);
+        assert_elapsed(&player, 2.0);
+        player.resume();
+        assert!(!player.is_paused() && !player.sink.as_ref().unwrap().is_paused());
+        assert_elapsed(&player, 2.0);
+        run_for(&mut player, 3);
+        assert_elapsed(&player, 5.0);
+        // Pausing twice or resuming twice changes nothing
+        player.pause();
+        player.pause();
+        assert_elapsed(&player, 5.0);
+        player.resume();
+        player.resume();
+        run_for(&mut player, 1);
+        assert_elapsed(&player, 6.0);
+    }
+
+    #[test]
+    fn faster_buttons_play_for_less_time() {
+        let path = write_test_wav("speed", 2.0);
+        let mut button = MusicButton { path: path.clone(), duration: 2.0, ..named_button("bed") };
+        button.playback.speed = 2.0;
+        assert_eq!(button.play_length(), 1.0);
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        assert_eq!((player.duration, player.sink.as_ref().unwrap().speed()), (1.0, 2.0));
+        // Seeking to the middle of the file is half a second into the shortened cue
+        player.seek(&button, Duration::from_secs(1)).unwrap();
+        assert_elapsed(&player, 0.5);
+        assert_eq!(player.sink.as_ref().unwrap().speed(), 2.0);
+        // Tones ignore it, and boards from before the setting play at normal speed
+        let tone = MusicButton { tone: Some(TestTone::default()), playback: button.playback, ..named_button("tone") };
+        assert_eq!(tone.speed(), 1.0);
+        let old: PlaybackSettings = serde_json::from_str(r#"{"cooldown_ms":0,"end_fade_ms":null,"random_start":false,"volume":1.0}"#).unwrap();
+        assert_eq!(old.speed, 1.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn trimmed_buttons_play_only_the_part_between_the_handles() {
+        let path = write_test_wav("trim", 1.0); // 8000 samples
+        let mut button = MusicButton { path: path.clone(), duration: 1.0, ..named_button("jingle") };
+        assert_eq!(button.trim(), None);
+        button.playback.start_offset = 0.25;
+        button.playback.end_offset = 0.25;
+        assert_eq!((button.play_length(), button.trim_span()), (0.5, (0.25, 0.75)));
+        let prepared = PreparedSink::for_button(&button, false).unwrap();
+        let heard = prepared.output.take(8000).filter(|sample| *sample != 0.0).count();
+        assert!((3900..=4100).contains(&heard), "heard {} samples", heard);
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        assert_eq!(player.duration, 0.5);
+        // Seeks count from the start handle
+        player.seek(&button, Duration::from_millis(200)).unwrap();
+        assert_elapsed(&player, 0.2);
+        // Handles can't cross, and tones ignore them
+        button.playback.start_offset = 0.9;
+        button.playback.end_offset = 0.5;
+        assert_eq!(button.trim_range(), (0.9, 1.0));
+        let tone = MusicButton { tone: Some(TestTone::default()), duration: 1.0, playback: button.playback, ..named_button("tone") };
+        assert_eq!(tone.trim(), None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn seeking_keeps_the_clock_and_the_pause() {
+        let path = write_test_wav("seek_clock", 2.0);
+        let button = MusicButton { path: path.clone(), duration: 2.0, ..named_button("long") };
+        let mut player = AudioPlayer::new();
+        player.play((0, 0), &button).unwrap();
+        player.latency_probe = None;
+        player.set_position(Duration::ZERO, Instant::now());
+        run_for(&mut player, 1);
+        player.seek(&button, Duration::from_millis(500)).unwrap();
+        assert_elapsed(&player, 0.5);
+        player.pause();
+        player.seek(&button, Duration::from_millis(1500)).unwrap();
+        assert!(player.is_paused() && player.sink.as_ref().unwrap().is_paused());
+        assert_elapsed(&player, 1.5);
+        player.resume();
+        assert_elapsed(&player, 1.5);
+        player.stop();
+        assert_elapsed(&player, 0.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn tone_pads_play_without_a_file() {
+        let tone = TestTone { kind: ToneKind::PinkNoise, level_db: -20.0, ..Default::default() };
+        let button = MusicButton { name: tone.label(), tone: Some(tone), ..Default::default() };
+        assert!((tone.gain() - 0.1).abs() < 1e-4);
+        let mut player = AudioPlayer::new();
+        player.sync_preloaded(&[MusicTab { name: "Tab 1".to_string(), buttons: vec![MusicButton { tone: Some(tone), ..named_button("tone") }], pages: 1, settings: TabSettings::default() }]);
+        assert!(player.preloaded.is_empty());
+        player.play((0, 0), &button).unwrap();
+        assert!(player.sink.as_ref().is_some_and(|sink| !sink.empty()));
+        let peak = PinkNoise::new().take(MIX_SAMPLE_RATE as usize).fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak > 0.1 && peak <= 1.0, "peak was {}", peak);
+    }
+
+    #[test]
+    fn remote_commands_parse() {
+        assert_eq!(RemoteCommand::parse("play 1 3"), Ok(RemoteCommand::Play(0, 2)));
+        assert_eq!(RemoteCommand::parse("  STOP "), Ok(RemoteCommand::Stop));
+        assert_eq!(RemoteCommand::parse("fade"), Ok(RemoteCommand::Fade));
+        assert_eq!(RemoteCommand::parse("load /shows/my show.bin"), Ok(RemoteCommand::Load(PathBuf::from("/shows/my show.bin"))));
+        assert!(RemoteCommand::parse("play 0 1").is_err());
+        assert!(RemoteCommand::parse("play 1").is_err());
+        assert!(RemoteCommand::parse("load").is_err());
+        assert!(RemoteCommand::parse("dance").is_err());
+    }
+
+    #[test]
+    fn ipc_server_forwards_commands() {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = probe.local_addr().unwrap().to_string();
+        drop(probe);
+        let server = IpcServer::start(&address).unwrap();
+        let mut client = TcpStream::connect(&address).unwrap();
+        writeln!(client, "play 2 5").unwrap();
+        writeln!(client, "nonsense").unwrap();
+        let mut replies = BufReader::new(client.try_clone().unwrap()).lines();
+        assert_eq!(replies.next().unwrap().unwrap(), "OK");
+        assert!(replies.next().unwrap().unwrap().starts_with("ERR"));
+        assert_eq!(server.commands.recv_timeout(Duration::from_secs(1)), Ok(RemoteCommand::Play(1, 4)));
+    }
+
+    #[test]
+    fn web_remote_serves_the_board_and_takes_commands() {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = probe.local_addr().unwrap().to_string();
+        drop(probe);
+        let remote = WebRemote::start(&address).unwrap();
+        let app = sample_interface();
+        *remote.state.lock().unwrap() = WebRemote::state_json(&app.tabs, |key| key == (1, 0));
+        let mut client = TcpStream::connect(&address).unwrap();
+        write!(client, "GET /state HTTP/1.1\r\nHost: {}\r\n\r\n", address).unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut client, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let state: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        // The emptied slot is left out; slots keep their UI numbers
+        assert_eq!(state["tabs"][0]["buttons"][1]["slot"], 3);
+        assert_eq!(state["tabs"][0]["buttons"][1]["name"], "Stinger");
+        assert_eq!(state["tabs"][1]["buttons"][0]["playing"], true);
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/ws", address), TcpStream::connect(&address).unwrap()).unwrap();
+        for (line, ok) in [("play 1 3", true), ("load /etc/passwd", false)] {
+            socket.send(tungstenite::Message::Text(line.to_string())).unwrap();
+            let reply = socket.read().unwrap().into_text().unwrap();
+            assert_eq!(reply == "OK", ok, "{}", reply);
+        }
+        assert_eq!(remote.commands.recv_timeout(Duration::from_secs(1)), Ok(RemoteCommand::Play(0, 2)));
+        assert!(remote.commands.try_recv().is_err());
+        // A page served from elsewhere can't open the socket
+        use tungstenite::client::IntoClientRequest;
+        let mut request = format!("ws://{}/ws", address).into_client_request().unwrap();
+        request.headers_mut().insert("Origin", "http://radio.example".parse().unwrap());
+        assert!(tungstenite::client(request, TcpStream::connect(&address).unwrap()).is_err());
+        assert!(WebRemote::origin_allowed(Some("http://192.168.1.20:8080"), Some("192.168.1.20:8080")));
+        assert!(!WebRemote::origin_allowed(Some("null"), Some("192.168.1.20:8080")));
+        assert!(!WebRemote::origin_allowed(Some("http://192.168.1.20:8080"), None));
+        assert_eq!(WebRemote::header("GET /ws HTTP/1.1\r\nHOST: 10.0.0.2:8080\r\n\r\n", "host"), Some("10.0.0.2:8080"));
+        // Bytes that aren't UTF-8 take up more room once decoded; only the raw ones are drained
+        let mut client = TcpStream::connect(&address).unwrap();
+        let sent = Instant::now();
+        client.write_all(b"GET /\xff\xfe HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut client, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+        assert!(sent.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn web_remote_serves_the_now_playing_overlay() {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = probe.local_addr().unwrap().to_string();
+        drop(probe);
+        let remote = WebRemote::start(&address).unwrap();
+        let get = |path: &str| {
+            let mut client = TcpStream::connect(&address).unwrap();
+            write!(client, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, address).unwrap();
+            let mut response = String::new();
+            std::io::Read::read_to_string(&mut client, &mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+            response.split("\r\n\r\n").nth(1).unwrap().to_string()
+        };
+        assert!(get("/overlay").contains("/now-playing"));
+        let idle: serde_json::Value = serde_json::from_str(&get("/now-playing")).unwrap();
+        assert_eq!(idle["playing"], false);
+        // A 60 s file trimmed to 40 s and played at double speed lasts 20 s on air
+        let mut button = MusicButton { duration: 60.0, ..named_button("Morning Show") };
+        button.playback.start_offset = 20.0;
+        button.playback.speed = 2.0;
+        *remote.now_playing.lock().unwrap() = WebRemote::now_playing_json(Some((&button, 5.0, false)));
+        let cue: serde_json::Value = serde_json::from_str(&get("/now-playing")).unwrap();
+        assert_eq!((cue["playing"].as_bool(), cue["name"].as_str()), (Some(true), Some("Morning Show")));
+        assert_eq!((cue["duration"].as_f64(), cue["elapsed"].as_f64()), (Some(20.0), Some(5.0)));
+        assert_eq!((cue["paused"].as_bool(), cue["looping"].as_bool()), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn repair_trims_trailing_empty_slots() {
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![named_button("a"), MusicButton::default(), MusicButton::default()];
+        app.validate_and_repair();
+        assert_eq!(app.tabs[0].buttons.len(), 1);
+    }
+
+    #[test]
+    fn repair_moves_buttons_beyond_the_grid_into_free_slots() {
+        let mut app = MusicInterface::default();
+        let mut buttons: Vec<MusicButton> = (0..GRID_SLOTS).map(|i| named_button(&i.to_string())).collect();
+        buttons[3] = MusicButton::default();
+        buttons.push(named_button("stranded"));
+        app.tabs[0].buttons = buttons;
+        assert!(app.validate_and_repair());
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.tabs[0].buttons.len(), GRID_SLOTS);
+        assert_eq!(app.tabs[0].buttons[3].name, "stranded");
+    }
+
+    #[test]
+    fn repair_spills_overflow_into_a_new_tab_when_the_grid_is_full() {
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = (0..GRID_SLOTS + 2).map(|i| named_button(&i.to_string())).collect();
+        app.validate_and_repair();
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.tabs[0].buttons.len(), GRID_SLOTS);
+        assert_eq!(app.tabs[1].buttons.len(), 2);
+        assert_eq!(app.tabs[1].name, "Tab 1 (overflow)");
+    }
+
+    #[test]
+    fn repair_clears_stale_indices() {
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![named_button("a"), MusicButton::default()];
+        app.current_tab = 4;
+        app.current_playing = Some((0, 1));
+        app.edit_state.editing = Some(7);
+        app.renaming_tab = Some(2);
+        assert!(app.validate_and_repair());
+        assert_eq!(app.current_tab, 0);
+        assert_eq!(app.current_playing, None);
+        assert_eq!(app.edit_state.editing, None);
+        assert_eq!(app.renaming_tab, None);
+    }
+
+    #[test]
+    fn repair_resets_start_latencies_a_duration_cant_hold() {
+        let mut app = MusicInterface::default();
+        let latencies = [-5.0, f32::NAN, f32::INFINITY, 12.5];
+        app.tabs[0].buttons = latencies.iter().map(|&ms| MusicButton { start_latency_ms: ms, ..named_button("a") }).collect();
+        assert!(app.validate_and_repair());
+        let repaired: Vec<f32> = app.tabs[0].buttons.iter().map(|button| button.start_latency_ms).collect();
+        assert_eq!(repaired, [0.0, 0.0, 0.0, 12.5]);
+    }
+
+    #[test]
+    fn repair_keeps_a_valid_board_untouched() {
+        let mut app = MusicInterface::default();
+        app.tabs[0].buttons = vec![named_button("a"), named_button("b")];
+        app.current_playing = Some((0, 1));
+        assert!(!app.validate_and_repair());
+        assert_eq!(app.current_playing, Some((0, 1)));
+    }
+
+    #[test]
+    fn repair_recreates_a_tab_when_none_exist() {
+        let mut app = MusicInterface::default();
+        app.tabs.clear();
+        assert!(app.validate_and_repair());
+        assert_eq!(app.tabs.len(), 1);
+    }
+
+    #[test]
+    fn recent_files_keep_the_newest_first_without_repeats() {
+        let mut config = AppConfig::default();
+        assert!(config.startup_board == StartupBoard::LastFile);
+        for n in 0..RECENT_FILES + 2 {
+            config.push_recent(Path::new(&format!("/shows/{}.bin", n)));
+        }
+        config.push_recent(Path::new("/shows/5.bin"));
+        assert_eq!(config.recent_files.len(), RECENT_FILES);
+        assert_eq!(config.recent_files[0], Path::new("/shows/5.bin"));
+        assert_eq!(config.recent_files[1], Path::new("/shows/9.bin"));
+        assert_eq!(config.recent_files.iter().filter(|path| path.ends_with("5.bin")).count(), 1);
+        assert!(!config.recent_files.iter().any(|path| path.ends_with("1.bin")));
+    }
+
+    #[test]
+    fn unsaved_changes_are_tracked_against_the_last_board_file() {
+        let mut app = sample_interface();
+        app.mark_saved();
+        assert!(!app.has_unsaved_changes());
+        app.tabs[0].buttons[0].name = "Renamed".to_string();
+        assert!(app.has_unsaved_changes());
+        // Putting it back is as good as saving
+        app.tabs[0].buttons[0].name = "Intro".to_string();
+        assert!(!app.has_unsaved_changes());
+        app.tabs[1].settings.playlist_mode = false;
+        assert!(app.has_unsaved_changes());
+        app.mark_saved();
+        assert!(!app.has_unsaved_changes());
+        // Saving on exit only writes back over .bin boards
+        assert_eq!(app.save_back_path(), None);
+        app.config.last_file = Some(PathBuf::from("/shows/morning.BIN"));
+        assert_eq!(app.save_back_path(), Some(PathBuf::from("/shows/morning.BIN")));
+        app.config.last_file = Some(PathBuf::from("/shows/unpacked/board.json"));
+        assert_eq!(app.save_back_path(), None);
+    }
+
+    #[test]
+    fn pad_names_scale_with_the_pad_within_bounds() {
+        let mut config = AppConfig::default();
+        assert!((config.name_font_size(Vec2::new(250.0, 150.0)) - 22.0).abs() < 0.1);
+        assert_eq!(config.name_font_size(Vec2::new(60.0, 40.0)), 12.0);
+        assert_eq!(config.name_font_size(Vec2::new(1200.0, 800.0)), 32.0);
+        // Narrow pads go by their width
+        assert_eq!(config.name_font_size(Vec2::new(150.0, 300.0)), 15.0);
+        // A maximum below the minimum gives way to it
+        (config.name_font_min, config.name_font_max) = (40.0, 20.0);
+        assert_eq!(config.name_font_size(Vec2::new(250.0, 150.0)), 40.0);
+        config.ui_scale = f32::NAN;
+        assert_eq!(config.ui_scale(), 1.0);
+    }
+
+    #[test]
+    fn fade_curves_hit_their_midpoints() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        for curve in [FadeCurve::Linear, FadeCurve::Log, FadeCurve::EqualPower] {
+            assert_eq!((curve.gain(0.0), curve.gain(1.0)), (0.0, 1.0), "{:?}", curve);
+            assert_eq!(curve.gain(-1.0), 0.0);
+            assert!(curve.gain(0.25) < curve.gain(0.75), "{:?}", curve);
+        }
+        assert!(close(FadeCurve::Linear.gain(0.5), 0.5));
+        // Halfway in dB: -20 dB over the 40 dB range
+        assert!(close(FadeCurve::Log.gain(0.5), 0.1));
+        // -3 dB, so the outgoing and incoming cue of a crossfade add up to full power
+        let half = FadeCurve::EqualPower.gain(0.5);
+        assert!(close(half, std::f32::consts::FRAC_1_SQRT_2));
+        let (out, inc) = (FadeCurve::EqualPower.gain(1.0 - 0.3), FadeCurve::EqualPower.gain(0.3));
+        assert!(close(out * out + inc * inc, 1.0));
+    }
+
+    #[test]
+    fn names_keep_clear_of_the_time_label() {
+        let pad = egui::Rect::from_min_size(Pos2::ZERO, Vec2::new(200.0, 120.0));
+        let (area, rows) = MusicInterface::name_area(pad, 20.0, 14.0);
+        assert_eq!(rows, 2);
+        assert!(area.bottom() <= pad.bottom() - 10.0 - 14.0);
+        assert_eq!(area.center(), pad.center());
+        // A short pad only has room for one row between the bands
+        let (area, rows) = MusicInterface::name_area(pad.with_max_y(70.0), 20.0, 14.0);
+        assert_eq!(rows, 1);
+        assert!(area.bottom() <= 70.0 - 10.0 - 14.0);
+        // One too small for even that still gets its name, on one row across the middle
+        let (area, rows) = MusicInterface::name_area(pad.with_max_y(30.0), 20.0, 14.0);
+        assert_eq!((rows, area.center()), (1, Pos2::new(100.0, 15.0)));
+    }
+
+    #[test]
+    fn wide_windows_keep_pads_to_the_aspect_limit() {
+        let mut config = AppConfig::default();
+        let ultrawide = MusicInterface::grid_button_size(Vec2::new(3440.0, 900.0));
+        assert_eq!(MusicInterface::limit_pad_aspect(ultrawide, config.max_pad_aspect()), ultrawide);
+        config.max_pad_aspect = 2.0;
+        let limited = MusicInterface::limit_pad_aspect(ultrawide, config.max_pad_aspect());
+        assert_eq!(limited, Vec2::new(ultrawide.y * 2.0, ultrawide.y));
+        // Pads already narrower than the limit are left as they are
+        let square = MusicInterface::grid_button_size(Vec2::new(1000.0, 900.0));
+        assert_eq!(MusicInterface::limit_pad_aspect(square, config.max_pad_aspect()), square);
+        // Hand-edited values are held to the slider's range
+        config.max_pad_aspect = 0.2;
+        assert_eq!(config.max_pad_aspect(), Some(1.0));
+        config.max_pad_aspect = f32::NAN;
+        assert_eq!(config.max_pad_aspect(), None);
+    }
+
+    #[test]
+    fn play_stats_count_plays_and_reset_per_tab() {
+        let mut stats = PlayStats::default();
+        assert_eq!(stats.summary(SystemTime::now()), None);
+        stats.record_play();
+        let first = stats.last_played.unwrap();
+        assert_eq!(stats.summary(first).as_deref(), Some("Played once, last just now"));
+        stats.record_play();
+        assert_eq!(stats.play_count, 2);
+        assert_eq!(stats.summary(first + Duration::from_secs(2 * 3600 + 59)).as_deref(), Some("Played 2 times, last 2 h ago"));
+        // Reset is only for the tab asked for
+        let mut app = sample_interface();
+        app.mark_saved();
+        app.tabs[1].buttons[0].info.stats = stats;
+        assert_eq!(app.reset_play_stats(0), 1);
+        assert!(app.tabs[0].buttons.iter().all(|button| button.info.stats == PlayStats::default()));
+        assert_eq!(app.tabs[1].buttons[0].info.stats, stats);
+        assert_eq!(app.reset_play_stats(0), 0);
+        // Plays and resets are no edits: nothing to save and nothing to undo
+        assert!(!app.has_unsaved_changes());
+        assert!(app.history.undo.is_empty());
+        // An undone edit keeps the plays made since
+        app.record_undo("rename");
+        app.tabs[0].buttons[0].name = "Renamed".to_string();
+        app.tabs[0].buttons[0].info.stats.record_play();
+        app.undo();
+        assert_eq!(app.tabs[0].buttons[0].name, "Intro");
+        assert_eq!(app.tabs[0].buttons[0].info.stats.play_count, 1);
+    }
+}